@@ -0,0 +1,17 @@
+-- Install history for rollback to previously installed versions
+-- Each successful install retains its archive in the app data dir (archive_path)
+CREATE TABLE IF NOT EXISTS install_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    slug TEXT NOT NULL,
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    source_type TEXT NOT NULL,       -- 'index' | 'github' | 'local'
+    source_repo TEXT,
+    version_sort_key INTEGER,
+    commit_sha TEXT,
+    install_info TEXT,               -- InstallInfo JSON (index addons only)
+    archive_path TEXT NOT NULL,      -- Retained archive in the app data dir
+    installed_at TEXT NOT NULL       -- ISO 8601 timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_install_history_slug ON install_history(slug);
@@ -0,0 +1,3 @@
+-- Store the Last-Modified validator alongside the ETag for conditional index requests
+
+ALTER TABLE index_cache ADD COLUMN last_modified TEXT;
@@ -0,0 +1,19 @@
+-- Configurable addon index sources, merged into one catalog by priority
+-- index_cache rows are keyed by index_sources.id
+CREATE TABLE IF NOT EXISTS index_sources (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    url TEXT UNIQUE NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0, -- Lower value wins slug conflicts
+    added_at TEXT NOT NULL
+);
+
+-- Seed the community index as source 1 so an existing index_cache row (id = 1) stays valid
+INSERT INTO index_sources (id, name, url, priority, added_at)
+SELECT 1,
+       'ESO Addon Index',
+       COALESCE((SELECT value FROM settings WHERE key = 'index_url'),
+                'https://xop.co/eso-addon-index/index.json'),
+       0,
+       strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+WHERE NOT EXISTS (SELECT 1 FROM index_sources);
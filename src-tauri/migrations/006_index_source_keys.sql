@@ -0,0 +1,4 @@
+-- Optional minisign public key per index source; when set, index.json must carry a
+-- valid detached signature (index.json.minisig) before it is cached
+
+ALTER TABLE index_sources ADD COLUMN public_key TEXT;
@@ -0,0 +1,4 @@
+-- Addon folders installed from the same archive as another addon (bundled libraries,
+-- suite modules) are tracked as linked rows pointing at the main addon's slug
+
+ALTER TABLE installed_addons ADD COLUMN parent_slug TEXT;
@@ -0,0 +1,12 @@
+-- File inventory of each installed addon, recorded right after install
+-- Paths are relative to the addon folder and use '/' separators
+CREATE TABLE IF NOT EXISTS addon_files (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    slug TEXT NOT NULL,              -- installed_addons.slug
+    path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    UNIQUE(slug, path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_addon_files_slug ON addon_files(slug);
@@ -0,0 +1,6 @@
+-- Download queue: each job stores the full install request (JSON) so pending and
+-- interrupted jobs can be resumed after a restart
+
+ALTER TABLE downloads ADD COLUMN addon_name TEXT;
+ALTER TABLE downloads ADD COLUMN request TEXT;
+ALTER TABLE downloads ADD COLUMN created_at TEXT;
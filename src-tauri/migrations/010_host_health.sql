@@ -0,0 +1,11 @@
+-- Reliability and throughput of each download host, used to try healthy
+-- sources first and to skip hosts that failed recently
+CREATE TABLE IF NOT EXISTS host_health (
+    host TEXT PRIMARY KEY,           -- host name, with the port if not the default
+    successes INTEGER NOT NULL DEFAULT 0,
+    failures INTEGER NOT NULL DEFAULT 0,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    bytes_per_second REAL,           -- smoothed throughput of successful downloads
+    last_success_at TEXT,
+    last_failure_at TEXT
+);
@@ -1,27 +1,28 @@
+use super::github::archive_pin;
+use super::queue::{enqueue_install, ProgressRecorder};
+use crate::error::AppError;
 use crate::models::{
-    index::DownloadSource, DownloadProgress, DownloadStatus, InstallInfo, InstalledAddon,
-    SourceType, UpdateInfo,
+    index::DownloadSource, DownloadPhase, DownloadProgress, DownloadStatus, InstallHistoryEntry,
+    InstallInfo, InstalledAddon, SourceType, TransferProgress, UpdateInfo, VerifyResult,
+    VersionTracking,
+};
+use crate::services::queue::Operation;
+use crate::services::{
+    cache, catalog, database, downloader, graphql, history, installer, inventory, linked, scanner,
+    AddonInventory, DownloadControl, DownloadPriority, GitHubClient, RepoStatus, SourceHealth,
+    TrackedRepo,
 };
-use crate::services::{database, downloader, installer, scanner};
 use crate::state::AppState;
-use crate::utils::paths::get_eso_addon_path_with_custom;
+use crate::utils::checksum::ExpectedIntegrity;
+use crate::utils::paths::{get_eso_addon_path_with_custom, get_history_path, get_patches_path};
 use crate::utils::version::is_update_available;
-use std::path::PathBuf;
-use tauri::{Emitter, State, Window};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, State};
 use tempfile::NamedTempFile;
 
-/// Version tracking info passed from frontend for simplified update detection
-#[derive(Debug, Clone, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct VersionTracking {
-    /// Pre-computed sort key from index for direct integer comparison
-    pub version_sort_key: Option<i64>,
-    /// Commit SHA for branch-based version tracking
-    pub commit_sha: Option<String>,
-}
-
 /// Helper to get the ESO addon path, checking database for custom path first
-fn get_addon_path_from_state(state: &State<'_, AppState>) -> Result<PathBuf, String> {
+fn get_addon_path_from_state(state: &AppState) -> Result<PathBuf, String> {
     let conn = state.db.lock().map_err(|e| e.to_string())?;
     let custom_path = database::get_setting(&conn, "eso_addon_path")
         .ok()
@@ -141,21 +142,127 @@ pub async fn get_installed_addons(
     Ok(valid_addons)
 }
 
+/// Folders owned by other managed addons, which a bundled copy must not overwrite
+fn get_foreign_folders(state: &AppState, slug: &str) -> Vec<String> {
+    state
+        .db
+        .lock()
+        .ok()
+        .and_then(|conn| linked::foreign_folders(&conn, slug).ok())
+        .unwrap_or_default()
+}
+
+/// Load the file inventories of an addon and its linked addons
+/// The database is only held while reading them, not while the files are hashed.
+fn load_inventories(state: &AppState, slug: &str) -> Result<Vec<AddonInventory>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    inventory::load_inventories(&conn, slug).map_err(|e| e.to_string())
+}
+
+/// Files of an addon and its linked addons that an update would lose
+async fn local_changes(state: &AppState, slug: &str) -> Result<Vec<String>, String> {
+    let inventories = load_inventories(state, slug)?;
+    tokio::task::spawn_blocking(move || inventory::changed_files(&inventories))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Back up an addon's locally changed files, returning the backup directory if any
+async fn backup_changes(state: &AppState, slug: &str) -> Result<Option<PathBuf>, String> {
+    let patches_dir =
+        get_patches_path().ok_or_else(|| "Could not determine patches directory".to_string())?;
+    let inventories = load_inventories(state, slug)?;
+    let slug = slug.to_string();
+    tokio::task::spawn_blocking(move || {
+        inventory::backup_local_changes(&inventories, &slug, &patches_dir)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
 /// Helper to emit a failed status with error message
-fn emit_install_error(window: &Window, slug: &str, error: &str) {
-    let _ = window.emit(
+fn emit_install_error(app: &AppHandle, op: &Operation, slug: &str, error: &str) {
+    let _ = app.emit(
         "download-progress",
         DownloadProgress {
             slug: slug.to_string(),
             status: DownloadStatus::Failed,
             progress: 0.0,
             error: Some(error.to_string()),
+            operation_id: Some(op.id),
+            ..Default::default()
         },
     );
 }
 
+/// Report a cancelled install, returning the error for the caller
+fn install_cancelled(app: &AppHandle, op: &Operation, slug: &str) -> String {
+    let _ = app.emit(
+        "download-progress",
+        DownloadProgress {
+            slug: slug.to_string(),
+            status: DownloadStatus::Cancelled,
+            progress: 0.0,
+            error: None,
+            operation_id: Some(op.id),
+            ..Default::default()
+        },
+    );
+    AppError::Cancelled.to_string()
+}
+
+/// Look up the expected checksum and size of an index release in the cached catalog
+fn get_release_integrity(state: &AppState, slug: &str, version: &str) -> Option<ExpectedIntegrity> {
+    let conn = state.db.lock().ok()?;
+    let index = catalog::load_cached_catalog(&conn).ok()??;
+
+    index
+        .addons
+        .iter()
+        .find(|a| a.slug == slug)?
+        .latest_release
+        .as_ref()
+        .filter(|release| release.version == version)
+        .map(ExpectedIntegrity::from_release)
+        .filter(|integrity| !integrity.is_empty())
+}
+
+/// Everything needed to install or update an addon, stored with each queued download
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallRequest {
+    pub slug: String,
+    pub name: String,
+    pub version: String,
+    pub download_url: String,
+    pub source_type: Option<String>,
+    pub source_repo: Option<String>,
+    pub install_info: Option<InstallInfo>,
+    pub version_tracking: Option<VersionTracking>,
+    pub download_sources: Option<Vec<DownloadSource>>,
+    #[serde(default)]
+    pub overwrite_local_changes: bool,
+    #[serde(default)]
+    pub backup_local_changes: bool,
+    /// Background downloads (auto and bulk updates) use their own bandwidth limits
+    #[serde(default)]
+    pub priority: DownloadPriority,
+    /// Install this retained archive instead of downloading, for rollbacks
+    #[serde(default)]
+    pub archive_path: Option<PathBuf>,
+}
+
 /// Install an addon from a download URL with optional install info from the index
 /// Supports multiple download sources with fallback (jsDelivr CDN -> GitHub archive)
+/// Updating an addon whose installed files were edited locally fails unless
+/// `overwrite_local_changes` is set, so the user can be warned first; with
+/// `backup_local_changes` the changed files are copied to the patches directory
+/// and the update proceeds.
+/// Automatic and bulk updates should pass the background `priority`, so their
+/// downloads are held to the background bandwidth limits.
+/// The install runs through the download queue and resolves once it finishes.
 #[tauri::command]
 #[allow(clippy::too_many_arguments)]
 pub async fn install_addon(
@@ -168,18 +275,92 @@ pub async fn install_addon(
     install_info: Option<InstallInfo>,
     version_tracking: Option<VersionTracking>,
     download_sources: Option<Vec<DownloadSource>>,
+    overwrite_local_changes: Option<bool>,
+    backup_local_changes: Option<bool>,
+    priority: Option<DownloadPriority>,
     state: State<'_, AppState>,
-    window: Window,
+    app: AppHandle,
 ) -> Result<InstalledAddon, String> {
+    let request = InstallRequest {
+        slug,
+        name,
+        version,
+        download_url,
+        source_type,
+        source_repo,
+        install_info,
+        version_tracking,
+        download_sources,
+        overwrite_local_changes: overwrite_local_changes.unwrap_or(false),
+        backup_local_changes: backup_local_changes.unwrap_or(false),
+        priority: priority.unwrap_or_default(),
+        archive_path: None,
+    };
+
+    let (_, result) = enqueue_install(&state, &app, request)?;
+    result
+        .await
+        .map_err(|_| "Download was interrupted".to_string())?
+}
+
+/// Download and install an addon, emitting `download-progress` events along the way
+/// The operation can be cancelled until extraction starts; the temp file is removed
+/// either way and a partially downloaded archive is discarded.
+pub(crate) async fn perform_install(
+    state: &AppState,
+    app: &AppHandle,
+    op: &Operation,
+    request: &InstallRequest,
+) -> Result<InstalledAddon, String> {
+    let InstallRequest {
+        slug,
+        name,
+        version,
+        source_type,
+        source_repo,
+        install_info,
+        version_tracking,
+        overwrite_local_changes,
+        backup_local_changes,
+        archive_path,
+        ..
+    } = request;
+
+    // Refuse to silently overwrite edits to the installed version's files
+    if *backup_local_changes {
+        if let Err(e) = backup_changes(state, slug).await {
+            let error = format!("Failed to back up local changes: {}", e);
+            emit_install_error(app, op, slug, &error);
+            return Err(error);
+        }
+    } else if !overwrite_local_changes {
+        let modified = match local_changes(state, slug).await {
+            Ok(modified) => modified,
+            Err(e) => {
+                let error = format!("Failed to check for local changes: {}", e);
+                emit_install_error(app, op, slug, &error);
+                return Err(error);
+            }
+        };
+        if !modified.is_empty() {
+            let error = format!(
+                "Local changes would be overwritten: {}",
+                modified.join(", ")
+            );
+            emit_install_error(app, op, slug, &error);
+            return Err(error);
+        }
+    }
+
     // Emit initial progress
-    let _ = window.emit(
+    let _ = app.emit(
         "download-progress",
-        DownloadProgress {
-            slug: slug.clone(),
-            status: DownloadStatus::Downloading,
-            progress: 0.0,
-            error: None,
-        },
+        DownloadProgress::phase(
+            slug,
+            Some(op.id),
+            DownloadStatus::Downloading,
+            DownloadPhase::Resolving,
+        ),
     );
 
     // Create temp file for download
@@ -187,90 +368,117 @@ pub async fn install_addon(
         Ok(f) => f,
         Err(e) => {
             let error = format!("Failed to create temp file: {}", e);
-            emit_install_error(&window, &slug, &error);
+            emit_install_error(app, op, slug, &error);
             return Err(error);
         }
     };
     let temp_path = temp_file.path().to_path_buf();
 
-    // Download the addon - use multi-source fallback if available
-    let window_clone = window.clone();
+    // Download the addon
+    let app_clone = app.clone();
     let slug_clone = slug.clone();
-    let progress_callback = move |progress: f64| {
-        let _ = window_clone.emit(
+    let operation_id = op.id;
+    let recorder = ProgressRecorder::new(app.clone(), op.id);
+    let progress_callback = move |transfer: &TransferProgress| {
+        recorder.record(transfer);
+        let _ = app_clone.emit(
             "download-progress",
-            DownloadProgress {
-                slug: slug_clone.clone(),
-                status: DownloadStatus::Downloading,
-                progress,
-                error: None,
-            },
+            DownloadProgress::transfer(&slug_clone, Some(operation_id), transfer),
         );
     };
 
-    let download_result = if let Some(ref sources) = download_sources {
-        if !sources.is_empty() {
-            // Use multi-source fallback (prefers github_archive sources)
-            downloader::download_with_fallback(
-                sources,
-                Some(&download_url),
+    // Index releases carry a checksum/size that the download must match
+    let source = source_type
+        .as_deref()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(SourceType::Index);
+    let integrity = if source == SourceType::Index {
+        get_release_integrity(state, slug, version)
+    } else {
+        None
+    };
+
+    // Rollbacks reinstall a retained archive; everything else is downloaded
+    let fetched = match archive_path {
+        Some(archive) => tokio::fs::copy(archive, &temp_path)
+            .await
+            .map(|_| ())
+            .map_err(AppError::from),
+        None => {
+            download_archive(
+                state,
+                op,
+                request,
+                &source,
+                integrity.as_ref(),
                 &temp_path,
                 progress_callback,
             )
             .await
-        } else {
-            // Empty sources array, use single URL
-            downloader::download_file(&download_url, &temp_path, progress_callback).await
         }
-    } else {
-        // No sources provided, use single URL (backward compatibility)
-        downloader::download_file(&download_url, &temp_path, progress_callback).await
     };
 
-    if let Err(e) = download_result {
-        let error = format!("Download failed: {}", e);
-        emit_install_error(&window, &slug, &error);
-        return Err(error);
+    match fetched {
+        Ok(()) => {}
+        Err(AppError::Cancelled) => return Err(install_cancelled(app, op, slug)),
+        Err(e) => {
+            let error = format!("Download failed: {}", e);
+            emit_install_error(app, op, slug, &error);
+            return Err(error);
+        }
+    }
+
+    // Last chance to cancel: once extraction starts the install runs to completion
+    if op.is_cancelled() {
+        return Err(install_cancelled(app, op, slug));
     }
 
-    // Emit extracting status
-    let _ = window.emit(
+    // Emit extracting status, and save it so the job shows where it stopped
+    if let Ok(conn) = state.db.lock() {
+        let _ = database::set_download_status(&conn, op.id, DownloadStatus::Extracting, 1.0, None);
+    }
+    let _ = app.emit(
         "download-progress",
-        DownloadProgress {
-            slug: slug.clone(),
-            status: DownloadStatus::Extracting,
-            progress: 0.0,
-            error: None,
-        },
+        DownloadProgress::phase(
+            slug,
+            Some(op.id),
+            DownloadStatus::Extracting,
+            DownloadPhase::Extracting,
+        ),
     );
 
     // Get ESO addon directory (checks custom path from database first)
-    let addon_dir = match get_addon_path_from_state(&state) {
+    let addon_dir = match get_addon_path_from_state(state) {
         Ok(dir) => dir,
         Err(e) => {
-            emit_install_error(&window, &slug, &e);
+            emit_install_error(app, op, slug, &e);
             return Err(e);
         }
     };
 
-    // Install the addon using install_info if provided (index addons), otherwise fallback to auto-detection
-    let installed_path = if let Some(ref info) = install_info {
-        match installer::install_from_archive_with_info(&temp_path, &addon_dir, info) {
-            Ok(path) => path,
-            Err(e) => {
-                let error = format!("Extraction failed: {} (target: {})", e, info.target_folder);
-                emit_install_error(&window, &slug, &error);
-                return Err(error);
-            }
-        }
-    } else {
-        match installer::install_from_archive(&temp_path, &addon_dir) {
-            Ok(path) => path,
-            Err(e) => {
-                let error = format!("Extraction failed: {}", e);
-                emit_install_error(&window, &slug, &error);
-                return Err(error);
-            }
+    // Install the addon using install_info if provided (index addons), otherwise fallback
+    // to auto-detection, which installs every addon in the archive except bundled
+    // copies of addons that are managed separately
+    let skip_folders = get_foreign_folders(state, slug);
+    let archive = temp_path.clone();
+    let info = install_info.clone();
+    let owner = slug.clone();
+    let extracted = tokio::task::spawn_blocking(move || match info {
+        Some(info) => installer::install_from_archive_with_info(&archive, &addon_dir, &info)
+            .map(|path| (path, Vec::new()))
+            .map_err(|e| format!("Extraction failed: {} (target: {})", e, info.target_folder)),
+        None => installer::install_from_archive(&archive, &addon_dir, Some(&owner), &skip_folders)
+            .map(|install| (install.primary, install.linked))
+            .map_err(|e| format!("Extraction failed: {}", e)),
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("Extraction failed: {}", e)));
+
+    let (installed_path, linked_paths) = match extracted {
+        Ok(paths) => paths,
+        Err(error) => {
+            emit_install_error(app, op, slug, &error);
+            return Err(error);
         }
     };
 
@@ -282,35 +490,52 @@ pub async fn install_addon(
                 "Could not find addon manifest after extraction. Check that '{}' contains a valid ESO addon.",
                 installed_path.display()
             );
-            emit_install_error(&window, &slug, &error);
+            emit_install_error(app, op, slug, &error);
             return Err(error);
         }
     };
 
-    // Update database
-    let source = source_type
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(SourceType::Index);
+    let _ = app.emit(
+        "download-progress",
+        DownloadProgress::phase(
+            slug,
+            Some(op.id),
+            DownloadStatus::Extracting,
+            DownloadPhase::Installing,
+        ),
+    );
+
+    // Custom repositories are named after the addon folder their archive installs
+    let name = if source == SourceType::Github {
+        installed_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(name)
+    } else {
+        name.as_str()
+    };
 
+    // Update database
     // Extract version tracking info
     let (version_sort_key, commit_sha) = version_tracking
-        .map(|vt| (vt.version_sort_key, vt.commit_sha))
+        .as_ref()
+        .map(|vt| (vt.version_sort_key, vt.commit_sha.clone()))
         .unwrap_or((None, None));
 
     let conn = match state.db.lock() {
         Ok(c) => c,
         Err(e) => {
             let error = format!("Database lock failed: {}", e);
-            emit_install_error(&window, &slug, &error);
+            emit_install_error(app, op, slug, &error);
             return Err(error);
         }
     };
 
     let addon = match database::insert_installed(
         &conn,
-        &slug,
-        &name,
-        &version,
+        slug,
+        name,
+        version,
         source,
         source_repo.as_deref(),
         manifest_path.to_string_lossy().as_ref(),
@@ -320,48 +545,276 @@ pub async fn install_addon(
         Ok(a) => a,
         Err(e) => {
             let error = format!("Failed to save addon to database: {}", e);
-            emit_install_error(&window, &slug, &error);
+            emit_install_error(app, op, slug, &error);
             return Err(error);
         }
     };
 
+    // Track bundled addon folders so they are updated and uninstalled with this addon
+    let linked_addons = match linked::record_linked(&conn, &addon, &linked_paths) {
+        Ok(l) => l,
+        Err(e) => {
+            let error = format!("Failed to save bundled addons to database: {}", e);
+            emit_install_error(app, op, slug, &error);
+            return Err(error);
+        }
+    };
+
+    // Record the installed files so local edits can be detected later (best effort)
+    for installed in std::iter::once(&addon).chain(&linked_addons) {
+        let _ = inventory::record_inventory(&conn, installed);
+    }
+
+    // Retain the archive so this version can be rolled back to later (best effort)
+    if let Some(history_dir) = get_history_path() {
+        let keep = history::get_history_retention(&conn);
+        let _ = history::record_install(
+            &conn,
+            &history_dir,
+            &addon,
+            &temp_path,
+            install_info.as_ref(),
+            keep,
+        );
+    }
+
     // Emit completion
-    let _ = window.emit(
+    let _ = app.emit(
         "download-progress",
         DownloadProgress {
             slug: slug.clone(),
             status: DownloadStatus::Complete,
             progress: 1.0,
             error: None,
+            operation_id: Some(op.id),
+            ..Default::default()
         },
     );
 
     Ok(addon)
 }
 
+/// Download an install's archive to `target`, from the archive cache when it holds
+/// the pinned version and otherwise from the healthiest of its sources
+async fn download_archive<F>(
+    state: &AppState,
+    op: &Operation,
+    request: &InstallRequest,
+    source: &SourceType,
+    integrity: Option<&ExpectedIntegrity>,
+    target: &Path,
+    on_progress: F,
+) -> Result<(), AppError>
+where
+    F: Fn(&TransferProgress) + Send + Sync + 'static,
+{
+    let (cache, offline, health) = {
+        let conn = state
+            .db
+            .lock()
+            .map_err(|e| AppError::Custom(e.to_string()))?;
+        (
+            cache::open_archive_cache(&conn),
+            cache::is_offline_mode(&conn),
+            SourceHealth::load(&conn),
+        )
+    };
+    let commit_sha = request
+        .version_tracking
+        .as_ref()
+        .and_then(|vt| vt.commit_sha.as_deref());
+    let pin = if *source == SourceType::Github {
+        archive_pin(&request.version, commit_sha)
+    } else {
+        commit_sha
+            .or(integrity.and_then(|i| i.checksum.as_deref()))
+            .map(String::from)
+    };
+    let lookup = cache.as_ref().map(|cache| cache::CacheLookup {
+        cache,
+        pin: pin.as_deref(),
+        offline,
+    });
+
+    // Multi-source fallback, healthiest hosts first; without sources the URL is used.
+    // Custom GitHub repos may be private, so their URL is downloaded with the token.
+    let sources = request.download_sources.as_deref().unwrap_or_default();
+    let client = if *source == SourceType::Github && sources.is_empty() {
+        state.download_client(&request.download_url)
+    } else {
+        state.http_client()
+    };
+    let result = downloader::download_with_fallback(
+        &client,
+        sources,
+        Some(&request.download_url),
+        target,
+        downloader::partial_downloads_dir().as_deref(),
+        integrity,
+        lookup.as_ref(),
+        Some(&health),
+        &DownloadControl {
+            cancel: Some(op.token.clone()),
+            throttle: state.bandwidth.throttle(request.priority),
+        },
+        on_progress,
+    )
+    .await;
+
+    // Host health only steers later downloads, so failing to save it isn't fatal
+    if let Ok(conn) = state.db.lock() {
+        let _ = health.save(&conn);
+    }
+
+    result
+}
+
 /// Uninstall an addon
+/// With `keep_user_files`, only the files recorded at install time are removed.
 #[tauri::command]
-pub async fn uninstall_addon(slug: String, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn uninstall_addon(
+    slug: String,
+    keep_user_files: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let keep_user_files = keep_user_files.unwrap_or(false);
+
+    // Only the inventories are read under the lock; deleting files can take a while
+    let inventories = load_inventories(&state, &slug)?;
+    if inventories.is_empty() {
+        return Err(format!("Addon not found: {}", slug));
+    }
+    let slugs: Vec<String> = inventories.iter().map(|i| i.addon.slug.clone()).collect();
+
+    // Remove addon files, including addons installed from the same archive
+    tokio::task::spawn_blocking(move || {
+        inventories
+            .iter()
+            .try_for_each(|i| inventory::remove_inventory_files(i, keep_user_files))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    // Remove from database
     let conn = state.db.lock().map_err(|e| e.to_string())?;
+    for removed in &slugs {
+        database::delete_installed(&conn, removed).map_err(|e| e.to_string())?;
+    }
+
+    // Drop retained archives for rollback
+    let _ = history::clear_history(&conn, &slug);
 
-    // Get addon info
-    let addon = database::get_installed_by_slug(&conn, &slug)
+    Ok(())
+}
+
+/// Compare an installed addon's files with the inventory recorded when it was installed
+#[tauri::command]
+pub async fn verify_addon(
+    slug: String,
+    state: State<'_, AppState>,
+) -> Result<VerifyResult, String> {
+    let recorded = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        let addon = database::get_installed_by_slug(&conn, &slug)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Addon not found: {}", slug))?;
+        inventory::load_inventory(&conn, &addon).map_err(|e| e.to_string())?
+    };
+
+    tokio::task::spawn_blocking(move || inventory::verify_inventory(&recorded))
+        .await
         .map_err(|e| e.to_string())?
-        .ok_or_else(|| format!("Addon not found: {}", slug))?;
+        .map_err(|e| e.to_string())
+}
 
-    // Get the addon directory from the manifest path
-    let manifest_path = PathBuf::from(&addon.manifest_path);
-    let addon_dir = manifest_path
-        .parent()
-        .ok_or_else(|| "Invalid manifest path".to_string())?;
+/// Verify every installed addon that has a recorded inventory
+#[tauri::command]
+pub async fn verify_all(state: State<'_, AppState>) -> Result<Vec<VerifyResult>, String> {
+    let inventories = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        let installed = database::get_all_installed(&conn).map_err(|e| e.to_string())?;
+        installed
+            .iter()
+            .map(|addon| inventory::load_inventory(&conn, addon))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
 
-    // Remove addon files
-    installer::uninstall_addon(addon_dir).map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || {
+        inventories
+            .iter()
+            .filter(|i| !i.files.is_empty())
+            .map(inventory::verify_inventory)
+            .collect::<Result<Vec<_>, AppError>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
 
-    // Remove from database
-    database::delete_installed(&conn, &slug).map_err(|e| e.to_string())?;
+/// Copy an addon's locally edited and added files into the patches directory
+/// Returns the backup directory, or None when there were no local changes.
+#[tauri::command]
+pub async fn backup_addon_changes(
+    slug: String,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    Ok(backup_changes(&state, &slug)
+        .await?
+        .map(|p| p.to_string_lossy().to_string()))
+}
 
-    Ok(())
+/// Get the retained install history for an addon (newest first)
+#[tauri::command]
+pub async fn get_addon_history(
+    slug: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<InstallHistoryEntry>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    database::get_install_history(&conn, &slug).map_err(|e| e.to_string())
+}
+
+/// Roll an addon back to a previously installed version from its retained archive
+/// The retained archive is reinstalled through the download queue like any other
+/// install, so local changes are checked and the restored version is recorded.
+#[tauri::command]
+pub async fn rollback_addon(
+    slug: String,
+    version: String,
+    overwrite_local_changes: Option<bool>,
+    backup_local_changes: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<InstalledAddon, String> {
+    let entry = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        history::find_entry(&conn, &slug, &version).map_err(|e| e.to_string())?
+    };
+
+    let request = InstallRequest {
+        slug: entry.slug,
+        name: entry.name,
+        version: entry.version,
+        download_url: String::new(),
+        source_type: Some(entry.source_type.to_string()),
+        source_repo: entry.source_repo,
+        install_info: entry.install_info,
+        version_tracking: Some(VersionTracking {
+            version_sort_key: entry.version_sort_key,
+            commit_sha: entry.commit_sha,
+        }),
+        download_sources: None,
+        overwrite_local_changes: overwrite_local_changes.unwrap_or(false),
+        backup_local_changes: backup_local_changes.unwrap_or(false),
+        priority: DownloadPriority::User,
+        archive_path: Some(PathBuf::from(entry.archive_path)),
+    };
+
+    let (_, result) = enqueue_install(&state, &app, request)?;
+    result
+        .await
+        .map_err(|_| "Rollback was interrupted".to_string())?
 }
 
 /// Scan local addon directory for untracked addons
@@ -382,13 +835,8 @@ pub async fn check_updates(state: State<'_, AppState>) -> Result<Vec<UpdateInfo>
 
         let installed = database::get_all_installed(&conn).map_err(|e| e.to_string())?;
 
-        // Get cached index for Index source addons
-        let index_data = database::get_cached_index(&conn)
-            .map_err(|e| e.to_string())?
-            .map(|(data, _, _)| data);
-
-        let index: Option<crate::models::AddonIndex> =
-            index_data.and_then(|data| serde_json::from_str(&data).ok());
+        // Get the merged cached catalog for Index source addons
+        let index = catalog::load_cached_catalog(&conn).map_err(|e| e.to_string())?;
 
         // Get custom repos for GitHub source addons
         let custom_repos = database::get_all_custom_repos(&conn).unwrap_or_default();
@@ -396,9 +844,18 @@ pub async fn check_updates(state: State<'_, AppState>) -> Result<Vec<UpdateInfo>
         (installed, index, custom_repos)
     }; // conn is dropped here
 
+    // Update checks can wait: they stop asking GitHub once the rate limit budget runs
+    // low, and those addons are checked again next time
+    let github = state.github_client().background();
+    let github_statuses = fetch_github_statuses(&github, &installed, &custom_repos).await;
     let mut updates = Vec::new();
 
     for addon in installed {
+        // Bundled addons are updated together with the addon they were installed with
+        if addon.parent_slug.is_some() {
+            continue;
+        }
+
         match addon.source_type {
             SourceType::Index => {
                 // Check against the index using simplified version comparison
@@ -418,6 +875,15 @@ pub async fn check_updates(state: State<'_, AppState>) -> Result<Vec<UpdateInfo>
                                     source_repo: Some(index_entry.source.repo.clone()),
                                     install_info: Some(index_entry.install.clone()),
                                     download_sources: index_entry.download_sources.clone(),
+                                    has_local_changes: false,
+                                    commit_range: None,
+                                    version_tracking: Some(VersionTracking {
+                                        version_sort_key: index_entry
+                                            .version_info
+                                            .as_ref()
+                                            .and_then(|vi| vi.version_sort_key),
+                                        commit_sha: release.commit_sha.clone(),
+                                    }),
                                 });
                             }
                         }
@@ -430,14 +896,37 @@ pub async fn check_updates(state: State<'_, AppState>) -> Result<Vec<UpdateInfo>
                     // Find the custom repo config
                     let custom_repo = custom_repos.iter().find(|r| &r.repo == repo);
 
-                    // Only check release-based repos (branch-based would need commit tracking)
-                    if custom_repo
+                    // Branch-based repos compare the installed commit with the branch head
+                    if let Some(custom_repo) =
+                        custom_repo.filter(|r| r.release_type == crate::models::ReleaseType::Branch)
+                    {
+                        let status = github_statuses.get(repo);
+                        if let Some(update) =
+                            check_branch_update(&github, &addon, repo, &custom_repo.branch, status)
+                                .await
+                        {
+                            updates.push(update);
+                        }
+                    } else if custom_repo
                         .map(|r| r.release_type == crate::models::ReleaseType::Release)
                         .unwrap_or(true)
                     {
+                        // Skip the release lookup when the batch query says there is nothing new
+                        if let Some(status) = github_statuses.get(repo) {
+                            let newer = status.latest_release_tag.as_deref().is_some_and(|tag| {
+                                is_update_available(
+                                    &addon.installed_version,
+                                    tag.strip_prefix('v').unwrap_or(tag),
+                                )
+                            });
+                            if !newer {
+                                continue;
+                            }
+                        }
+
                         // Fetch latest release from GitHub
                         if let Ok(Some(release_info)) =
-                            downloader::get_github_release_info(repo).await
+                            downloader::get_github_release_info(&github, repo).await
                         {
                             // Clean up tag name (remove 'v' prefix if present) for comparison
                             let new_version = release_info
@@ -457,6 +946,9 @@ pub async fn check_updates(state: State<'_, AppState>) -> Result<Vec<UpdateInfo>
                                     source_repo: Some(repo.clone()),
                                     install_info: None, // GitHub repos don't have index install info
                                     download_sources: Vec::new(), // GitHub repos use single download URL
+                                    has_local_changes: false,
+                                    commit_range: None,
+                                    version_tracking: None,
                                 });
                             }
                         }
@@ -470,9 +962,98 @@ pub async fn check_updates(state: State<'_, AppState>) -> Result<Vec<UpdateInfo>
         }
     }
 
+    // Flag updates that would overwrite local edits
+    for update in &mut updates {
+        update.has_local_changes = local_changes(&state, &update.slug)
+            .await
+            .is_ok_and(|changed| !changed.is_empty());
+    }
+
     Ok(updates)
 }
 
+/// Check a GitHub addon that tracks a branch for commits newer than the installed one
+///
+/// Uses the branch head from the batch query when there is one. Addons installed
+/// without a recorded commit can't be compared and never report an update.
+async fn check_branch_update(
+    github: &GitHubClient,
+    addon: &InstalledAddon,
+    repo: &str,
+    branch: &str,
+    status: Option<&RepoStatus>,
+) -> Option<UpdateInfo> {
+    let installed_sha = addon.commit_sha.as_deref()?;
+    let head_sha = match status.and_then(|s| s.head_sha.clone()) {
+        Some(sha) => sha,
+        None => downloader::get_github_branch_head(github, repo, branch)
+            .await
+            .ok()
+            .flatten()?,
+    };
+    if head_sha == installed_sha {
+        return None;
+    }
+
+    // The commit list is informational; the update stands without it
+    let commit_range = downloader::compare_github_commits(github, repo, installed_sha, &head_sha)
+        .await
+        .ok()
+        .flatten();
+
+    Some(UpdateInfo {
+        slug: addon.slug.clone(),
+        name: addon.name.clone(),
+        current_version: downloader::short_sha(installed_sha).to_string(),
+        new_version: downloader::short_sha(&head_sha).to_string(),
+        download_url: downloader::get_github_commit_url(github, repo, &head_sha),
+        source_type: SourceType::Github,
+        source_repo: Some(repo.to_string()),
+        install_info: None,
+        download_sources: Vec::new(),
+        has_local_changes: false,
+        commit_range,
+        version_tracking: Some(VersionTracking {
+            version_sort_key: None,
+            commit_sha: Some(head_sha),
+        }),
+    })
+}
+
+/// Look up all GitHub-sourced addons' repositories in a few GraphQL queries
+///
+/// Only possible with a token; without one, or if the queries fail, this returns
+/// nothing and each repository is checked with its own REST request instead.
+async fn fetch_github_statuses(
+    github: &GitHubClient,
+    installed: &[InstalledAddon],
+    custom_repos: &[crate::models::CustomRepo],
+) -> HashMap<String, RepoStatus> {
+    if !github.is_authenticated() {
+        return Default::default();
+    }
+
+    let tracked: Vec<TrackedRepo> = installed
+        .iter()
+        .filter(|a| a.source_type == SourceType::Github && a.parent_slug.is_none())
+        .filter_map(|a| a.source_repo.clone())
+        .map(|repo| {
+            let branch = custom_repos
+                .iter()
+                .find(|r| r.repo == repo && r.release_type == crate::models::ReleaseType::Branch)
+                .map(|r| r.branch.clone());
+            TrackedRepo { repo, branch }
+        })
+        .collect();
+    if tracked.is_empty() {
+        return Default::default();
+    }
+
+    graphql::fetch_repo_statuses(github, &tracked)
+        .await
+        .unwrap_or_default()
+}
+
 /// Check if an index addon has an update available using simplified comparison
 /// Priority: 1) version_sort_key comparison, 2) commit_sha comparison, 3) version string fallback
 fn check_index_addon_update(
@@ -548,15 +1129,11 @@ pub async fn resolve_addon_dependencies(
 ) -> Result<crate::services::resolver::DependencyResult, String> {
     let conn = state.db.lock().map_err(|e| e.to_string())?;
 
-    // Get cached index
-    let index_data = database::get_cached_index(&conn)
+    // Get the merged cached catalog
+    let index = catalog::load_cached_catalog(&conn)
         .map_err(|e| e.to_string())?
-        .map(|(data, _, _)| data)
         .ok_or_else(|| "No cached index available. Please refresh the index.".to_string())?;
 
-    let index: crate::models::AddonIndex =
-        serde_json::from_str(&index_data).map_err(|e| format!("Failed to parse index: {}", e))?;
-
     // Get installed addons
     let installed = database::get_all_installed(&conn).map_err(|e| e.to_string())?;
 
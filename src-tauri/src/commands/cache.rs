@@ -0,0 +1,22 @@
+use crate::models::CacheStats;
+use crate::services::cache;
+use crate::state::AppState;
+use tauri::State;
+
+/// Get the size and contents of the archive cache
+#[tauri::command]
+pub async fn get_cache_stats(state: State<'_, AppState>) -> Result<CacheStats, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    let cache = cache::open_archive_cache(&conn)
+        .ok_or_else(|| "Could not determine the app data directory".to_string())?;
+    Ok(cache.stats())
+}
+
+/// Delete every cached archive, returning the number of bytes freed
+#[tauri::command]
+pub async fn clear_cache(state: State<'_, AppState>) -> Result<u64, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    let cache = cache::open_archive_cache(&conn)
+        .ok_or_else(|| "Could not determine the app data directory".to_string())?;
+    cache.clear().map_err(|e| e.to_string())
+}
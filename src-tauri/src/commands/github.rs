@@ -1,13 +1,22 @@
-use crate::models::{
-    CustomRepo, DownloadProgress, DownloadStatus, InstalledAddon, ReleaseType, SourceType,
+use super::addons::InstallRequest;
+use super::queue::enqueue_install;
+use crate::error::AppError;
+use crate::models::{CustomRepo, InstalledAddon, ReleaseType, SourceType, VersionTracking};
+use crate::services::{
+    cache, database, downloader, http, installer, secrets, DownloadControl, DownloadPriority,
+    GitHubBudget, GitHubClient, InstallPlan, SecretStore,
 };
-use crate::services::{database, downloader, installer};
 use crate::state::AppState;
 use crate::utils::paths::get_eso_addon_path_with_custom;
 use std::path::PathBuf;
-use tauri::{Emitter, State, Window};
+use std::time::Duration;
+use tauri::{AppHandle, State};
 use tempfile::NamedTempFile;
 
+/// How long repository details shown while adding a repository are reused without
+/// asking GitHub again
+const REPO_PREVIEW_TTL: Duration = Duration::from_secs(10 * 60);
+
 /// Helper to get the ESO addon path, checking database for custom path first
 fn get_addon_path_from_state(state: &State<'_, AppState>) -> Result<PathBuf, String> {
     let conn = state.db.lock().map_err(|e| e.to_string())?;
@@ -29,8 +38,10 @@ pub async fn add_custom_repo(
     release_type: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<CustomRepo, String> {
+    let github = state.github_client();
+
     // Validate repo exists
-    let exists = downloader::validate_github_repo(&repo)
+    let exists = downloader::validate_github_repo(&github, &repo)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -44,7 +55,7 @@ pub async fn add_custom_repo(
         .unwrap_or(ReleaseType::Release);
 
     if release_type == ReleaseType::Release {
-        let release_url = downloader::get_github_release_url(&repo)
+        let release_url = downloader::get_github_release_url(&github, &repo)
             .await
             .map_err(|e| e.to_string())?;
 
@@ -81,21 +92,79 @@ pub async fn remove_custom_repo(repo: String, state: State<'_, AppState>) -> Res
     database::delete_custom_repo(&conn, &repo).map_err(|e| e.to_string())
 }
 
+/// Store a GitHub personal access token, sent with every GitHub request from now on
+///
+/// The token is checked with GitHub first and stored encrypted; returns the login it
+/// belongs to. An empty token removes the stored one.
+#[tauri::command]
+pub async fn set_github_token(
+    token: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, String> {
+    let token = token
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    let config = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        http::load_http_config(&conn)
+    };
+    let github = GitHubClient::new(&config, token.as_deref()).map_err(|e| e.to_string())?;
+
+    let login = match token {
+        Some(_) => Some(github.authenticated_user().await.map_err(|e| match e {
+            AppError::HttpStatus(401, _) => "GitHub rejected the token".to_string(),
+            e => format!("Could not verify the token: {}", e),
+        })?),
+        None => None,
+    };
+
+    let store = SecretStore::open().ok_or("Could not determine app data path")?;
+    {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        store
+            .set(&conn, secrets::GITHUB_TOKEN, token.as_deref())
+            .map_err(|e| e.to_string())?;
+    }
+    state.set_github_client(github);
+
+    Ok(login)
+}
+
+/// Whether a GitHub personal access token is stored
+#[tauri::command]
+pub async fn has_github_token(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.github_client().is_authenticated())
+}
+
+/// Get the GitHub API rate limit budget
+/// Without `refresh` this is what the last API response reported; refreshing asks
+/// GitHub, which doesn't count against the budget.
+#[tauri::command]
+pub async fn get_github_rate_limit(
+    refresh: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<GitHubBudget, String> {
+    let github = state.github_client();
+    if refresh.unwrap_or(false) {
+        github.refresh_budget().await.map_err(|e| e.to_string())
+    } else {
+        Ok(github.budget())
+    }
+}
+
 /// Get GitHub repository information
 #[tauri::command]
-pub async fn get_github_repo_info(repo: String) -> Result<GitHubRepoInfo, String> {
-    let client = reqwest::Client::new();
+pub async fn get_github_repo_info(
+    repo: String,
+    state: State<'_, AppState>,
+) -> Result<GitHubRepoInfo, String> {
+    let github = state.github_client().cached_for(REPO_PREVIEW_TTL);
     let url = format!("https://api.github.com/repos/{}", repo);
 
-    let response: serde_json::Value = client
-        .get(&url)
-        .header("User-Agent", "eso-addon-manager")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .json()
+    let response = github
+        .get_cached(&url)
         .await
+        .and_then(|r| r.json())
         .map_err(|e| e.to_string())?;
 
     // Check for error response
@@ -134,20 +203,10 @@ pub async fn get_github_repo_info(repo: String) -> Result<GitHubRepoInfo, String
 
     // Check for latest release
     let release_url = format!("https://api.github.com/repos/{}/releases/latest", repo);
-    let latest_release = client
-        .get(&release_url)
-        .header("User-Agent", "eso-addon-manager")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await
-        .ok()
-        .and_then(|r| {
-            if r.status().is_success() {
-                Some(r)
-            } else {
-                None
-            }
-        });
+    let latest_release = match github.get_cached(&release_url).await {
+        Err(e @ AppError::RateLimited { .. }) => return Err(e.to_string()),
+        result => result.ok().filter(|r| r.is_success()),
+    };
 
     let has_releases = latest_release.is_some();
 
@@ -196,20 +255,18 @@ pub struct RepoPreview {
 
 /// List branches for a GitHub repository
 #[tauri::command]
-pub async fn list_github_branches(repo: String) -> Result<Vec<GitHubBranchInfo>, String> {
+pub async fn list_github_branches(
+    repo: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<GitHubBranchInfo>, String> {
     // First get repo info to find default branch
-    let client = reqwest::Client::new();
+    let github = state.github_client().cached_for(REPO_PREVIEW_TTL);
     let url = format!("https://api.github.com/repos/{}", repo);
 
-    let response: serde_json::Value = client
-        .get(&url)
-        .header("User-Agent", "eso-addon-manager")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .json()
+    let response = github
+        .get_cached(&url)
         .await
+        .and_then(|r| r.json())
         .map_err(|e| e.to_string())?;
 
     if let Some(message) = response.get("message").and_then(|m| m.as_str()) {
@@ -223,7 +280,7 @@ pub async fn list_github_branches(repo: String) -> Result<Vec<GitHubBranchInfo>,
         .and_then(|b| b.as_str())
         .unwrap_or("main");
 
-    let branches = downloader::list_github_branches(&repo, default_branch)
+    let branches = downloader::list_github_branches(&github, &repo, default_branch)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -238,19 +295,17 @@ pub async fn list_github_branches(repo: String) -> Result<Vec<GitHubBranchInfo>,
 
 /// Get full repository preview for the add modal
 #[tauri::command]
-pub async fn get_github_repo_preview(repo: String) -> Result<RepoPreview, String> {
-    let client = reqwest::Client::new();
+pub async fn get_github_repo_preview(
+    repo: String,
+    state: State<'_, AppState>,
+) -> Result<RepoPreview, String> {
+    let github = state.github_client().cached_for(REPO_PREVIEW_TTL);
     let url = format!("https://api.github.com/repos/{}", repo);
 
-    let response: serde_json::Value = client
-        .get(&url)
-        .header("User-Agent", "eso-addon-manager")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .json()
+    let response = github
+        .get_cached(&url)
         .await
+        .and_then(|r| r.json())
         .map_err(|e| e.to_string())?;
 
     // Check for error response
@@ -288,7 +343,7 @@ pub async fn get_github_repo_preview(repo: String) -> Result<RepoPreview, String
         .map(String::from);
 
     // Get branches
-    let branches = downloader::list_github_branches(&repo, &default_branch)
+    let branches = downloader::list_github_branches(&github, &repo, &default_branch)
         .await
         .map_err(|e| e.to_string())?
         .into_iter()
@@ -299,7 +354,7 @@ pub async fn get_github_repo_preview(repo: String) -> Result<RepoPreview, String
         .collect();
 
     // Get latest release
-    let release_info = downloader::get_github_release_info(&repo)
+    let release_info = downloader::get_github_release_info(&github, &repo)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -323,136 +378,184 @@ pub async fn get_github_repo_preview(repo: String) -> Result<RepoPreview, String
     })
 }
 
-/// Install an addon from a GitHub repository
-#[tauri::command]
-pub async fn install_from_github(
-    repo: String,
-    release_type: Option<String>,
-    branch: Option<String>,
-    state: State<'_, AppState>,
-    window: Window,
-) -> Result<InstalledAddon, String> {
-    // Generate a slug from the repo name
-    let slug = repo
-        .split('/')
+/// Generate an addon slug from a repository name
+fn repo_slug(repo: &str) -> String {
+    repo.split('/')
         .next_back()
-        .unwrap_or(&repo)
+        .unwrap_or(repo)
         .to_lowercase()
-        .replace(' ', "-");
-
-    // Emit initial progress
-    let _ = window.emit(
-        "download-progress",
-        DownloadProgress {
-            slug: slug.clone(),
-            status: DownloadStatus::Downloading,
-            progress: 0.0,
-            error: None,
-        },
-    );
+        .replace(' ', "-")
+}
 
+/// Get the download URL, version and (for branches) commit SHA to install for a repository
+/// Branches are downloaded at their current head, so the archive matches the recorded SHA.
+async fn resolve_github_download(
+    github: &GitHubClient,
+    repo: &str,
+    release_type: Option<String>,
+    branch: Option<&str>,
+) -> Result<(String, String, Option<String>), String> {
     let release_type = release_type
         .and_then(|s| s.parse().ok())
         .unwrap_or(ReleaseType::Release);
 
-    // Get download URL and version based on release type
-    let (download_url, version) = if release_type == ReleaseType::Release {
-        let release_info = downloader::get_github_release_info(&repo)
+    if release_type == ReleaseType::Release {
+        let release_info = downloader::get_github_release_info(github, repo)
             .await
             .map_err(|e| e.to_string())?
             .ok_or_else(|| format!("No releases found for {}", repo))?;
 
-        (release_info.download_url, release_info.tag_name)
+        Ok((release_info.download_url, release_info.tag_name, None))
     } else {
-        let branch_name = branch.as_deref().unwrap_or("main");
-        let url = downloader::get_github_branch_url(&repo, branch_name).await;
-        (url, format!("branch:{}", branch_name))
+        let branch_name = branch.unwrap_or("main");
+        let head = downloader::get_github_branch_head(github, repo, branch_name).await;
+        match head {
+            // Tracked installs are versioned by their commit, like branch updates
+            Ok(Some(sha)) => Ok((
+                downloader::get_github_commit_url(github, repo, &sha),
+                downloader::short_sha(&sha).to_string(),
+                Some(sha),
+            )),
+            Err(e @ AppError::RateLimited { .. }) => Err(e.to_string()),
+            // Without a known head the branch archive is installed untracked
+            _ => {
+                let url = downloader::get_github_branch_url(github, repo, branch_name).await;
+                Ok((url, format!("branch:{}", branch_name), None))
+            }
+        }
+    }
+}
+
+/// Key that pins a repository archive's content in the download cache
+/// Branch archives are pinned by their commit and releases by their tag; a branch
+/// installed without a known head changes over time and is never cached.
+pub(crate) fn archive_pin(version: &str, commit_sha: Option<&str>) -> Option<String> {
+    match commit_sha {
+        Some(sha) => Some(sha.to_string()),
+        None if !version.starts_with("branch:") => Some(format!("tag:{}", version)),
+        None => None,
+    }
+}
+
+/// Download a repository's archive and list the addon folders it would install
+///
+/// Archives can bundle several addons; the plan marks the main addon and any
+/// existing folders that would be replaced. The archive is kept in the download
+/// cache, so installing right after the preview doesn't download it again.
+#[tauri::command]
+pub async fn preview_github_install(
+    repo: String,
+    release_type: Option<String>,
+    branch: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<InstallPlan, String> {
+    let slug = repo_slug(&repo);
+    let (download_url, version, commit_sha) = resolve_github_download(
+        &state.github_client(),
+        &repo,
+        release_type,
+        branch.as_deref(),
+    )
+    .await?;
+    let client = state.download_client(&download_url);
+
+    let (cache, offline) = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        (
+            cache::open_archive_cache(&conn),
+            cache::is_offline_mode(&conn),
+        )
     };
+    let pin = archive_pin(&version, commit_sha.as_deref());
+    let lookup = cache.as_ref().map(|cache| cache::CacheLookup {
+        cache,
+        pin: pin.as_deref(),
+        offline,
+    });
 
-    // Create temp file for download
     let temp_file =
         NamedTempFile::new().map_err(|e| format!("Failed to create temp file: {}", e))?;
     let temp_path = temp_file.path().to_path_buf();
-
-    // Download the addon
-    let window_clone = window.clone();
-    let slug_clone = slug.clone();
-    downloader::download_file(&download_url, &temp_path, move |progress| {
-        let _ = window_clone.emit(
-            "download-progress",
-            DownloadProgress {
-                slug: slug_clone.clone(),
-                status: DownloadStatus::Downloading,
-                progress,
-                error: None,
-            },
-        );
-    })
+    let control = DownloadControl {
+        cancel: None,
+        throttle: state.bandwidth.throttle(DownloadPriority::User),
+    };
+    downloader::download_with_fallback(
+        &client,
+        &[],
+        Some(&download_url),
+        &temp_path,
+        downloader::partial_downloads_dir().as_deref(),
+        None,
+        lookup.as_ref(),
+        None,
+        &control,
+        |_| {},
+    )
     .await
     .map_err(|e| format!("Download failed: {}", e))?;
 
-    // Emit extracting status
-    let _ = window.emit(
-        "download-progress",
-        DownloadProgress {
-            slug: slug.clone(),
-            status: DownloadStatus::Extracting,
-            progress: 0.0,
-            error: None,
-        },
-    );
-
-    // Get ESO addon directory (checks custom path from database first)
     let addon_dir = get_addon_path_from_state(&state)?;
+    installer::plan_archive_install(&temp_path, &addon_dir, Some(&slug)).map_err(|e| e.to_string())
+}
 
-    // Install the addon
-    let installed_path = installer::install_from_archive(&temp_path, &addon_dir)
-        .map_err(|e| format!("Installation failed: {}", e))?;
-
-    // Get manifest path and addon name
-    let manifest_path = installer::get_manifest_path(&installed_path)
-        .ok_or_else(|| "Could not find addon manifest".to_string())?;
-
-    // Use the installed folder name as the addon name
-    let addon_name = installed_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or(&slug)
-        .to_string();
-
-    // Update database
-    let conn = state.db.lock().map_err(|e| e.to_string())?;
-    let addon = database::insert_installed(
-        &conn,
-        &slug,
-        &addon_name,
-        &version,
-        SourceType::Github,
-        Some(&repo),
-        manifest_path.to_string_lossy().as_ref(),
-        None, // No version_sort_key for custom GitHub repos
-        None, // No commit_sha tracking for custom GitHub repos yet
+/// Install an addon from a GitHub repository
+/// Every addon folder in the archive is installed; bundled ones are tracked as linked addons.
+/// The install runs through the download queue like any other, so it can be cancelled
+/// with `cancel_operation` and is resumed after a restart. Like `install_addon`, it
+/// fails rather than overwrite local edits unless `overwrite_local_changes` or
+/// `backup_local_changes` is set.
+#[tauri::command]
+pub async fn install_from_github(
+    repo: String,
+    release_type: Option<String>,
+    branch: Option<String>,
+    overwrite_local_changes: Option<bool>,
+    backup_local_changes: Option<bool>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<InstalledAddon, String> {
+    // Get download URL and version based on release type
+    let (download_url, version, commit_sha) = resolve_github_download(
+        &state.github_client(),
+        &repo,
+        release_type,
+        branch.as_deref(),
     )
-    .map_err(|e| e.to_string())?;
-
-    // Emit completion
-    let _ = window.emit(
-        "download-progress",
-        DownloadProgress {
-            slug: slug.clone(),
-            status: DownloadStatus::Complete,
-            progress: 1.0,
-            error: None,
-        },
-    );
-
-    Ok(addon)
+    .await?;
+
+    let request = InstallRequest {
+        slug: repo_slug(&repo),
+        name: repo.clone(),
+        version,
+        download_url,
+        source_type: Some(SourceType::Github.to_string()),
+        source_repo: Some(repo),
+        install_info: None,
+        version_tracking: Some(VersionTracking {
+            version_sort_key: None,
+            commit_sha,
+        }),
+        download_sources: None,
+        overwrite_local_changes: overwrite_local_changes.unwrap_or(false),
+        backup_local_changes: backup_local_changes.unwrap_or(false),
+        priority: DownloadPriority::User,
+        archive_path: None,
+    };
+
+    let (_, result) = enqueue_install(&state, &app, request)?;
+    result
+        .await
+        .map_err(|_| "Download was interrupted".to_string())?
 }
 
 /// Get release information for a GitHub repository
 #[tauri::command]
-pub async fn get_github_release(repo: String) -> Result<Option<GitHubReleaseInfo>, String> {
-    downloader::get_github_release_info(&repo)
+pub async fn get_github_release(
+    repo: String,
+    state: State<'_, AppState>,
+) -> Result<Option<GitHubReleaseInfo>, String> {
+    downloader::get_github_release_info(&state.github_client(), &repo)
         .await
         .map(|info| {
             info.map(|i| GitHubReleaseInfo {
@@ -1,13 +1,17 @@
-use crate::models::AddonIndex;
-use crate::services::database;
+use crate::models::{AddonIndex, IndexFetchSource, IndexSource};
+use crate::services::{catalog, database, downloader, IndexResponse};
 use crate::state::AppState;
+use crate::utils::signature;
 use chrono::Utc;
+use futures_util::future::join_all;
 use tauri::State;
 
-/// Default index URL (can be overridden in settings)
-const DEFAULT_INDEX_URL: &str = "https://xop.co/eso-addon-index/index.json";
-
-/// Fetch the addon index (from cache or remote)
+/// Fetch the merged addon index from all configured sources (from cache or remote)
+///
+/// Each source is cached separately. Within the cache window a source's cached copy
+/// is used directly; otherwise the request carries its cached ETag/Last-Modified so
+/// an unchanged index is only revalidated (HTTP 304) instead of downloaded again.
+/// A source that cannot be fetched falls back to its last cached copy.
 #[tauri::command]
 pub async fn fetch_index(
     force: Option<bool>,
@@ -15,78 +19,194 @@ pub async fn fetch_index(
 ) -> Result<AddonIndex, String> {
     let force = force.unwrap_or(false);
 
-    // Check cache first (unless force refresh) - scope the lock
-    let (cached_index, index_url) = {
+    let sources = {
         let conn = state.db.lock().map_err(|e| e.to_string())?;
+        database::get_index_sources(&conn).map_err(|e| e.to_string())?
+    };
+
+    if sources.is_empty() {
+        return Err("No index sources configured".to_string());
+    }
 
-        let cached = if !force {
-            if let Ok(Some((data, fetched_at, _))) = database::get_cached_index(&conn) {
-                // Check if cache is less than 1 hour old
-                if let Ok(fetched) = chrono::DateTime::parse_from_rfc3339(&fetched_at) {
-                    let age = Utc::now().signed_duration_since(fetched);
-                    if age.num_hours() < 1 {
-                        serde_json::from_str::<AddonIndex>(&data).ok()
-                    } else {
-                        None
+    // Fetch every source concurrently; results stay in priority order
+    let results = join_all(
+        sources
+            .iter()
+            .map(|source| fetch_source_index(source, force, &state)),
+    )
+    .await;
+
+    let mut indexes = Vec::new();
+    let mut fetch_sources = Vec::new();
+    let mut fetched_at: Option<String> = None;
+    let mut source_errors = Vec::new();
+
+    for (source, result) in sources.into_iter().zip(results) {
+        match result {
+            Ok((index, fetch_source, error)) => {
+                if let Some(error) = error {
+                    source_errors.push(format!("{}: {}", source.name, error));
+                }
+                if let Some(ref at) = index.fetched_at {
+                    if fetched_at.as_ref().map(|f| at < f).unwrap_or(true) {
+                        fetched_at = Some(at.clone());
                     }
-                } else {
-                    None
                 }
-            } else {
-                None
+                fetch_sources.push(fetch_source);
+                indexes.push((source, index));
             }
-        } else {
-            None
-        };
+            Err(e) => source_errors.push(format!("{}: {}", source.name, e)),
+        }
+    }
+
+    if indexes.is_empty() {
+        return Err(format!(
+            "Failed to fetch index: {}",
+            source_errors.join("; ")
+        ));
+    }
+
+    let mut index = catalog::merge_indexes(indexes);
+    index.fetched_at = fetched_at;
+    index.fetch_source = [
+        IndexFetchSource::Network,
+        IndexFetchSource::Revalidated,
+        IndexFetchSource::Cache,
+    ]
+    .into_iter()
+    .find(|kind| fetch_sources.contains(kind));
+    index.source_errors = source_errors;
 
-        let url = database::get_setting(&conn, "index_url")
+    Ok(index)
+}
+
+/// Fetch a single index source, returning the index, how it was obtained and
+/// the fetch error if a stale cached copy had to be used instead
+async fn fetch_source_index(
+    source: &IndexSource,
+    force: bool,
+    state: &State<'_, AppState>,
+) -> Result<(AddonIndex, IndexFetchSource, Option<String>), String> {
+    // Only keep a cache entry that still parses; otherwise refetch in full
+    let cached = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        database::get_cached_index(&conn, source.id)
             .ok()
             .flatten()
-            .unwrap_or_else(|| DEFAULT_INDEX_URL.to_string());
-
-        (cached, url)
+            .and_then(|cache| {
+                serde_json::from_str::<AddonIndex>(&cache.data)
+                    .ok()
+                    .map(|mut index| {
+                        index.fetched_at = Some(cache.fetched_at.clone());
+                        (cache, index)
+                    })
+            })
     }; // Lock is dropped here
 
-    // Return cached index if valid
-    if let Some(index) = cached_index {
-        return Ok(index);
+    // Use the cached index if it is less than 1 hour old (unless force refresh)
+    if let Some((cache, index)) = &cached {
+        if !force && is_cache_fresh(&cache.fetched_at) {
+            return Ok((index.clone(), IndexFetchSource::Cache, None));
+        }
     }
 
-    // Fetch from remote
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&index_url)
-        .header("User-Agent", "eso-addon-manager")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to fetch index: {}", e))?;
-
-    let etag = response
-        .headers()
-        .get("etag")
-        .and_then(|v| v.to_str().ok())
-        .map(String::from);
-
-    let data = response
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read index: {}", e))?;
-
-    // Parse the index
-    let mut index: AddonIndex =
-        serde_json::from_str(&data).map_err(|e| format!("Failed to parse index: {}", e))?;
-
-    // Update fetched_at
-    index.fetched_at = Some(Utc::now().to_rfc3339());
-
-    // Cache the index - acquire lock again
+    // Fetch from remote, revalidating the cached copy if we have one
+    let (etag, last_modified) = cached
+        .as_ref()
+        .map(|(cache, _)| (cache.etag.as_deref(), cache.last_modified.as_deref()))
+        .unwrap_or((None, None));
+
+    let client = state.http_client();
+    let response = match downloader::fetch_index_conditional(
+        &client,
+        &source.url,
+        etag,
+        last_modified,
+    )
+    .await
     {
-        let conn = state.db.lock().map_err(|e| e.to_string())?;
-        database::update_cached_index(&conn, &data, etag.as_deref())
-            .map_err(|e| format!("Failed to cache index: {}", e))?;
+        Ok(response) => response,
+        Err(e) => {
+            let error = format!("Failed to fetch index: {}", e);
+            return match cached {
+                Some((_, index)) => Ok((index, IndexFetchSource::Cache, Some(error))),
+                None => Err(error),
+            };
+        }
+    };
+
+    match response {
+        IndexResponse::NotModified => {
+            let (_, mut index) = cached.ok_or_else(|| {
+                "Index server returned 304 but no cached index is available".to_string()
+            })?;
+
+            {
+                let conn = state.db.lock().map_err(|e| e.to_string())?;
+                database::touch_cached_index(&conn, source.id)
+                    .map_err(|e| format!("Failed to update index cache: {}", e))?;
+            }
+
+            index.fetched_at = Some(Utc::now().to_rfc3339());
+            Ok((index, IndexFetchSource::Revalidated, None))
+        }
+        IndexResponse::Modified {
+            data,
+            etag,
+            last_modified,
+        } => {
+            // Verify the signature before the index is trusted or cached; on mismatch
+            // keep using the last verified copy
+            if let Some(ref public_key) = source.public_key {
+                if let Err(e) = verify_index(&client, &source.url, &data, public_key).await {
+                    let error = e.to_string();
+                    return match cached {
+                        Some((_, index)) => Ok((index, IndexFetchSource::Cache, Some(error))),
+                        None => Err(error),
+                    };
+                }
+            }
+
+            // Parse the index
+            let mut index: AddonIndex =
+                serde_json::from_str(&data).map_err(|e| format!("Failed to parse index: {}", e))?;
+
+            index.fetched_at = Some(Utc::now().to_rfc3339());
+
+            // Cache the index - acquire lock again
+            {
+                let conn = state.db.lock().map_err(|e| e.to_string())?;
+                database::update_cached_index(
+                    &conn,
+                    source.id,
+                    &data,
+                    etag.as_deref(),
+                    last_modified.as_deref(),
+                )
+                .map_err(|e| format!("Failed to cache index: {}", e))?;
+            }
+
+            Ok((index, IndexFetchSource::Network, None))
+        }
     }
+}
 
-    Ok(index)
+/// Fetch the detached signature for an index and verify it against the source key
+async fn verify_index(
+    client: &reqwest::Client,
+    index_url: &str,
+    data: &str,
+    public_key: &str,
+) -> crate::error::Result<()> {
+    let signature = downloader::fetch_index_signature(client, index_url).await?;
+    signature::verify_signature(data.as_bytes(), &signature, public_key)
+}
+
+/// Check whether a cache timestamp is less than 1 hour old
+fn is_cache_fresh(fetched_at: &str) -> bool {
+    chrono::DateTime::parse_from_rfc3339(fetched_at)
+        .map(|fetched| Utc::now().signed_duration_since(fetched).num_hours() < 1)
+        .unwrap_or(false)
 }
 
 /// Get the cached index without fetching
@@ -94,16 +214,12 @@ pub async fn fetch_index(
 pub async fn get_cached_index(state: State<'_, AppState>) -> Result<Option<AddonIndex>, String> {
     let conn = state.db.lock().map_err(|e| e.to_string())?;
 
-    let cached = database::get_cached_index(&conn).map_err(|e| e.to_string())?;
+    let cached = catalog::load_cached_catalog(&conn).map_err(|e| e.to_string())?;
 
-    match cached {
-        Some((data, _, _)) => {
-            let index: AddonIndex =
-                serde_json::from_str(&data).map_err(|e| format!("Failed to parse index: {}", e))?;
-            Ok(Some(index))
-        }
-        None => Ok(None),
-    }
+    Ok(cached.map(|mut index| {
+        index.fetch_source = Some(IndexFetchSource::Cache);
+        index
+    }))
 }
 
 /// Get index statistics
@@ -111,18 +227,13 @@ pub async fn get_cached_index(state: State<'_, AppState>) -> Result<Option<Addon
 pub async fn get_index_stats(state: State<'_, AppState>) -> Result<IndexStats, String> {
     let conn = state.db.lock().map_err(|e| e.to_string())?;
 
-    let cached = database::get_cached_index(&conn).map_err(|e| e.to_string())?;
+    let cached = catalog::load_cached_catalog(&conn).map_err(|e| e.to_string())?;
 
     match cached {
-        Some((data, fetched_at, _)) => {
-            let index: AddonIndex =
-                serde_json::from_str(&data).map_err(|e| format!("Failed to parse index: {}", e))?;
-
-            Ok(IndexStats {
-                total_addons: index.addons.len(),
-                fetched_at,
-            })
-        }
+        Some(index) => Ok(IndexStats {
+            total_addons: index.addons.len(),
+            fetched_at: index.fetched_at.unwrap_or_default(),
+        }),
         None => Ok(IndexStats {
             total_addons: 0,
             fetched_at: String::new(),
@@ -136,3 +247,105 @@ pub struct IndexStats {
     pub total_addons: usize,
     pub fetched_at: String,
 }
+
+/// Get all configured index sources in priority order
+#[tauri::command]
+pub async fn get_index_sources(state: State<'_, AppState>) -> Result<Vec<IndexSource>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    database::get_index_sources(&conn).map_err(|e| e.to_string())
+}
+
+/// Add an index source at the lowest priority
+/// When `public_key` is given, the source's index must be signed with that minisign key.
+#[tauri::command]
+pub async fn add_index_source(
+    url: String,
+    name: Option<String>,
+    public_key: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<IndexSource, String> {
+    let url = url.trim().to_string();
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(format!("Invalid index URL: {}", url));
+    }
+
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| url.clone());
+
+    let public_key = normalize_public_key(public_key)?;
+
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    database::insert_index_source(&conn, &name, &url, public_key.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Set or clear the minisign public key used to verify an index source
+#[tauri::command]
+pub async fn set_index_source_key(
+    id: i64,
+    public_key: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<IndexSource, String> {
+    let public_key = normalize_public_key(public_key)?;
+
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    database::set_index_source_key(&conn, id, public_key.as_deref()).map_err(|e| e.to_string())?;
+
+    database::get_index_sources(&conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| format!("Index source not found: {}", id))
+}
+
+/// Trim a user-supplied public key, treating blank as none, and reject malformed keys
+fn normalize_public_key(public_key: Option<String>) -> Result<Option<String>, String> {
+    let public_key = public_key
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty());
+
+    if let Some(ref key) = public_key {
+        signature::validate_public_key(key).map_err(|e| e.to_string())?;
+    }
+
+    Ok(public_key)
+}
+
+/// Remove an index source and its cached data
+#[tauri::command]
+pub async fn remove_index_source(id: i64, state: State<'_, AppState>) -> Result<(), String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+
+    let sources = database::get_index_sources(&conn).map_err(|e| e.to_string())?;
+    if !sources.iter().any(|s| s.id == id) {
+        return Err(format!("Index source not found: {}", id));
+    }
+    if sources.len() == 1 {
+        return Err("At least one index source is required".to_string());
+    }
+
+    database::delete_index_source(&conn, id).map_err(|e| e.to_string())
+}
+
+/// Reorder index sources; `ids` lists every source id from highest to lowest priority
+#[tauri::command]
+pub async fn reorder_index_sources(
+    ids: Vec<i64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<IndexSource>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+
+    let sources = database::get_index_sources(&conn).map_err(|e| e.to_string())?;
+    let mut expected: Vec<i64> = sources.iter().map(|s| s.id).collect();
+    let mut given = ids.clone();
+    expected.sort_unstable();
+    given.sort_unstable();
+    if expected != given {
+        return Err("Reorder must list every index source exactly once".to_string());
+    }
+
+    database::reorder_index_sources(&conn, &ids).map_err(|e| e.to_string())?;
+    database::get_index_sources(&conn).map_err(|e| e.to_string())
+}
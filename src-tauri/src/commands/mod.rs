@@ -1,9 +1,13 @@
 pub mod addons;
+pub mod cache;
 pub mod github;
 pub mod index;
+pub mod queue;
 pub mod settings;
 
 pub use addons::*;
+pub use cache::*;
 pub use github::*;
 pub use index::*;
+pub use queue::*;
 pub use settings::*;
@@ -0,0 +1,319 @@
+use super::addons::{perform_install, InstallRequest};
+use crate::error::AppError;
+use crate::models::{DownloadJob, DownloadProgress, DownloadStatus, TransferProgress};
+use crate::services::database;
+use crate::services::queue::{JobResult, Operation};
+use crate::state::AppState;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::oneshot;
+
+/// Minimum time between saves of a running job's progress to the download list
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Queue an install and return the job once it is recorded
+/// Progress is reported through the usual `download-progress` events.
+#[tauri::command]
+pub async fn queue_install(
+    request: InstallRequest,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DownloadJob, String> {
+    let (job, _) = enqueue_install(&state, &app, request)?;
+    Ok(job)
+}
+
+/// Get all download jobs, newest first
+#[tauri::command]
+pub async fn get_downloads(state: State<'_, AppState>) -> Result<Vec<DownloadJob>, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    database::get_downloads(&conn).map_err(|e| e.to_string())
+}
+
+/// Cancel a queued or running install or update
+/// The download stops and its temp and partial files are removed. An install that
+/// has already started extracting is left to finish, so the addon is never half
+/// replaced; the job's status tells which happened.
+#[tauri::command]
+pub async fn cancel_operation(
+    id: i64,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DownloadJob, String> {
+    let job = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        database::get_download(&conn, id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Download not found: {}", id))?
+    };
+
+    if job.status.is_finished() {
+        return Err(format!("Download {} has already finished", id));
+    }
+
+    // Jobs running in this session record the cancellation themselves once stopped
+    if !state.queue.cancel(id) {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        database::set_download_status(&conn, id, DownloadStatus::Cancelled, job.progress, None)
+            .map_err(|e| e.to_string())?;
+        emit_job_status(&app, id, &job.slug, DownloadStatus::Cancelled, None);
+    }
+
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    database::get_download(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Download not found: {}", id))
+}
+
+/// Cancel a pending or running download
+/// Same as `cancel_operation`, for the download list.
+#[tauri::command]
+pub async fn cancel_download(
+    id: i64,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DownloadJob, String> {
+    cancel_operation(id, state, app).await
+}
+
+/// Run a failed or cancelled download again with its original request
+#[tauri::command]
+pub async fn retry_download(
+    id: i64,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<DownloadJob, String> {
+    let (job, request) = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        let job = database::get_download(&conn, id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Download not found: {}", id))?;
+
+        if !matches!(
+            job.status,
+            DownloadStatus::Failed | DownloadStatus::Cancelled
+        ) {
+            return Err(format!(
+                "Only failed or cancelled downloads can be retried: {}",
+                id
+            ));
+        }
+
+        let request = load_request(&conn, id)?;
+        ensure_not_queued(&conn, &job.slug)?;
+        database::reset_download(&conn, id).map_err(|e| e.to_string())?;
+        (job, request)
+    };
+
+    emit_job_status(&app, id, &job.slug, DownloadStatus::Pending, None);
+    spawn_job(&state, app, id, request);
+
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    database::get_download(&conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Download not found: {}", id))
+}
+
+/// Remove completed, failed and cancelled downloads from the list, returning how many were removed
+#[tauri::command]
+pub async fn clear_downloads(state: State<'_, AppState>) -> Result<usize, String> {
+    let conn = state.db.lock().map_err(|e| e.to_string())?;
+    database::delete_finished_downloads(&conn).map_err(|e| e.to_string())
+}
+
+/// Record an install in the download queue and start it when a slot is free
+/// Returns the new job and a receiver for its result. An addon can only have one
+/// job queued or running at a time, since both would replace the same folders.
+pub(crate) fn enqueue_install(
+    state: &AppState,
+    app: &AppHandle,
+    request: InstallRequest,
+) -> Result<(DownloadJob, oneshot::Receiver<JobResult>), String> {
+    let serialized = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+
+    let job = {
+        let conn = state.db.lock().map_err(|e| e.to_string())?;
+        ensure_not_queued(&conn, &request.slug)?;
+        database::insert_download(
+            &conn,
+            &request.slug,
+            &request.name,
+            &request.version,
+            Some(&serialized),
+        )
+        .map_err(|e| e.to_string())?
+    };
+
+    emit_job_status(app, job.id, &job.slug, DownloadStatus::Pending, None);
+    let result = state.queue.wait(job.id);
+    spawn_job(state, app.clone(), job.id, request);
+
+    Ok((job, result))
+}
+
+/// Record how an operation ended and pass the result to whoever is waiting on it
+pub(crate) fn finish_operation(state: &AppState, op: &Operation, result: JobResult) {
+    if let Ok(conn) = state.db.lock() {
+        let _ = match &result {
+            Ok(_) => {
+                database::set_download_status(&conn, op.id, DownloadStatus::Complete, 1.0, None)
+            }
+            Err(e) if *e == AppError::Cancelled.to_string() => {
+                database::set_download_status(&conn, op.id, DownloadStatus::Cancelled, 0.0, None)
+            }
+            Err(e) => {
+                database::set_download_status(&conn, op.id, DownloadStatus::Failed, 0.0, Some(e))
+            }
+        };
+    }
+    state.queue.finish(op.id, result);
+}
+
+/// Restart downloads left pending or interrupted when the app last closed
+/// Jobs recorded without a request (by older versions) can't be restarted and are marked failed.
+pub fn resume_downloads(app: AppHandle) {
+    let state = app.state::<AppState>();
+
+    let jobs: Vec<(i64, InstallRequest)> = {
+        let Ok(conn) = state.db.lock() else {
+            return;
+        };
+        let _ = database::fail_interrupted_downloads(&conn);
+        let ids = database::get_unfinished_downloads(&conn).unwrap_or_default();
+
+        ids.into_iter()
+            .filter_map(|id| match load_request(&conn, id) {
+                Ok(request) => {
+                    let _ = database::reset_download(&conn, id);
+                    Some((id, request))
+                }
+                Err(e) => {
+                    let _ = database::set_download_status(
+                        &conn,
+                        id,
+                        DownloadStatus::Failed,
+                        0.0,
+                        Some(&e),
+                    );
+                    None
+                }
+            })
+            .collect()
+    };
+
+    for (id, request) in jobs {
+        spawn_job(&state, app.clone(), id, request);
+    }
+}
+
+/// Refuse to queue a job for an addon that already has one queued or running
+fn ensure_not_queued(conn: &rusqlite::Connection, slug: &str) -> Result<(), String> {
+    match database::get_active_download(conn, slug).map_err(|e| e.to_string())? {
+        Some(id) => Err(format!(
+            "{} already has a queued or running download ({})",
+            slug, id
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Read and parse the install request stored with a download job
+fn load_request(conn: &rusqlite::Connection, id: i64) -> Result<InstallRequest, String> {
+    let request = database::get_download_request(conn, id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Download {} has no stored request", id))?;
+    serde_json::from_str(&request).map_err(|e| format!("Invalid stored request: {}", e))
+}
+
+/// Run a queued job in the background, persisting its status as it progresses
+fn spawn_job(state: &AppState, app: AppHandle, id: i64, request: InstallRequest) {
+    let op = state.queue.begin(id);
+
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+
+        let result = state
+            .queue
+            .run(&op, async {
+                if let Ok(conn) = state.db.lock() {
+                    let _ = database::set_download_status(
+                        &conn,
+                        id,
+                        DownloadStatus::Downloading,
+                        0.0,
+                        None,
+                    );
+                }
+                perform_install(&state, &app, &op, &request).await
+            })
+            .await;
+
+        // Cancelled before a download slot came free
+        let result = result.unwrap_or_else(|| {
+            emit_job_status(&app, id, &request.slug, DownloadStatus::Cancelled, None);
+            Err(AppError::Cancelled.to_string())
+        });
+
+        finish_operation(&state, &op, result);
+    });
+}
+
+/// Saves a running job's transfer progress to its download row, so the download
+/// list shows it after a reload; saves are throttled to `PROGRESS_SAVE_INTERVAL`
+pub(crate) struct ProgressRecorder {
+    app: AppHandle,
+    id: i64,
+    last_saved: Mutex<Option<Instant>>,
+}
+
+impl ProgressRecorder {
+    pub fn new(app: AppHandle, id: i64) -> Self {
+        Self {
+            app,
+            id,
+            last_saved: Mutex::new(None),
+        }
+    }
+
+    /// Save the progress of a transfer unless it was saved too recently
+    pub fn record(&self, transfer: &TransferProgress) {
+        let Some(progress) = transfer.fraction() else {
+            return;
+        };
+
+        if let Ok(mut last_saved) = self.last_saved.lock() {
+            let recent = last_saved.is_some_and(|at| at.elapsed() < PROGRESS_SAVE_INTERVAL);
+            if recent && progress < 1.0 {
+                return;
+            }
+            *last_saved = Some(Instant::now());
+        }
+
+        let state = self.app.state::<AppState>();
+        if let Ok(conn) = state.db.lock() {
+            let _ = database::set_download_progress(&conn, self.id, progress);
+        }
+    }
+}
+
+/// Emit a status change for a queued job
+fn emit_job_status(
+    app: &AppHandle,
+    id: i64,
+    slug: &str,
+    status: DownloadStatus,
+    error: Option<&str>,
+) {
+    let _ = app.emit(
+        "download-progress",
+        DownloadProgress {
+            slug: slug.to_string(),
+            status,
+            progress: 0.0,
+            error: error.map(String::from),
+            operation_id: Some(id),
+            ..Default::default()
+        },
+    );
+}
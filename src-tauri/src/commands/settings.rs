@@ -1,5 +1,8 @@
 use crate::models::{AppSettings, Theme};
-use crate::services::database;
+use crate::services::http::{self, HttpConfig};
+use crate::services::secrets::load_github_token;
+use crate::services::throttle::{self, BandwidthConfig};
+use crate::services::{cache, database, GitHubClient};
 use crate::state::AppState;
 use tauri::State;
 
@@ -35,23 +38,66 @@ pub async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, Str
         })
         .unwrap_or(Theme::System);
 
-    let index_url = database::get_setting(&conn, "index_url").ok().flatten();
+    let history_retention = database::get_setting(&conn, "history_retention")
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok());
+
+    let max_concurrent_downloads = database::get_setting(&conn, "max_concurrent_downloads")
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok());
+
+    let cache_max_size_mb = database::get_setting(&conn, "cache_max_size_mb")
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok());
+
+    let network = http::load_http_config(&conn);
+    let bandwidth = throttle::load_bandwidth_config(&conn);
 
     Ok(AppSettings {
         eso_addon_path,
         check_updates_on_startup,
         auto_update,
         theme,
-        index_url,
+        history_retention,
+        max_concurrent_downloads,
+        proxy_url: network.proxy_url,
+        ca_certificate_path: network.ca_certificate_path,
+        connect_timeout_secs: network.connect_timeout_secs,
+        read_timeout_secs: network.read_timeout_secs,
+        cache_max_size_mb,
+        offline_mode: cache::is_offline_mode(&conn),
+        download_rate_limit_kib: bandwidth.user.total_kib,
+        download_rate_limit_per_download_kib: bandwidth.user.per_download_kib,
+        background_rate_limit_kib: bandwidth.background.total_kib,
+        background_rate_limit_per_download_kib: bandwidth.background.per_download_kib,
     })
 }
 
 /// Update application settings
+/// Network settings take effect immediately; invalid ones are rejected before anything is saved.
 #[tauri::command]
 pub async fn update_settings(
     settings: AppSettings,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
+    let network = HttpConfig {
+        connect_timeout_secs: settings.connect_timeout_secs,
+        read_timeout_secs: settings.read_timeout_secs,
+        proxy_url: settings
+            .proxy_url
+            .as_ref()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty()),
+        ca_certificate_path: settings
+            .ca_certificate_path
+            .clone()
+            .filter(|p| !p.as_os_str().is_empty()),
+    };
+    let client = http::build_client(&network).map_err(|e| e.to_string())?;
+
     let conn = state.db.lock().map_err(|e| e.to_string())?;
 
     if let Some(path) = &settings.eso_addon_path {
@@ -88,10 +134,84 @@ pub async fn update_settings(
     };
     database::set_setting(&conn, "theme", theme_str).map_err(|e| e.to_string())?;
 
-    if let Some(url) = &settings.index_url {
-        database::set_setting(&conn, "index_url", url).map_err(|e| e.to_string())?;
+    if let Some(retention) = settings.history_retention {
+        database::set_setting(&conn, "history_retention", &retention.to_string())
+            .map_err(|e| e.to_string())?;
+    }
+
+    if let Some(max) = settings.max_concurrent_downloads {
+        database::set_setting(&conn, "max_concurrent_downloads", &max.max(1).to_string())
+            .map_err(|e| e.to_string())?;
+    }
+
+    if let Some(size) = settings.cache_max_size_mb {
+        database::set_setting(&conn, "cache_max_size_mb", &size.to_string())
+            .map_err(|e| e.to_string())?;
     }
 
+    database::set_setting(
+        &conn,
+        "offline_mode",
+        if settings.offline_mode {
+            "true"
+        } else {
+            "false"
+        },
+    )
+    .map_err(|e| e.to_string())?;
+
+    // Cleared network settings and rate limits fall back to the defaults (unlimited)
+    let limit = |kib: Option<u64>| kib.filter(|&k| k > 0).map(|k| k.to_string());
+    let optional_settings = [
+        (
+            "connect_timeout_secs",
+            network.connect_timeout_secs.map(|s| s.to_string()),
+        ),
+        (
+            "read_timeout_secs",
+            network.read_timeout_secs.map(|s| s.to_string()),
+        ),
+        ("proxy_url", network.proxy_url.clone()),
+        (
+            "ca_certificate_path",
+            network
+                .ca_certificate_path
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string()),
+        ),
+        (
+            "download_rate_limit_kib",
+            limit(settings.download_rate_limit_kib),
+        ),
+        (
+            "download_rate_limit_per_download_kib",
+            limit(settings.download_rate_limit_per_download_kib),
+        ),
+        (
+            "background_rate_limit_kib",
+            limit(settings.background_rate_limit_kib),
+        ),
+        (
+            "background_rate_limit_per_download_kib",
+            limit(settings.background_rate_limit_per_download_kib),
+        ),
+    ];
+    for (key, value) in optional_settings {
+        match value {
+            Some(value) => database::set_setting(&conn, key, &value),
+            None => database::delete_setting(&conn, key),
+        }
+        .map_err(|e| e.to_string())?;
+    }
+
+    state.set_http_client(client);
+    if let Ok(github) = GitHubClient::new(&network, load_github_token(&conn).as_deref()) {
+        state.set_github_client(github);
+    }
+    state
+        .bandwidth
+        .configure(throttle::load_bandwidth_config(&conn));
+
     Ok(())
 }
 
@@ -100,9 +220,18 @@ pub async fn update_settings(
 pub async fn reset_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
     let conn = state.db.lock().map_err(|e| e.to_string())?;
 
-    // Clear all settings
-    conn.execute("DELETE FROM settings", [])
+    // Clear all settings; stored secrets such as the GitHub token are kept
+    conn.execute("DELETE FROM settings WHERE key NOT LIKE 'secret:%'", [])
         .map_err(|e| e.to_string())?;
 
+    if let Ok(client) = http::build_client(&HttpConfig::default()) {
+        state.set_http_client(client);
+    }
+    let token = load_github_token(&conn);
+    if let Ok(github) = GitHubClient::new(&HttpConfig::default(), token.as_deref()) {
+        state.set_github_client(github);
+    }
+    state.bandwidth.configure(BandwidthConfig::default());
+
     Ok(AppSettings::default())
 }
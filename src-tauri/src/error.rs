@@ -1,3 +1,5 @@
+use crate::models::DownloadAttempt;
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -32,10 +34,42 @@ pub enum AppError {
     #[error("Download failed: {0}")]
     Download(String),
 
+    #[error("HTTP {0} from {1}")]
+    HttpStatus(u16, String),
+
+    #[error("All download sources failed: {}", describe_attempts(.0))]
+    SourcesFailed(Vec<DownloadAttempt>),
+
+    #[error("Index signature verification failed: {0}")]
+    SignatureInvalid(String),
+
+    #[error("Integrity check failed: {0}")]
+    Integrity(String),
+
+    #[error("Unsafe archive: {0}")]
+    UnsafeArchive(String),
+
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("GitHub rate limit reached, try again after {reset_at}")]
+    RateLimited { reset_at: DateTime<Utc> },
+
+    #[error("Secret storage error: {0}")]
+    Secret(String),
+
     #[error("{0}")]
     Custom(String),
 }
 
+fn describe_attempts(attempts: &[DownloadAttempt]) -> String {
+    attempts
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 impl From<AppError> for String {
     fn from(error: AppError) -> Self {
         error.to_string()
@@ -19,6 +19,11 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
         .manage(app_state)
+        .setup(|app| {
+            // Pick up downloads that were queued or interrupted when the app last closed
+            commands::resume_downloads(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Addon commands
             commands::get_installed_addons,
@@ -29,6 +34,11 @@ pub fn run() {
             commands::get_addon_directory,
             commands::set_addon_directory,
             commands::resolve_addon_dependencies,
+            commands::get_addon_history,
+            commands::rollback_addon,
+            commands::verify_addon,
+            commands::verify_all,
+            commands::backup_addon_changes,
             // GitHub commands
             commands::add_custom_repo,
             commands::get_custom_repos,
@@ -37,11 +47,29 @@ pub fn run() {
             commands::get_github_repo_preview,
             commands::list_github_branches,
             commands::install_from_github,
+            commands::preview_github_install,
             commands::get_github_release,
+            commands::set_github_token,
+            commands::has_github_token,
+            commands::get_github_rate_limit,
             // Index commands
             commands::fetch_index,
             commands::get_cached_index,
             commands::get_index_stats,
+            commands::get_index_sources,
+            commands::add_index_source,
+            commands::remove_index_source,
+            commands::reorder_index_sources,
+            commands::set_index_source_key,
+            // Download queue commands
+            commands::queue_install,
+            commands::get_downloads,
+            commands::cancel_download,
+            commands::cancel_operation,
+            commands::retry_download,
+            commands::clear_downloads,
+            commands::get_cache_stats,
+            commands::clear_cache,
             // Settings commands
             commands::get_settings,
             commands::update_settings,
@@ -18,6 +18,61 @@ pub struct InstalledAddon {
     pub version_sort_key: Option<i64>,
     /// Commit SHA for branch-based version tracking
     pub commit_sha: Option<String>,
+    /// Slug of the addon this folder was installed with (bundled addons only)
+    pub parent_slug: Option<String>,
+}
+
+/// A file recorded in an installed addon's inventory
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonFile {
+    /// Path relative to the addon folder, with '/' separators
+    pub path: String,
+    pub size: u64,
+    /// SHA-256 of the file contents (lowercase hex)
+    pub sha256: String,
+}
+
+/// Comparison of an installed addon's files on disk with its recorded inventory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyResult {
+    pub slug: String,
+    pub name: String,
+    /// False when no inventory was recorded (e.g. installed before tracking or auto-imported)
+    pub has_inventory: bool,
+    /// Files on disk that were not part of the install
+    pub added: Vec<String>,
+    /// Installed files missing from disk
+    pub removed: Vec<String>,
+    /// Installed files whose contents changed
+    pub modified: Vec<String>,
+}
+
+impl VerifyResult {
+    /// Returns true if any file differs from the install
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty() || !self.modified.is_empty()
+    }
+}
+
+/// A previous install retained for rollback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallHistoryEntry {
+    pub id: i64,
+    pub slug: String,
+    pub name: String,
+    pub version: String,
+    pub source_type: SourceType,
+    pub source_repo: Option<String>,
+    pub version_sort_key: Option<i64>,
+    pub commit_sha: Option<String>,
+    /// Installation info used for this install (index addons only)
+    pub install_info: Option<super::InstallInfo>,
+    /// Path to the retained archive in the app data directory
+    pub archive_path: String,
+    pub installed_at: String,
 }
 
 /// Source type for an installed addon
@@ -126,25 +181,260 @@ pub struct UpdateInfo {
     /// Multiple download sources with fallback (jsDelivr CDN -> GitHub archive)
     #[serde(default)]
     pub download_sources: Vec<super::index::DownloadSource>,
+    /// Installed files were edited or added locally and would be lost by updating
+    #[serde(default)]
+    pub has_local_changes: bool,
+    /// New commits for GitHub addons tracking a branch
+    #[serde(default)]
+    pub commit_range: Option<CommitRange>,
+    /// Version tracking of the new version; pass it unchanged with the install so the
+    /// next check compares against it
+    #[serde(default)]
+    pub version_tracking: Option<VersionTracking>,
 }
 
-/// Download progress event
+/// Version tracking info passed from frontend for simplified update detection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionTracking {
+    /// Pre-computed sort key from index for direct integer comparison
+    pub version_sort_key: Option<i64>,
+    /// Commit SHA for branch-based version tracking
+    pub commit_sha: Option<String>,
+}
+
+/// Commits between the installed and the latest commit of a branch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitRange {
+    pub from_sha: String,
+    pub to_sha: String,
+    /// Number of commits in the range, which can be more than are listed
+    pub total_commits: u32,
+    /// Oldest first
+    pub commits: Vec<CommitSummary>,
+}
+
+/// A commit listed in a `CommitRange`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct CommitSummary {
+    pub sha: String,
+    pub message: String,
+    pub author: Option<String>,
+    pub date: Option<String>,
+}
+
+/// Download progress event
+/// Byte counts, rate and ETA are only set while a file is being transferred.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DownloadProgress {
     pub slug: String,
     pub status: DownloadStatus,
+    /// Fraction complete (0–1); stays 0 while downloading when the size is unknown
     pub progress: f64,
     pub error: Option<String>,
+    /// Id of the install or update this event belongs to, for `cancel_operation`
+    pub operation_id: Option<i64>,
+    pub phase: Option<DownloadPhase>,
+    pub bytes_downloaded: Option<u64>,
+    pub total_bytes: Option<u64>,
+    pub bytes_per_second: Option<f64>,
+    pub eta_secs: Option<u64>,
+    /// Type of the download source in use (e.g. "github_archive")
+    pub source: Option<String>,
+    pub url: Option<String>,
+}
+
+impl DownloadProgress {
+    /// Progress event for a file transfer
+    pub fn transfer(slug: &str, operation_id: Option<i64>, transfer: &TransferProgress) -> Self {
+        Self {
+            slug: slug.to_string(),
+            status: DownloadStatus::Downloading,
+            progress: transfer.fraction().unwrap_or(0.0),
+            operation_id,
+            phase: Some(transfer.phase.clone()),
+            bytes_downloaded: Some(transfer.downloaded),
+            total_bytes: transfer.total,
+            bytes_per_second: transfer.bytes_per_second,
+            eta_secs: transfer.eta_secs,
+            source: transfer.source.clone(),
+            url: Some(transfer.url.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// Status event for a step of an install outside the file transfer
+    pub fn phase(
+        slug: &str,
+        operation_id: Option<i64>,
+        status: DownloadStatus,
+        phase: DownloadPhase,
+    ) -> Self {
+        Self {
+            slug: slug.to_string(),
+            status,
+            operation_id,
+            phase: Some(phase),
+            ..Default::default()
+        }
+    }
+}
+
+/// Step of an install or update
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPhase {
+    /// Looking up where to download from
+    Resolving,
+    #[default]
+    Downloading,
+    /// Checking the downloaded file's size and checksum
+    Verifying,
+    Extracting,
+    /// Recording the installed addon
+    Installing,
+}
+
+/// State of a file transfer, reported by the downloader
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransferProgress {
+    pub url: String,
+    /// Source type of the URL when downloading from a list of sources
+    pub source: Option<String>,
+    pub phase: DownloadPhase,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    pub bytes_per_second: Option<f64>,
+    pub eta_secs: Option<u64>,
+}
+
+impl TransferProgress {
+    /// Fraction complete, when the total size is known
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .filter(|&total| total > 0)
+            .map(|total| (self.downloaded as f64 / total as f64).min(1.0))
+    }
+}
+
+/// One download source that was tried and failed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadAttempt {
+    /// Source type (e.g. "github_archive") or "fallback" for the legacy download URL
+    pub source: String,
+    pub url: String,
+    /// Number of requests made, including retries
+    pub attempts: u32,
+    pub error: String,
+}
+
+impl std::fmt::Display for DownloadAttempt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.source, self.url)?;
+        if self.attempts > 1 {
+            write!(f, " after {} attempts", self.attempts)?;
+        }
+        write!(f, ": {}", self.error)
+    }
 }
 
 /// Status of a download operation
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum DownloadStatus {
+    #[default]
     Pending,
     Downloading,
     Extracting,
     Complete,
     Failed,
+    Cancelled,
+}
+
+impl DownloadStatus {
+    /// Whether the operation has ended, successfully or not
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            DownloadStatus::Complete | DownloadStatus::Failed | DownloadStatus::Cancelled
+        )
+    }
+}
+
+impl std::fmt::Display for DownloadStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadStatus::Pending => write!(f, "pending"),
+            DownloadStatus::Downloading => write!(f, "downloading"),
+            DownloadStatus::Extracting => write!(f, "extracting"),
+            DownloadStatus::Complete => write!(f, "complete"),
+            DownloadStatus::Failed => write!(f, "failed"),
+            DownloadStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::str::FromStr for DownloadStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(DownloadStatus::Pending),
+            "downloading" => Ok(DownloadStatus::Downloading),
+            "extracting" => Ok(DownloadStatus::Extracting),
+            "complete" => Ok(DownloadStatus::Complete),
+            "failed" => Ok(DownloadStatus::Failed),
+            "cancelled" => Ok(DownloadStatus::Cancelled),
+            _ => Err(format!("Unknown download status: {}", s)),
+        }
+    }
+}
+
+/// A queued install or update, persisted in the downloads table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadJob {
+    pub id: i64,
+    pub slug: String,
+    pub name: String,
+    pub version: String,
+    pub status: DownloadStatus,
+    pub progress: f64,
+    pub error: Option<String>,
+    pub created_at: Option<String>,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+/// Size and contents of the archive cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    /// Cached downloads (URL and checksum or commit)
+    pub entries: usize,
+    /// Distinct archives stored; mirrors of the same file share one
+    pub archives: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub path: String,
+}
+
+/// Observed reliability and speed of a download host
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostHealth {
+    /// Host name, with the port when it isn't the default one
+    pub host: String,
+    pub successes: u64,
+    pub failures: u64,
+    /// Failures since the last success
+    pub consecutive_failures: u32,
+    /// Smoothed throughput of successful downloads
+    pub bytes_per_second: Option<f64>,
+    pub last_success_at: Option<String>,
+    pub last_failure_at: Option<String>,
 }
@@ -14,6 +14,24 @@ pub struct AddonIndex {
     /// When we fetched this index (added client-side)
     #[serde(default)]
     pub fetched_at: Option<String>,
+    /// Where this copy of the index came from (added client-side)
+    #[serde(default)]
+    pub fetch_source: Option<IndexFetchSource>,
+    /// Sources that could not be loaded into the merged catalog (added client-side)
+    #[serde(default)]
+    pub source_errors: Vec<String>,
+}
+
+/// How the index returned by `fetch_index` was obtained
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexFetchSource {
+    /// Downloaded from the index URL
+    Network,
+    /// Server answered 304 Not Modified; the cached copy was reused
+    Revalidated,
+    /// Served from the local cache without contacting the server
+    Cache,
 }
 
 /// An addon entry from the index
@@ -40,6 +58,19 @@ pub struct IndexAddon {
     /// ISO 8601 timestamp of when the addon was last updated
     #[serde(default)]
     pub last_updated: Option<String>,
+    /// Which configured index source provided this entry (added client-side)
+    #[serde(default)]
+    pub provenance: Option<IndexProvenance>,
+}
+
+/// Origin of an addon entry in the merged catalog
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexProvenance {
+    pub source_id: i64,
+    pub source_name: String,
+    /// Lower-priority sources that also listed this slug and were overridden
+    #[serde(default)]
+    pub overridden_sources: Vec<String>,
 }
 
 /// Source repository information
@@ -131,11 +162,26 @@ pub struct VersionInfo {
     pub commit_message: Option<String>,
 }
 
-/// Cached index data stored in database
+/// A configured addon index source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSource {
+    pub id: i64,
+    pub name: String,
+    pub url: String,
+    /// Lower value wins when two sources carry the same slug
+    pub priority: i64,
+    pub added_at: String,
+    /// Minisign public key; when set the index must carry a valid detached signature
+    pub public_key: Option<String>,
+}
+
+/// Cached index data stored in database (one row per index source)
 #[derive(Debug, Clone)]
 pub struct IndexCache {
     pub id: i64,
     pub data: String,
     pub fetched_at: String,
     pub etag: Option<String>,
+    pub last_modified: Option<String>,
 }
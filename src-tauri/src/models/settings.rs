@@ -17,8 +17,53 @@ pub struct AppSettings {
     /// Theme preference
     pub theme: Theme,
 
-    /// Index source URL
-    pub index_url: Option<String>,
+    /// Number of previous installs to keep per addon for rollback
+    #[serde(default)]
+    pub history_retention: Option<usize>,
+
+    /// Number of queued downloads that run at the same time (applies after restart)
+    #[serde(default)]
+    pub max_concurrent_downloads: Option<usize>,
+
+    /// HTTP, HTTPS or SOCKS5 proxy for all network requests
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+
+    /// PEM file with additional root certificates to trust
+    #[serde(default)]
+    pub ca_certificate_path: Option<PathBuf>,
+
+    /// Seconds allowed to establish a connection
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+
+    /// Seconds allowed between reads before a request times out
+    #[serde(default)]
+    pub read_timeout_secs: Option<u64>,
+
+    /// Size limit of the downloaded archive cache in megabytes
+    #[serde(default)]
+    pub cache_max_size_mb: Option<u64>,
+
+    /// Install only from the archive cache, without downloading
+    #[serde(default)]
+    pub offline_mode: bool,
+
+    /// Combined download rate of user-initiated installs in KiB/s
+    #[serde(default)]
+    pub download_rate_limit_kib: Option<u64>,
+
+    /// Rate of each user-initiated download in KiB/s
+    #[serde(default)]
+    pub download_rate_limit_per_download_kib: Option<u64>,
+
+    /// Combined download rate of automatic and bulk updates in KiB/s
+    #[serde(default)]
+    pub background_rate_limit_kib: Option<u64>,
+
+    /// Rate of each automatic or bulk update download in KiB/s
+    #[serde(default)]
+    pub background_rate_limit_per_download_kib: Option<u64>,
 }
 
 impl Default for AppSettings {
@@ -28,7 +73,18 @@ impl Default for AppSettings {
             check_updates_on_startup: true,
             auto_update: false,
             theme: Theme::System,
-            index_url: None,
+            history_retention: None,
+            max_concurrent_downloads: None,
+            proxy_url: None,
+            ca_certificate_path: None,
+            connect_timeout_secs: None,
+            read_timeout_secs: None,
+            cache_max_size_mb: None,
+            offline_mode: false,
+            download_rate_limit_kib: None,
+            download_rate_limit_per_download_kib: None,
+            background_rate_limit_kib: None,
+            background_rate_limit_per_download_kib: None,
         }
     }
 }
@@ -0,0 +1,132 @@
+use crate::error::Result;
+use crate::utils::checksum::sha256_hex;
+use crate::utils::paths::get_api_cache_path;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Entries not revalidated for this long are deleted when the cache is opened
+const ENTRY_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A cached API response with the validators to revalidate it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+    /// When the response was last fetched or confirmed unchanged
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedResponse {
+    /// Whether the response is recent enough to use without asking the server
+    pub fn is_fresh(&self, max_age: Duration, now: DateTime<Utc>) -> bool {
+        now.signed_duration_since(self.fetched_at)
+            .to_std()
+            .is_ok_and(|age| age < max_age)
+    }
+}
+
+/// Successful API responses stored on disk as `<key>.json`, keyed by URL
+///
+/// Responses are cached per `scope`, so the answers one token got are never served
+/// to another. Conditional requests built from the stored ETag or Last-Modified
+/// value are answered with 304 by GitHub, which doesn't count against the rate limit.
+#[derive(Debug, Clone)]
+pub struct ApiCache {
+    dir: PathBuf,
+    scope: String,
+}
+
+impl ApiCache {
+    pub fn new(dir: PathBuf, scope: &str) -> Self {
+        Self {
+            dir,
+            scope: scope.to_string(),
+        }
+    }
+
+    /// The cache in the app data directory, with stale entries removed
+    pub fn open(scope: &str) -> Option<Self> {
+        let cache = Self::new(get_api_cache_path()?, scope);
+        cache.prune(ENTRY_MAX_AGE);
+        Some(cache)
+    }
+
+    /// The cached response for a URL
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let data = fs::read(self.entry_path(url)).ok()?;
+        serde_json::from_slice::<CachedResponse>(&data)
+            .ok()
+            .filter(|entry| entry.url == url)
+    }
+
+    /// Store a response, replacing the previous one for its URL
+    pub fn put(&self, entry: &CachedResponse) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.entry_path(&entry.url), serde_json::to_vec(entry)?)?;
+        Ok(())
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        let key = sha256_hex(format!("{}\n{}", self.scope, url).as_bytes());
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Delete entries that were not written for longer than `max_age`
+    fn prune(&self, max_age: Duration) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > max_age);
+            if stale {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(url: &str, fetched_at: DateTime<Utc>) -> CachedResponse {
+        CachedResponse {
+            url: url.to_string(),
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+            body: "{\"tag_name\":\"v1.0\"}".to_string(),
+            fetched_at,
+        }
+    }
+
+    #[test]
+    fn test_responses_are_cached_per_scope() {
+        let temp = tempfile::tempdir().unwrap();
+        let url = "https://api.github.com/repos/o/r/releases/latest";
+        let now = Utc::now();
+
+        let anonymous = ApiCache::new(temp.path().to_path_buf(), "anonymous");
+        assert_eq!(anonymous.get(url), None);
+        anonymous.put(&response(url, now)).unwrap();
+        assert_eq!(anonymous.get(url), Some(response(url, now)));
+
+        let other = ApiCache::new(temp.path().to_path_buf(), "token");
+        assert_eq!(other.get(url), None);
+
+        let entry = anonymous.get(url).unwrap();
+        assert!(entry.is_fresh(Duration::from_secs(60), now));
+        assert!(!entry.is_fresh(Duration::ZERO, now));
+        assert!(!entry.is_fresh(Duration::from_secs(60), now + chrono::Duration::minutes(2)));
+    }
+}
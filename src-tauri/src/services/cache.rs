@@ -0,0 +1,360 @@
+use super::database;
+use crate::error::Result;
+use crate::models::CacheStats;
+use crate::utils::checksum::{
+    hash_file, sha256_hex, verify_file, ChecksumAlgorithm, ExpectedIntegrity,
+};
+use crate::utils::paths::get_archive_cache_path;
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Size limit of the archive cache in megabytes unless configured otherwise
+pub const DEFAULT_CACHE_MAX_SIZE_MB: u64 = 1024;
+
+/// Get the configured archive cache size limit in bytes
+pub fn get_cache_max_bytes(conn: &Connection) -> u64 {
+    database::get_setting(conn, "cache_max_size_mb")
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CACHE_MAX_SIZE_MB)
+        .saturating_mul(1024 * 1024)
+}
+
+/// Whether installs should only use cached archives instead of the network
+pub fn is_offline_mode(conn: &Connection) -> bool {
+    database::get_setting(conn, "offline_mode")
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true")
+}
+
+/// Open the archive cache in the app data directory with the configured size limit
+pub fn open_archive_cache(conn: &Connection) -> Option<ArchiveCache> {
+    Some(ArchiveCache::new(
+        get_archive_cache_path()?,
+        get_cache_max_bytes(conn),
+    ))
+}
+
+/// Archive cache to consult for one download
+pub struct CacheLookup<'a> {
+    pub cache: &'a ArchiveCache,
+    /// Checksum or commit SHA that pins the archive's content; nothing is cached without one
+    pub pin: Option<&'a str>,
+    /// Fail instead of downloading when the archive isn't cached
+    pub offline: bool,
+}
+
+/// A cached download, stored next to the archives as `<key>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    pin: String,
+    /// SHA-256 of the archive, which is stored as `<sha256>.zip`
+    sha256: String,
+    size: u64,
+    /// Milliseconds since the epoch
+    last_used: i64,
+}
+
+/// Downloaded archives keyed by URL and pin, stored once per distinct content
+///
+/// Several URLs (e.g. mirrors) can share one archive file. When the cache grows past
+/// its limit, the least recently used entries are evicted first.
+pub struct ArchiveCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl ArchiveCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self { dir, max_bytes }
+    }
+
+    /// Cached archive for a URL pinned to `pin`, marking it as recently used
+    pub fn get(&self, url: &str, pin: &str) -> Option<PathBuf> {
+        let entry_path = self.entry_path(url, pin);
+        let mut entry: CacheEntry =
+            serde_json::from_str(&fs::read_to_string(&entry_path).ok()?).ok()?;
+        if entry.url != url || entry.pin != pin {
+            return None;
+        }
+
+        let archive = self.archive_path(&entry.sha256);
+        if fs::metadata(&archive).ok()?.len() != entry.size {
+            self.remove(url, pin);
+            return None;
+        }
+
+        entry.last_used = Utc::now().timestamp_millis();
+        if let Ok(json) = serde_json::to_string(&entry) {
+            let _ = fs::write(&entry_path, json);
+        }
+        Some(archive)
+    }
+
+    /// Copy the first cached archive of `urls` that passes the integrity check to `target_path`
+    /// Returns the URL it was cached for; entries that fail the check are removed.
+    pub fn restore(
+        &self,
+        urls: &[&str],
+        pin: &str,
+        target_path: &Path,
+        integrity: Option<&ExpectedIntegrity>,
+    ) -> Option<String> {
+        for url in urls {
+            let Some(archive) = self.get(url, pin) else {
+                continue;
+            };
+
+            let restored = fs::copy(&archive, target_path).is_ok()
+                && integrity.is_none_or(|i| verify_file(target_path, i).is_ok());
+            if restored {
+                return Some(url.to_string());
+            }
+            self.remove(url, pin);
+        }
+        None
+    }
+
+    /// Add a downloaded archive to the cache, then evict entries over the size limit
+    pub fn put(&self, url: &str, pin: &str, archive: &Path) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let sha256 = hash_file(archive, ChecksumAlgorithm::Sha256)?;
+        let cached = self.archive_path(&sha256);
+        let entry = CacheEntry {
+            url: url.to_string(),
+            pin: pin.to_string(),
+            size: fs::metadata(archive)?.len(),
+            sha256,
+            last_used: Utc::now().timestamp_millis(),
+        };
+
+        // The entry is written first so a concurrent eviction never sees the new
+        // archive unreferenced, and the archive is copied under a temporary name so
+        // a half-written one is never served
+        fs::write(self.entry_path(url, pin), serde_json::to_string(&entry)?)?;
+        if !cached.exists() {
+            let partial = cached.with_extension("zip.tmp");
+            fs::copy(archive, &partial)?;
+            fs::rename(&partial, &cached)?;
+        }
+
+        self.evict()?;
+        Ok(())
+    }
+
+    /// Forget a cached URL, deleting its archive if nothing else uses it
+    pub fn remove(&self, url: &str, pin: &str) {
+        let _ = fs::remove_file(self.entry_path(url, pin));
+        let _ = self.evict();
+    }
+
+    /// Number of entries and archives and their total size
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries();
+        let archives = self.archives();
+
+        CacheStats {
+            entries: entries.len(),
+            archives: archives.len(),
+            total_bytes: archives.values().sum(),
+            max_bytes: self.max_bytes,
+            path: self.dir.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Delete every cached archive, returning the number of bytes freed
+    pub fn clear(&self) -> Result<u64> {
+        let freed = self.archives().values().sum();
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(freed)
+    }
+
+    /// Remove the least recently used entries until the cache fits its limit,
+    /// along with archives no entry refers to any more
+    fn evict(&self) -> Result<()> {
+        let mut entries = self.entries();
+        entries.sort_by_key(|(_, entry)| entry.last_used);
+
+        let mut archives = self.archives();
+        let mut users: HashMap<String, usize> = HashMap::new();
+        for (_, entry) in &entries {
+            *users.entry(entry.sha256.clone()).or_default() += 1;
+        }
+
+        // Unreferenced archives go first (left over from removed or evicted entries)
+        archives.retain(|sha256, _| {
+            let used = users.contains_key(sha256);
+            if !used {
+                let _ = fs::remove_file(self.archive_path(sha256));
+            }
+            used
+        });
+
+        let mut total: u64 = archives.values().sum();
+        for (path, entry) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            fs::remove_file(&path)?;
+
+            let remaining = users.entry(entry.sha256.clone()).or_default();
+            *remaining -= 1;
+            if *remaining == 0 {
+                fs::remove_file(self.archive_path(&entry.sha256))?;
+                total = total.saturating_sub(archives.remove(&entry.sha256).unwrap_or(0));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// All entries with the path of their file
+    fn entries(&self) -> Vec<(PathBuf, CacheEntry)> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        dir.flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let entry = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+                Some((path, entry))
+            })
+            .collect()
+    }
+
+    /// Size of every stored archive, by SHA-256
+    fn archives(&self) -> HashMap<String, u64> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return HashMap::new();
+        };
+
+        dir.flatten()
+            .filter_map(|e| {
+                let path = e.path();
+                if path.extension().is_none_or(|ext| ext != "zip") {
+                    return None;
+                }
+                let sha256 = path.file_stem()?.to_str()?.to_string();
+                Some((sha256, e.metadata().ok()?.len()))
+            })
+            .collect()
+    }
+
+    fn entry_path(&self, url: &str, pin: &str) -> PathBuf {
+        let key = &sha256_hex(format!("{}\n{}", url, pin).as_bytes())[..32];
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn archive_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(format!("{}.zip", sha256))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn archive(dir: &Path, name: &str, size: usize) -> PathBuf {
+        let path = dir.join(name);
+        let byte = name.bytes().next().unwrap_or(0);
+        fs::write(&path, vec![byte; size]).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_cache_is_keyed_by_url_and_pin() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = ArchiveCache::new(temp.path().join("cache"), 1024 * 1024);
+        let source = archive(temp.path(), "a.zip", 100);
+
+        cache
+            .put("https://a.example/x.zip", "sha256:1", &source)
+            .unwrap();
+        cache
+            .put("https://b.example/x.zip", "sha256:1", &source)
+            .unwrap();
+
+        assert!(cache.get("https://a.example/x.zip", "sha256:1").is_some());
+        assert!(cache.get("https://a.example/x.zip", "sha256:2").is_none());
+        assert!(cache.get("https://c.example/x.zip", "sha256:1").is_none());
+
+        // Both mirrors share one archive
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.archives, 1);
+        assert_eq!(stats.total_bytes, 100);
+
+        let target = temp.path().join("restored.zip");
+        let restored = cache.restore(
+            &["https://c.example/x.zip", "https://b.example/x.zip"],
+            "sha256:1",
+            &target,
+            None,
+        );
+        assert_eq!(restored.as_deref(), Some("https://b.example/x.zip"));
+        assert_eq!(fs::read(&target).unwrap(), fs::read(&source).unwrap());
+
+        assert_eq!(cache.clear().unwrap(), 100);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_least_recently_used_entries_are_evicted() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = ArchiveCache::new(temp.path().join("cache"), 250);
+        let a = archive(temp.path(), "a.zip", 100);
+        let b = archive(temp.path(), "b.zip", 100);
+        let c = archive(temp.path(), "c.zip", 100);
+
+        cache.put("https://example.com/a.zip", "1", &a).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        cache.put("https://example.com/b.zip", "1", &b).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(cache.get("https://example.com/a.zip", "1").is_some());
+        std::thread::sleep(Duration::from_millis(5));
+        cache.put("https://example.com/c.zip", "1", &c).unwrap();
+
+        assert!(cache.get("https://example.com/a.zip", "1").is_some());
+        assert!(cache.get("https://example.com/b.zip", "1").is_none());
+        assert!(cache.get("https://example.com/c.zip", "1").is_some());
+        assert_eq!(cache.stats().total_bytes, 200);
+    }
+
+    #[test]
+    fn test_corrupt_archive_is_not_restored() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = ArchiveCache::new(temp.path().join("cache"), 1024 * 1024);
+        let source = archive(temp.path(), "a.zip", 100);
+        cache
+            .put("https://example.com/a.zip", "pin", &source)
+            .unwrap();
+
+        let integrity = ExpectedIntegrity {
+            checksum: Some(format!("sha256:{}", sha256_hex(b"something else"))),
+            file_size: None,
+        };
+        let target = temp.path().join("restored.zip");
+        let restored = cache.restore(
+            &["https://example.com/a.zip"],
+            "pin",
+            &target,
+            Some(&integrity),
+        );
+
+        assert!(restored.is_none());
+        assert_eq!(cache.stats().archives, 0);
+    }
+}
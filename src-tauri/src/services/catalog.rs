@@ -0,0 +1,207 @@
+use crate::error::Result;
+use crate::models::{AddonIndex, IndexProvenance, IndexSource};
+use crate::services::database;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// Merge the indexes of several sources into one catalog
+///
+/// `sources` must be in priority order. When two sources carry the same slug the
+/// entry from the higher-priority (earlier) source wins, and the names of the
+/// overridden sources are recorded in its provenance.
+pub fn merge_indexes(sources: Vec<(IndexSource, AddonIndex)>) -> AddonIndex {
+    let mut merged = AddonIndex {
+        version: String::new(),
+        generated_at: String::new(),
+        addon_count: 0,
+        addons: Vec::new(),
+        fetched_at: None,
+        fetch_source: None,
+        source_errors: Vec::new(),
+    };
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for (source, index) in sources {
+        if merged.version.is_empty() {
+            merged.version = index.version.clone();
+        }
+        if index.generated_at > merged.generated_at {
+            merged.generated_at = index.generated_at.clone();
+        }
+
+        for mut addon in index.addons {
+            if let Some(&pos) = positions.get(&addon.slug) {
+                if let Some(provenance) = merged.addons[pos].provenance.as_mut() {
+                    provenance.overridden_sources.push(source.name.clone());
+                }
+                continue;
+            }
+
+            addon.provenance = Some(IndexProvenance {
+                source_id: source.id,
+                source_name: source.name.clone(),
+                overridden_sources: Vec::new(),
+            });
+            positions.insert(addon.slug.clone(), merged.addons.len());
+            merged.addons.push(addon);
+        }
+    }
+
+    merged.addon_count = merged.addons.len();
+    merged
+}
+
+/// Build the merged catalog from the cached index of every source
+///
+/// Returns `None` when no source has a usable cached index. `fetched_at` is set
+/// to the oldest cache timestamp so callers can tell how stale the catalog is.
+pub fn load_cached_catalog(conn: &Connection) -> Result<Option<AddonIndex>> {
+    let mut indexes = Vec::new();
+    let mut oldest_fetch: Option<String> = None;
+
+    for source in database::get_index_sources(conn)? {
+        let Some(cache) = database::get_cached_index(conn, source.id)? else {
+            continue;
+        };
+        let Ok(index) = serde_json::from_str::<AddonIndex>(&cache.data) else {
+            continue;
+        };
+
+        if oldest_fetch
+            .as_ref()
+            .map(|oldest| cache.fetched_at < *oldest)
+            .unwrap_or(true)
+        {
+            oldest_fetch = Some(cache.fetched_at.clone());
+        }
+        indexes.push((source, index));
+    }
+
+    if indexes.is_empty() {
+        return Ok(None);
+    }
+
+    let mut catalog = merge_indexes(indexes);
+    catalog.fetched_at = oldest_fetch;
+    Ok(Some(catalog))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{AddonCompatibility, AddonSource, IndexAddon, InstallInfo};
+
+    fn source(id: i64, name: &str) -> IndexSource {
+        IndexSource {
+            id,
+            name: name.to_string(),
+            url: format!("https://example.com/{}/index.json", id),
+            priority: id,
+            added_at: String::new(),
+            public_key: None,
+        }
+    }
+
+    fn addon(slug: &str, description: &str) -> IndexAddon {
+        IndexAddon {
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            description: description.to_string(),
+            authors: vec![],
+            license: None,
+            tags: vec![],
+            url: None,
+            source: AddonSource {
+                source_type: "github".to_string(),
+                repo: "test/repo".to_string(),
+                branch: "main".to_string(),
+                path: None,
+            },
+            compatibility: AddonCompatibility {
+                api_version: None,
+                game_versions: vec![],
+                required_dependencies: vec![],
+                optional_dependencies: vec![],
+            },
+            install: InstallInfo {
+                method: "branch".to_string(),
+                extract_path: None,
+                target_folder: slug.to_string(),
+                excludes: vec![],
+            },
+            latest_release: None,
+            version_info: None,
+            download_sources: vec![],
+            last_updated: None,
+            provenance: None,
+        }
+    }
+
+    fn index(generated_at: &str, addons: Vec<IndexAddon>) -> AddonIndex {
+        AddonIndex {
+            version: "1.0".to_string(),
+            generated_at: generated_at.to_string(),
+            addon_count: addons.len(),
+            addons,
+            fetched_at: None,
+            fetch_source: None,
+            source_errors: vec![],
+        }
+    }
+
+    #[test]
+    fn test_merge_prefers_higher_priority_source() {
+        let merged = merge_indexes(vec![
+            (
+                source(1, "Guild"),
+                index("2024-01-01", vec![addon("lib-shared", "guild build")]),
+            ),
+            (
+                source(2, "Community"),
+                index(
+                    "2024-02-01",
+                    vec![
+                        addon("lib-shared", "community build"),
+                        addon("combat-metrics", "community"),
+                    ],
+                ),
+            ),
+        ]);
+
+        assert_eq!(merged.addon_count, 2);
+        assert_eq!(merged.generated_at, "2024-02-01");
+
+        let shared = &merged.addons[0];
+        assert_eq!(shared.description, "guild build");
+        let provenance = shared.provenance.as_ref().unwrap();
+        assert_eq!(provenance.source_id, 1);
+        assert_eq!(provenance.overridden_sources, vec!["Community"]);
+
+        let metrics = &merged.addons[1];
+        assert_eq!(
+            metrics.provenance.as_ref().unwrap().source_name,
+            "Community"
+        );
+    }
+
+    #[test]
+    fn test_load_cached_catalog_merges_sources() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        let guild = database::insert_index_source(&conn, "Guild", "https://guild/index.json", None)
+            .unwrap();
+
+        // Put the guild index ahead of the seeded community index
+        database::reorder_index_sources(&conn, &[guild.id, 1]).unwrap();
+
+        let community = serde_json::to_string(&index("2024-01-01", vec![addon("a", "c")])).unwrap();
+        let private = serde_json::to_string(&index("2024-01-01", vec![addon("a", "g")])).unwrap();
+        database::update_cached_index(&conn, 1, &community, None, None).unwrap();
+        database::update_cached_index(&conn, guild.id, &private, None, None).unwrap();
+
+        let catalog = load_cached_catalog(&conn).unwrap().unwrap();
+        assert_eq!(catalog.addons.len(), 1);
+        assert_eq!(catalog.addons[0].description, "g");
+        assert!(catalog.fetched_at.is_some());
+    }
+}
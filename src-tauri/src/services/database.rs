@@ -1,5 +1,8 @@
 use crate::error::{AppError, Result};
-use crate::models::{CustomRepo, InstalledAddon, ReleaseType, SourceType};
+use crate::models::{
+    AddonFile, CustomRepo, DownloadJob, DownloadStatus, HostHealth, IndexCache, IndexSource,
+    InstallHistoryEntry, InstalledAddon, ReleaseType, SourceType,
+};
 use crate::utils::paths::get_database_path;
 use chrono::Utc;
 use rusqlite::{params, Connection};
@@ -21,7 +24,7 @@ pub fn init_database() -> Result<Connection> {
 }
 
 /// Run database migrations
-fn run_migrations(conn: &Connection) -> Result<()> {
+pub(crate) fn run_migrations(conn: &Connection) -> Result<()> {
     conn.execute_batch(include_str!("../../migrations/001_initial.sql"))?;
 
     // Run migration 002 - add version tracking columns
@@ -35,6 +38,35 @@ fn run_migrations(conn: &Connection) -> Result<()> {
         [],
     );
 
+    // Run migration 003 - install history for rollback
+    conn.execute_batch(include_str!("../../migrations/003_install_history.sql"))?;
+
+    // Run migration 004 - store Last-Modified for conditional index requests
+    let _ = conn.execute("ALTER TABLE index_cache ADD COLUMN last_modified TEXT", []);
+
+    // Run migration 005 - multiple index sources
+    conn.execute_batch(include_str!("../../migrations/005_index_sources.sql"))?;
+
+    // Run migration 006 - optional signing key per index source
+    let _ = conn.execute("ALTER TABLE index_sources ADD COLUMN public_key TEXT", []);
+
+    // Run migration 007 - link bundled addon folders to the addon they came with
+    let _ = conn.execute(
+        "ALTER TABLE installed_addons ADD COLUMN parent_slug TEXT",
+        [],
+    );
+
+    // Run migration 008 - per-addon installed file inventory
+    conn.execute_batch(include_str!("../../migrations/008_addon_files.sql"))?;
+
+    // Run migration 009 - persist install requests for the download queue
+    let _ = conn.execute("ALTER TABLE downloads ADD COLUMN addon_name TEXT", []);
+    let _ = conn.execute("ALTER TABLE downloads ADD COLUMN request TEXT", []);
+    let _ = conn.execute("ALTER TABLE downloads ADD COLUMN created_at TEXT", []);
+
+    // Run migration 010 - download host health for source ordering
+    conn.execute_batch(include_str!("../../migrations/010_host_health.sql"))?;
+
     Ok(())
 }
 
@@ -42,36 +74,41 @@ fn run_migrations(conn: &Connection) -> Result<()> {
 // Installed Addons
 // ============================================================================
 
+/// Columns selected for an installed addon row, in the order read by `installed_from_row`
+const INSTALLED_COLUMNS: &str = "id, slug, name, installed_version, source_type, source_repo,
+                installed_at, updated_at, auto_update, manifest_path,
+                version_sort_key, commit_sha, parent_slug";
+
+fn installed_from_row(row: &rusqlite::Row) -> rusqlite::Result<InstalledAddon> {
+    Ok(InstalledAddon {
+        id: row.get(0)?,
+        slug: row.get(1)?,
+        name: row.get(2)?,
+        installed_version: row.get(3)?,
+        source_type: row
+            .get::<_, String>(4)?
+            .parse()
+            .unwrap_or(SourceType::Local),
+        source_repo: row.get(5)?,
+        installed_at: row.get(6)?,
+        updated_at: row.get(7)?,
+        auto_update: row.get(8)?,
+        manifest_path: row.get(9)?,
+        version_sort_key: row.get(10)?,
+        commit_sha: row.get(11)?,
+        parent_slug: row.get(12)?,
+    })
+}
+
 /// Get all installed addons
 pub fn get_all_installed(conn: &Connection) -> Result<Vec<InstalledAddon>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, slug, name, installed_version, source_type, source_repo,
-                installed_at, updated_at, auto_update, manifest_path,
-                version_sort_key, commit_sha
-         FROM installed_addons
-         ORDER BY name ASC",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM installed_addons ORDER BY name ASC",
+        INSTALLED_COLUMNS
+    ))?;
 
     let addons = stmt
-        .query_map([], |row| {
-            Ok(InstalledAddon {
-                id: row.get(0)?,
-                slug: row.get(1)?,
-                name: row.get(2)?,
-                installed_version: row.get(3)?,
-                source_type: row
-                    .get::<_, String>(4)?
-                    .parse()
-                    .unwrap_or(SourceType::Local),
-                source_repo: row.get(5)?,
-                installed_at: row.get(6)?,
-                updated_at: row.get(7)?,
-                auto_update: row.get(8)?,
-                manifest_path: row.get(9)?,
-                version_sort_key: row.get(10)?,
-                commit_sha: row.get(11)?,
-            })
-        })?
+        .query_map([], installed_from_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
     Ok(addons)
@@ -79,39 +116,30 @@ pub fn get_all_installed(conn: &Connection) -> Result<Vec<InstalledAddon>> {
 
 /// Get an installed addon by slug
 pub fn get_installed_by_slug(conn: &Connection, slug: &str) -> Result<Option<InstalledAddon>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, slug, name, installed_version, source_type, source_repo,
-                installed_at, updated_at, auto_update, manifest_path,
-                version_sort_key, commit_sha
-         FROM installed_addons
-         WHERE slug = ?1",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM installed_addons WHERE slug = ?1",
+        INSTALLED_COLUMNS
+    ))?;
 
-    let addon = stmt
-        .query_row([slug], |row| {
-            Ok(InstalledAddon {
-                id: row.get(0)?,
-                slug: row.get(1)?,
-                name: row.get(2)?,
-                installed_version: row.get(3)?,
-                source_type: row
-                    .get::<_, String>(4)?
-                    .parse()
-                    .unwrap_or(SourceType::Local),
-                source_repo: row.get(5)?,
-                installed_at: row.get(6)?,
-                updated_at: row.get(7)?,
-                auto_update: row.get(8)?,
-                manifest_path: row.get(9)?,
-                version_sort_key: row.get(10)?,
-                commit_sha: row.get(11)?,
-            })
-        })
-        .optional()?;
+    let addon = stmt.query_row([slug], installed_from_row).optional()?;
 
     Ok(addon)
 }
 
+/// Get the addons installed as part of another addon's archive
+pub fn get_linked_addons(conn: &Connection, parent_slug: &str) -> Result<Vec<InstalledAddon>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM installed_addons WHERE parent_slug = ?1 ORDER BY name ASC",
+        INSTALLED_COLUMNS
+    ))?;
+
+    let addons = stmt
+        .query_map([parent_slug], installed_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(addons)
+}
+
 /// Insert a new installed addon
 #[allow(clippy::too_many_arguments)]
 pub fn insert_installed(
@@ -152,12 +180,362 @@ pub fn insert_installed(
     get_installed_by_slug(conn, slug)?.ok_or(AppError::AddonNotFound(slug.into()))
 }
 
-/// Delete an installed addon
+/// Insert or update an addon folder installed from `parent`'s archive
+/// The linked row shares the parent's source so it is updated and removed with it.
+pub fn insert_linked_installed(
+    conn: &Connection,
+    parent: &InstalledAddon,
+    slug: &str,
+    name: &str,
+    version: &str,
+    manifest_path: &str,
+) -> Result<InstalledAddon> {
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO installed_addons (slug, name, installed_version, source_type, source_repo, installed_at, updated_at, manifest_path, parent_slug)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(slug) DO UPDATE SET
+             name = excluded.name,
+             installed_version = excluded.installed_version,
+             updated_at = excluded.updated_at,
+             manifest_path = excluded.manifest_path,
+             parent_slug = excluded.parent_slug",
+        params![
+            slug,
+            name,
+            version,
+            parent.source_type.to_string(),
+            parent.source_repo,
+            &now,
+            &now,
+            manifest_path,
+            parent.slug
+        ],
+    )?;
+
+    get_installed_by_slug(conn, slug)?.ok_or(AppError::AddonNotFound(slug.into()))
+}
+
+/// Delete an installed addon and its file inventory
 pub fn delete_installed(conn: &Connection, slug: &str) -> Result<()> {
     conn.execute("DELETE FROM installed_addons WHERE slug = ?1", [slug])?;
+    conn.execute("DELETE FROM addon_files WHERE slug = ?1", [slug])?;
+    Ok(())
+}
+
+// ============================================================================
+// Addon Files
+// ============================================================================
+
+/// Get the recorded file inventory of an installed addon
+pub fn get_addon_files(conn: &Connection, slug: &str) -> Result<Vec<AddonFile>> {
+    let mut stmt = conn.prepare(
+        "SELECT path, size, sha256
+         FROM addon_files
+         WHERE slug = ?1
+         ORDER BY path ASC",
+    )?;
+
+    let files = stmt
+        .query_map([slug], |row| {
+            Ok(AddonFile {
+                path: row.get(0)?,
+                size: row.get::<_, i64>(1)? as u64,
+                sha256: row.get(2)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// Replace the file inventory of an installed addon
+pub fn replace_addon_files(conn: &Connection, slug: &str, files: &[AddonFile]) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+
+    tx.execute("DELETE FROM addon_files WHERE slug = ?1", [slug])?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO addon_files (slug, path, size, sha256) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for file in files {
+            stmt.execute(params![slug, file.path, file.size as i64, file.sha256])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+// ============================================================================
+// Install History
+// ============================================================================
+
+/// Get the install history for an addon, newest first
+pub fn get_install_history(conn: &Connection, slug: &str) -> Result<Vec<InstallHistoryEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, slug, name, version, source_type, source_repo, version_sort_key,
+                commit_sha, install_info, archive_path, installed_at
+         FROM install_history
+         WHERE slug = ?1
+         ORDER BY installed_at DESC, id DESC",
+    )?;
+
+    let entries = stmt
+        .query_map([slug], |row| {
+            Ok(InstallHistoryEntry {
+                id: row.get(0)?,
+                slug: row.get(1)?,
+                name: row.get(2)?,
+                version: row.get(3)?,
+                source_type: row
+                    .get::<_, String>(4)?
+                    .parse()
+                    .unwrap_or(SourceType::Local),
+                source_repo: row.get(5)?,
+                version_sort_key: row.get(6)?,
+                commit_sha: row.get(7)?,
+                install_info: row
+                    .get::<_, Option<String>>(8)?
+                    .and_then(|data| serde_json::from_str(&data).ok()),
+                archive_path: row.get(9)?,
+                installed_at: row.get(10)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// Record a completed install in the history table
+pub fn insert_install_history(conn: &Connection, entry: &InstallHistoryEntry) -> Result<i64> {
+    let install_info = entry
+        .install_info
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()?;
+
+    conn.execute(
+        "INSERT INTO install_history (slug, name, version, source_type, source_repo, version_sort_key, commit_sha, install_info, archive_path, installed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            entry.slug,
+            entry.name,
+            entry.version,
+            entry.source_type.to_string(),
+            entry.source_repo,
+            entry.version_sort_key,
+            entry.commit_sha,
+            install_info,
+            entry.archive_path,
+            entry.installed_at
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Delete a single install history entry
+pub fn delete_install_history_entry(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM install_history WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+// ============================================================================
+// Downloads
+// ============================================================================
+
+/// Columns selected for a download job row, in the order read by `download_from_row`
+const DOWNLOAD_COLUMNS: &str = "id, addon_slug, addon_name, version, status, progress,
+                error_message, created_at, started_at, completed_at";
+
+fn download_from_row(row: &rusqlite::Row) -> rusqlite::Result<DownloadJob> {
+    let slug: String = row.get(1)?;
+    Ok(DownloadJob {
+        id: row.get(0)?,
+        name: row
+            .get::<_, Option<String>>(2)?
+            .unwrap_or_else(|| slug.clone()),
+        slug,
+        version: row.get(3)?,
+        status: row
+            .get::<_, String>(4)?
+            .parse()
+            .unwrap_or(DownloadStatus::Failed),
+        progress: row.get(5)?,
+        error: row.get(6)?,
+        created_at: row.get(7)?,
+        started_at: row.get(8)?,
+        completed_at: row.get(9)?,
+    })
+}
+
+/// Get all download jobs, newest first
+pub fn get_downloads(conn: &Connection) -> Result<Vec<DownloadJob>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM downloads ORDER BY id DESC",
+        DOWNLOAD_COLUMNS
+    ))?;
+
+    let jobs = stmt
+        .query_map([], download_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(jobs)
+}
+
+/// Get a download job by id
+pub fn get_download(conn: &Connection, id: i64) -> Result<Option<DownloadJob>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM downloads WHERE id = ?1",
+        DOWNLOAD_COLUMNS
+    ))?;
+
+    let job = stmt.query_row([id], download_from_row).optional()?;
+
+    Ok(job)
+}
+
+/// Record a new download job
+/// Queued jobs store their serialized install request so they can be resumed and
+/// retried; jobs without one (e.g. GitHub installs) are only tracked.
+pub fn insert_download(
+    conn: &Connection,
+    slug: &str,
+    name: &str,
+    version: &str,
+    request: Option<&str>,
+) -> Result<DownloadJob> {
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO downloads (addon_slug, addon_name, version, status, progress, request, created_at)
+         VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6)",
+        params![
+            slug,
+            name,
+            version,
+            DownloadStatus::Pending.to_string(),
+            request,
+            &now
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    get_download(conn, id)?.ok_or(AppError::Custom(format!("Download not found: {}", id)))
+}
+
+/// Get the serialized install request of a download job
+pub fn get_download_request(conn: &Connection, id: i64) -> Result<Option<String>> {
+    let request = conn
+        .query_row("SELECT request FROM downloads WHERE id = ?1", [id], |row| {
+            row.get::<_, Option<String>>(0)
+        })
+        .optional()?
+        .flatten();
+
+    Ok(request)
+}
+
+/// Update the status of a download job
+/// Starting a job records `started_at`; finishing it records `completed_at`.
+pub fn set_download_status(
+    conn: &Connection,
+    id: i64,
+    status: DownloadStatus,
+    progress: f64,
+    error: Option<&str>,
+) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    let finished = status.is_finished();
+
+    conn.execute(
+        "UPDATE downloads SET
+             status = ?1,
+             progress = ?2,
+             error_message = ?3,
+             started_at = CASE WHEN ?1 = 'downloading' THEN COALESCE(started_at, ?4) ELSE started_at END,
+             completed_at = CASE WHEN ?5 THEN ?4 ELSE NULL END
+         WHERE id = ?6",
+        params![status.to_string(), progress, error, &now, finished, id],
+    )?;
     Ok(())
 }
 
+/// Save the progress of a running download job
+/// Jobs that have already finished keep the progress they finished with.
+pub fn set_download_progress(conn: &Connection, id: i64, progress: f64) -> Result<()> {
+    conn.execute(
+        "UPDATE downloads SET progress = ?1
+         WHERE id = ?2 AND status IN ('pending', 'downloading', 'extracting')",
+        params![progress, id],
+    )?;
+    Ok(())
+}
+
+/// Reset a download job so it runs again from the start
+pub fn reset_download(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE downloads SET
+             status = 'pending', progress = 0, error_message = NULL,
+             started_at = NULL, completed_at = NULL
+         WHERE id = ?1",
+        [id],
+    )?;
+    Ok(())
+}
+
+/// Get the ids of queued jobs that did not finish, oldest first
+/// Jobs from before the queue existed have no stored request and are skipped.
+pub fn get_unfinished_downloads(conn: &Connection) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM downloads
+         WHERE status IN ('pending', 'downloading', 'extracting') AND request IS NOT NULL
+         ORDER BY id ASC",
+    )?;
+
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(ids)
+}
+
+/// Get the id of an addon's queued or running download job, if it has one
+pub fn get_active_download(conn: &Connection, slug: &str) -> Result<Option<i64>> {
+    let id = conn
+        .query_row(
+            "SELECT id FROM downloads
+             WHERE addon_slug = ?1 AND status IN ('pending', 'downloading', 'extracting')
+             ORDER BY id ASC LIMIT 1",
+            [slug],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(id)
+}
+
+/// Mark unfinished jobs that cannot be resumed (no stored request) as failed
+pub fn fail_interrupted_downloads(conn: &Connection) -> Result<usize> {
+    let failed = conn.execute(
+        "UPDATE downloads SET status = 'failed', error_message = 'Interrupted', completed_at = ?1
+         WHERE status IN ('pending', 'downloading', 'extracting') AND request IS NULL",
+        [Utc::now().to_rfc3339()],
+    )?;
+    Ok(failed)
+}
+
+/// Delete finished (complete, failed or cancelled) download jobs, returning how many were removed
+pub fn delete_finished_downloads(conn: &Connection) -> Result<usize> {
+    let removed = conn.execute(
+        "DELETE FROM downloads WHERE status IN ('complete', 'failed', 'cancelled')",
+        [],
+    )?;
+    Ok(removed)
+}
+
 // ============================================================================
 // Custom Repositories
 // ============================================================================
@@ -234,38 +612,205 @@ pub fn delete_custom_repo(conn: &Connection, repo: &str) -> Result<()> {
     Ok(())
 }
 
+// ============================================================================
+// Index Sources
+// ============================================================================
+
+/// Get all index sources in priority order
+pub fn get_index_sources(conn: &Connection) -> Result<Vec<IndexSource>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, url, priority, added_at, public_key
+         FROM index_sources
+         ORDER BY priority ASC, id ASC",
+    )?;
+
+    let sources = stmt
+        .query_map([], |row| {
+            Ok(IndexSource {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                url: row.get(2)?,
+                priority: row.get(3)?,
+                added_at: row.get(4)?,
+                public_key: row.get(5)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(sources)
+}
+
+/// Insert an index source at the lowest priority
+pub fn insert_index_source(
+    conn: &Connection,
+    name: &str,
+    url: &str,
+    public_key: Option<&str>,
+) -> Result<IndexSource> {
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO index_sources (name, url, priority, added_at, public_key)
+         VALUES (?1, ?2, (SELECT COALESCE(MAX(priority) + 1, 0) FROM index_sources), ?3, ?4)",
+        params![name, url, &now, public_key],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    get_index_sources(conn)?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| AppError::Custom(format!("Index source {} not found", id)))
+}
+
+/// Delete an index source and its cached data
+pub fn delete_index_source(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM index_sources WHERE id = ?1", [id])?;
+    conn.execute("DELETE FROM index_cache WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Set or clear the signing key of an index source
+/// The source's cached index is dropped because it was not verified with the new key.
+pub fn set_index_source_key(conn: &Connection, id: i64, public_key: Option<&str>) -> Result<()> {
+    conn.execute(
+        "UPDATE index_sources SET public_key = ?1 WHERE id = ?2",
+        params![public_key, id],
+    )?;
+    conn.execute("DELETE FROM index_cache WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Set source priorities from their position in `ids`
+pub fn reorder_index_sources(conn: &Connection, ids: &[i64]) -> Result<()> {
+    for (priority, id) in ids.iter().enumerate() {
+        conn.execute(
+            "UPDATE index_sources SET priority = ?1 WHERE id = ?2",
+            params![priority as i64, id],
+        )?;
+    }
+    Ok(())
+}
+
 // ============================================================================
 // Index Cache
 // ============================================================================
 
-/// Get the cached index data
-pub fn get_cached_index(conn: &Connection) -> Result<Option<(String, String, Option<String>)>> {
-    let mut stmt = conn.prepare("SELECT data, fetched_at, etag FROM index_cache WHERE id = 1")?;
+/// Get the cached index data for an index source
+pub fn get_cached_index(conn: &Connection, source_id: i64) -> Result<Option<IndexCache>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, data, fetched_at, etag, last_modified FROM index_cache WHERE id = ?1",
+    )?;
 
     let result = stmt
-        .query_row([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .query_row([source_id], |row| {
+            Ok(IndexCache {
+                id: row.get(0)?,
+                data: row.get(1)?,
+                fetched_at: row.get(2)?,
+                etag: row.get(3)?,
+                last_modified: row.get(4)?,
+            })
+        })
         .optional()?;
 
     Ok(result)
 }
 
-/// Update the cached index data
-pub fn update_cached_index(conn: &Connection, data: &str, etag: Option<&str>) -> Result<()> {
+/// Update the cached index data for an index source
+pub fn update_cached_index(
+    conn: &Connection,
+    source_id: i64,
+    data: &str,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> Result<()> {
     let now = Utc::now().to_rfc3339();
 
     conn.execute(
-        "INSERT INTO index_cache (id, data, fetched_at, etag)
-         VALUES (1, ?1, ?2, ?3)
+        "INSERT INTO index_cache (id, data, fetched_at, etag, last_modified)
+         VALUES (?1, ?2, ?3, ?4, ?5)
          ON CONFLICT(id) DO UPDATE SET
              data = excluded.data,
              fetched_at = excluded.fetched_at,
-             etag = excluded.etag",
-        params![data, &now, etag],
+             etag = excluded.etag,
+             last_modified = excluded.last_modified",
+        params![source_id, data, &now, etag, last_modified],
     )?;
 
     Ok(())
 }
 
+/// Mark the cached index of a source as revalidated without changing its data
+pub fn touch_cached_index(conn: &Connection, source_id: i64) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "UPDATE index_cache SET fetched_at = ?1 WHERE id = ?2",
+        params![&now, source_id],
+    )?;
+    Ok(())
+}
+
+// ============================================================================
+// Host Health
+// ============================================================================
+
+const HOST_HEALTH_COLUMNS: &str = "host, successes, failures, consecutive_failures,
+     bytes_per_second, last_success_at, last_failure_at";
+
+fn host_health_from_row(row: &rusqlite::Row) -> rusqlite::Result<HostHealth> {
+    Ok(HostHealth {
+        host: row.get(0)?,
+        successes: row.get(1)?,
+        failures: row.get(2)?,
+        consecutive_failures: row.get(3)?,
+        bytes_per_second: row.get(4)?,
+        last_success_at: row.get(5)?,
+        last_failure_at: row.get(6)?,
+    })
+}
+
+/// Get the recorded health of every download host
+pub fn get_host_health(conn: &Connection) -> Result<Vec<HostHealth>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM host_health ORDER BY host",
+        HOST_HEALTH_COLUMNS
+    ))?;
+    let hosts = stmt
+        .query_map([], host_health_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(hosts)
+}
+
+/// Get the recorded health of one download host
+pub fn get_host_health_for(conn: &Connection, host: &str) -> Result<Option<HostHealth>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM host_health WHERE host = ?1",
+        HOST_HEALTH_COLUMNS
+    ))?;
+    let health = stmt.query_row([host], host_health_from_row).optional()?;
+    Ok(health)
+}
+
+/// Insert or replace the health of a download host
+pub fn save_host_health(conn: &Connection, health: &HostHealth) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO host_health ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            HOST_HEALTH_COLUMNS
+        ),
+        params![
+            health.host,
+            health.successes,
+            health.failures,
+            health.consecutive_failures,
+            health.bytes_per_second,
+            health.last_success_at,
+            health.last_failure_at,
+        ],
+    )?;
+    Ok(())
+}
+
 // ============================================================================
 // Settings
 // ============================================================================
@@ -287,6 +832,12 @@ pub fn set_setting(conn: &Connection, key: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
+/// Remove a setting so its default applies again
+pub fn delete_setting(conn: &Connection, key: &str) -> Result<()> {
+    conn.execute("DELETE FROM settings WHERE key = ?1", [key])?;
+    Ok(())
+}
+
 // Trait extension for optional query results
 trait OptionalExt<T> {
     fn optional(self) -> std::result::Result<Option<T>, rusqlite::Error>;
@@ -1,111 +1,746 @@
+use super::cache::CacheLookup;
+use super::github::GitHubClient;
+use super::health::{is_host_failure, SourceHealth};
+use super::jsdelivr::{download_package, JSDELIVR_API_URL};
+use super::throttle::Throttle;
 use crate::error::{AppError, Result};
-use crate::models::index::DownloadSource;
-use std::path::PathBuf;
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
+use crate::models::{
+    index::DownloadSource, CommitRange, CommitSummary, DownloadAttempt, DownloadPhase,
+    TransferProgress,
+};
+use crate::utils::checksum::{parse_checksum, sha256_hex, ExpectedIntegrity, StreamHasher};
+use crate::utils::paths::get_partial_downloads_path;
+use crate::utils::progress::ProgressTracker;
+use crate::utils::retry::{is_transient, RetryPolicy};
+use crate::utils::signature::signature_url;
+use reqwest::header::{
+    HeaderMap, ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use std::time::Duration;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::OwnedMutexGuard;
+use tokio_util::sync::CancellationToken;
 
-/// Download a file from a URL with progress callback
-pub async fn download_file<F>(url: &str, target_path: &PathBuf, on_progress: F) -> Result<()>
+/// The partial downloads directory, with stale partial files removed
+pub fn partial_downloads_dir() -> Option<PathBuf> {
+    let dir = get_partial_downloads_path()?;
+    prune_partial_downloads(&dir, PARTIAL_MAX_AGE);
+    Some(dir)
+}
+
+/// Download a URL, retrying transient failures according to `policy`
+/// Progress is reported at most every `PROGRESS_INTERVAL`, with byte counts even when
+/// the server doesn't send the size.
+/// When `integrity` is given, the file is hashed while streaming and its size and
+/// checksum are verified before the download is reported as successful.
+/// With a `partial_dir`, interrupted downloads are kept there and resumed with a
+/// Range request the next time the same URL is downloaded.
+/// Cancelling `control.cancel` stops the download with `AppError::Cancelled` and removes
+/// the partial file; `control.throttle` limits the transfer rate.
+/// Returns what the successful attempt fetched; on failure, returns the last error
+/// and the number of attempts made.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn download_with_retry<F>(
+    client: &reqwest::Client,
+    url: &str,
+    target_path: &Path,
+    integrity: Option<&ExpectedIntegrity>,
+    partial_dir: Option<&Path>,
+    policy: &RetryPolicy,
+    control: &DownloadControl,
+    on_progress: &F,
+) -> std::result::Result<Transfer, (AppError, u32)>
+where
+    F: Fn(&TransferProgress) + Sync,
+{
+    // Downloads of the same URL share a partial file, so they take turns using it
+    let _partial_lock = match partial_dir.and_then(|dir| PartialDownload::new(dir, url).ok()) {
+        Some(partial) => Some(
+            control
+                .until_cancelled(partial.lock())
+                .await
+                .map_err(|e| (e, 0))?,
+        ),
+        None => None,
+    };
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match download_resumable(
+            client,
+            url,
+            target_path,
+            integrity,
+            partial_dir,
+            control,
+            on_progress,
+        )
+        .await
+        {
+            Ok(transfer) => return Ok(transfer),
+            // A cancelled download won't be resumed, so don't keep its partial file
+            Err(AppError::Cancelled) => {
+                if let Some(partial) =
+                    partial_dir.and_then(|dir| PartialDownload::new(dir, url).ok())
+                {
+                    partial.discard();
+                }
+                return Err((AppError::Cancelled, attempt));
+            }
+            // A dropped connection resumes from the partial file on the next attempt
+            Err(e) if attempt < policy.max_attempts && is_transient(&e) => {
+                let delay = tokio::time::sleep(policy.delay(attempt - 1));
+                if let Err(e) = control.until_cancelled(delay).await {
+                    return Err((e, attempt));
+                }
+            }
+            Err(e) => return Err((e, attempt)),
+        }
+    }
+}
+
+/// Bytes fetched from the server and the time spent reading them
+/// Bytes resumed from a partial file and time spent waiting on the throttle are left
+/// out, so the rate reflects how fast the host served the download.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Transfer {
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl Transfer {
+    /// Bytes per second, when anything was measured
+    pub fn rate(&self) -> Option<f64> {
+        let secs = self.elapsed.as_secs_f64();
+        (self.bytes > 0 && secs > 0.0).then(|| self.bytes as f64 / secs)
+    }
+}
+
+impl std::ops::AddAssign for Transfer {
+    fn add_assign(&mut self, other: Self) {
+        self.bytes += other.bytes;
+        self.elapsed += other.elapsed;
+    }
+}
+
+/// How a running download can be cancelled and slowed down
+#[derive(Debug, Clone, Default)]
+pub struct DownloadControl {
+    pub cancel: Option<CancellationToken>,
+    pub throttle: Throttle,
+}
+
+impl DownloadControl {
+    /// Run a future unless the download is cancelled first, in which case
+    /// `AppError::Cancelled` is returned
+    async fn until_cancelled<T>(&self, future: impl Future<Output = T>) -> Result<T> {
+        match self.cancel {
+            Some(ref token) => tokio::select! {
+                output = future => Ok(output),
+                _ = token.cancelled() => Err(AppError::Cancelled),
+            },
+            None => Ok(future.await),
+        }
+    }
+}
+
+/// Partial downloads untouched for this long are deleted
+const PARTIAL_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// What is needed to resume a partial download, stored next to the partial file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PartialMeta {
+    url: String,
+    /// Strong ETag or Last-Modified value sent as `If-Range`
+    validator: String,
+    total_size: Option<u64>,
+}
+
+/// Locks of the partial files in use, so that two downloads of one URL (e.g. index
+/// addons sharing a repository archive) never write to the same file at once
+static PARTIAL_LOCKS: LazyLock<Mutex<HashMap<PathBuf, Weak<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Default::default);
+
+/// Files of a partial download in the partial downloads directory, keyed by URL
+struct PartialDownload {
+    data_path: PathBuf,
+    meta_path: PathBuf,
+}
+
+impl PartialDownload {
+    fn new(dir: &Path, url: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let key = &sha256_hex(url.as_bytes())[..32];
+        Ok(Self {
+            data_path: dir.join(format!("{}.part", key)),
+            meta_path: dir.join(format!("{}.json", key)),
+        })
+    }
+
+    /// Wait until no other download is using this partial file
+    /// The file is reserved for the caller until the returned guard is dropped.
+    async fn lock(&self) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = PARTIAL_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+            locks.retain(|_, lock| lock.strong_count() > 0);
+            match locks.get(&self.data_path).and_then(Weak::upgrade) {
+                Some(lock) => lock,
+                None => {
+                    let lock = Arc::new(tokio::sync::Mutex::new(()));
+                    locks.insert(self.data_path.clone(), Arc::downgrade(&lock));
+                    lock
+                }
+            }
+        };
+        lock.lock_owned().await
+    }
+
+    /// Bytes already downloaded and the validator to resume them with, if resumable
+    fn resume_point(&self, url: &str) -> Option<(u64, PartialMeta)> {
+        let meta: PartialMeta =
+            serde_json::from_str(&std::fs::read_to_string(&self.meta_path).ok()?).ok()?;
+        let offset = std::fs::metadata(&self.data_path).ok()?.len();
+
+        let complete = meta.total_size.is_some_and(|total| offset >= total);
+        (meta.url == url && offset > 0 && !complete).then_some((offset, meta))
+    }
+
+    /// Remember how to resume this download, or forget it when the server can't resume
+    fn save_meta(&self, meta: Option<&PartialMeta>) {
+        match meta.and_then(|m| serde_json::to_string(m).ok()) {
+            Some(json) => {
+                let _ = std::fs::write(&self.meta_path, json);
+            }
+            None => {
+                let _ = std::fs::remove_file(&self.meta_path);
+            }
+        }
+    }
+
+    fn discard(&self) {
+        let _ = std::fs::remove_file(&self.data_path);
+        let _ = std::fs::remove_file(&self.meta_path);
+    }
+
+    /// Move the finished download to its target
+    fn complete(&self, target_path: &Path) -> Result<()> {
+        // The target may be on another filesystem (e.g. a temp dir), so fall back to copying
+        if std::fs::rename(&self.data_path, target_path).is_err() {
+            std::fs::copy(&self.data_path, target_path)?;
+        }
+        self.discard();
+        Ok(())
+    }
+}
+
+/// Delete partial downloads that have not been written to for longer than `max_age`
+fn prune_partial_downloads(dir: &Path, max_age: Duration) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > max_age);
+        if stale {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Validator for `If-Range`: a strong ETag, or Last-Modified when there is none
+fn range_validator(headers: &HeaderMap) -> Option<String> {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+        .map(String::from)
+}
+
+/// Start offset and total length from a `Content-Range: bytes start-end/total` header
+fn parse_content_range(headers: &HeaderMap) -> Option<(u64, Option<u64>)> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+/// Download a file, resuming from a partial download in `partial_dir` when the server
+/// supports it. Without a partial directory the file is written straight to the target.
+/// Returns the bytes fetched by this request and the time spent reading them.
+async fn download_resumable<F>(
+    client: &reqwest::Client,
+    url: &str,
+    target_path: &Path,
+    integrity: Option<&ExpectedIntegrity>,
+    partial_dir: Option<&Path>,
+    control: &DownloadControl,
+    on_progress: &F,
+) -> Result<Transfer>
 where
-    F: Fn(f64) + Send + 'static,
+    F: Fn(&TransferProgress) + Sync,
 {
-    let client = reqwest::Client::new();
-    let response = client
-        .get(url)
-        .header("User-Agent", "eso-addon-manager")
-        .send()
-        .await?;
+    // Parse the expected checksum up front so a malformed one fails before downloading
+    let expected_checksum = integrity
+        .and_then(|i| i.checksum.as_deref())
+        .map(parse_checksum)
+        .transpose()?;
+    let expected_size = integrity.and_then(|i| i.file_size);
+
+    let partial = partial_dir
+        .map(|dir| PartialDownload::new(dir, url))
+        .transpose()?;
+    let data_path = partial
+        .as_ref()
+        .map(|p| p.data_path.clone())
+        .unwrap_or_else(|| target_path.to_path_buf());
+
+    let mut resume = partial.as_ref().and_then(|p| p.resume_point(url));
+    let mut response = control
+        .until_cancelled(send_download_request(client, url, resume.as_ref()))
+        .await??;
+
+    // The partial file no longer fits the resource on the server; start over
+    if resume.is_some() && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        resume = None;
+        response = control
+            .until_cancelled(send_download_request(client, url, None))
+            .await??;
+    }
+
+    if !response.status().is_success() {
+        return Err(AppError::HttpStatus(
+            response.status().as_u16(),
+            url.to_string(),
+        ));
+    }
+
+    // Error and login pages are sometimes served with 200; never save one as an archive
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim_start().starts_with("text/html"));
+    if is_html {
+        return Err(AppError::Download(format!(
+            "{} returned an HTML page instead of a file",
+            url
+        )));
+    }
+
+    // A 200 instead of 206 means the server ignored the range or the file changed
+    let resumed = match resume {
+        Some((offset, meta)) if response.status() == StatusCode::PARTIAL_CONTENT => {
+            match parse_content_range(response.headers()) {
+                Some((start, total)) if start == offset => {
+                    Some((offset, total.or(meta.total_size)))
+                }
+                _ => {
+                    if let Some(ref partial) = partial {
+                        partial.discard();
+                    }
+                    return Err(AppError::Download(
+                        "Server resumed the download at the wrong offset".to_string(),
+                    ));
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let (mut downloaded, total_size) = match resumed {
+        Some((offset, total)) => (
+            offset,
+            total.unwrap_or(offset + response.content_length().unwrap_or(0)),
+        ),
+        None => (0, response.content_length().unwrap_or(0)),
+    };
+
+    if let Some(expected) = expected_size {
+        if total_size > 0 && total_size != expected {
+            return Err(AppError::Integrity(format!(
+                "expected {} bytes but server reported {}",
+                expected, total_size
+            )));
+        }
+    }
+
+    let mut hasher = expected_checksum
+        .as_ref()
+        .map(|(algorithm, _)| StreamHasher::new(*algorithm));
+
+    let mut file = if resumed.is_some() {
+        // Bytes from the earlier attempt still count towards the checksum
+        if let Some(ref mut hasher) = hasher {
+            hash_existing(&data_path, hasher).await?;
+        }
+        OpenOptions::new().append(true).open(&data_path).await?
+    } else {
+        // Fresh download: remember how to resume it if the server allows
+        if let Some(ref partial) = partial {
+            let accepts_ranges = response
+                .headers()
+                .get(ACCEPT_RANGES)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.eq_ignore_ascii_case("bytes"));
+            let meta = range_validator(response.headers())
+                .filter(|_| accepts_ranges)
+                .map(|validator| PartialMeta {
+                    url: url.to_string(),
+                    validator,
+                    total_size: (total_size > 0).then_some(total_size),
+                });
+            partial.save_meta(meta.as_ref());
+        }
+        File::create(&data_path).await?
+    };
 
-    let total_size = response.content_length().unwrap_or(0);
-    let mut downloaded: u64 = 0;
+    let mut tracker = ProgressTracker::new(url, (total_size > 0).then_some(total_size), downloaded);
+    if let Some(progress) = tracker.update(downloaded) {
+        on_progress(&progress);
+    }
 
-    let mut file = File::create(target_path).await?;
     let mut stream = response.bytes_stream();
+    let resumed_from = downloaded;
+    let mut reading = Duration::ZERO;
 
     use futures_util::StreamExt;
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
+    loop {
+        // On a dropped connection the bytes written so far stay behind for resuming
+        let read_started = std::time::Instant::now();
+        let next = control.until_cancelled(stream.next()).await;
+        reading += read_started.elapsed();
+        let chunk = match next {
+            Ok(Some(Ok(chunk))) => chunk,
+            Ok(None) => break,
+            Ok(Some(Err(e))) => {
+                file.flush().await?;
+                return Err(e.into());
+            }
+            Err(e) => {
+                file.flush().await?;
+                return Err(e);
+            }
+        };
         file.write_all(&chunk).await?;
+        if let Some(ref mut hasher) = hasher {
+            hasher.update(&chunk);
+        }
         downloaded += chunk.len() as u64;
 
-        if total_size > 0 {
-            let progress = downloaded as f64 / total_size as f64;
-            on_progress(progress);
+        if let Some(progress) = tracker.update(downloaded) {
+            on_progress(&progress);
+        }
+
+        let throttled = control.throttle.acquire(chunk.len() as u64);
+        if let Err(e) = control.until_cancelled(throttled).await {
+            file.flush().await?;
+            return Err(e);
         }
     }
 
     file.flush().await?;
-    on_progress(1.0);
+    drop(file);
+
+    // Always report the final byte count, whatever the throttling skipped
+    let phase = if integrity.is_some() {
+        DownloadPhase::Verifying
+    } else {
+        DownloadPhase::Downloading
+    };
+    on_progress(&tracker.report(downloaded, phase));
+
+    // A file that fails verification can't be resumed either
+    let discard = || {
+        if let Some(ref partial) = partial {
+            partial.discard();
+        }
+    };
+
+    if let Some(expected) = expected_size {
+        if downloaded != expected {
+            discard();
+            return Err(AppError::Integrity(format!(
+                "expected {} bytes but downloaded {}",
+                expected, downloaded
+            )));
+        }
+    }
+
+    if let (Some(hasher), Some((_, expected))) = (hasher, expected_checksum) {
+        let actual = hasher.finalize_hex();
+        if actual != expected {
+            discard();
+            return Err(AppError::Integrity(format!(
+                "checksum mismatch (expected {}, got {})",
+                expected, actual
+            )));
+        }
+    }
+
+    if let Some(ref partial) = partial {
+        partial.complete(target_path)?;
+    }
+
+    Ok(Transfer {
+        bytes: downloaded - resumed_from,
+        elapsed: reading,
+    })
+}
+
+/// Send a download request, asking for the rest of a partial file when resuming
+async fn send_download_request(
+    client: &reqwest::Client,
+    url: &str,
+    resume: Option<&(u64, PartialMeta)>,
+) -> Result<reqwest::Response> {
+    let mut request = client.get(url);
+
+    if let Some((offset, meta)) = resume {
+        request = request
+            .header(RANGE, format!("bytes={}-", offset))
+            .header(IF_RANGE, &meta.validator);
+    }
+
+    Ok(request.send().await?)
+}
+
+/// Feed the bytes of an existing partial file into a hasher
+async fn hash_existing(path: &Path, hasher: &mut StreamHasher) -> Result<()> {
+    let mut file = File::open(path).await?;
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
 
     Ok(())
 }
 
 /// Download from multiple sources with fallback
 /// Tries each source in order until one succeeds
-/// Prefers github_archive sources since they provide ZIP files directly
-/// (jsDelivr serves individual files which requires different handling)
+/// Prefers github_archive sources since they provide ZIP files directly. jsDelivr
+/// sources serve individual files; those are downloaded one by one and packed into
+/// an archive of the same layout, each file checked against jsDelivr's hash for it.
+/// A source whose file fails the integrity check counts as failed. Each source is
+/// retried on transient failures before moving on; when every source fails the
+/// error lists each one and why it failed.
+/// With a `cache`, a cached archive of any of the sources is used instead of the
+/// network, and a fresh download is added to the cache.
+/// With `health`, sources are tried in order of their hosts' health instead, and the
+/// outcome of each source tried is recorded in it.
+/// Interrupted downloads are resumed from `partial_dir` (see `partial_downloads_dir`).
+#[allow(clippy::too_many_arguments)]
 pub async fn download_with_fallback<F>(
+    client: &reqwest::Client,
     sources: &[DownloadSource],
     fallback_url: Option<&str>,
-    target_path: &PathBuf,
+    target_path: &Path,
+    partial_dir: Option<&Path>,
+    integrity: Option<&ExpectedIntegrity>,
+    cache: Option<&CacheLookup<'_>>,
+    health: Option<&SourceHealth>,
+    control: &DownloadControl,
     on_progress: F,
 ) -> Result<()>
 where
-    F: Fn(f64) + Send + Clone + 'static,
+    F: Fn(&TransferProgress) + Send + Sync + 'static,
 {
-    let mut last_error: Option<String> = None;
-
-    // Filter to prefer github_archive sources (they provide ZIP files)
-    // jsDelivr serves individual files which we can't easily handle as a ZIP
-    let archive_sources: Vec<_> = sources
-        .iter()
-        .filter(|s| s.source_type == "github_archive")
-        .collect();
+    let mut candidates = download_candidates(sources, fallback_url);
+    if let Some(health) = health {
+        health.order(&mut candidates, chrono::Utc::now());
+    }
 
-    // Try archive sources first
-    for source in &archive_sources {
-        match download_file(&source.url, target_path, on_progress.clone()).await {
-            Ok(_) => {
-                return Ok(());
-            }
-            Err(e) => {
-                last_error = Some(format!("{} download failed: {}", source.source_type, e));
-            }
+    if let Some(lookup) = cache {
+        // An archive packed from jsDelivr files can't match the index checksum
+        let restored = lookup.pin.and_then(|pin| {
+            candidates.iter().find_map(|(source, url)| {
+                let integrity = integrity.filter(|_| !is_package_source(source, url));
+                lookup
+                    .cache
+                    .restore(&[url.as_str()], pin, target_path, integrity)
+            })
+        });
+        if let Some(url) = restored {
+            let size = std::fs::metadata(target_path)?.len();
+            on_progress(&TransferProgress {
+                url,
+                source: Some("cache".to_string()),
+                phase: DownloadPhase::Downloading,
+                downloaded: size,
+                total: Some(size),
+                ..Default::default()
+            });
+            return Ok(());
+        }
+        if lookup.offline {
+            return Err(AppError::Download(
+                "Offline mode is on and this version is not in the download cache".to_string(),
+            ));
         }
     }
 
-    // Try remaining sources (jsdelivr etc) if they provide direct file downloads
-    // Note: jsDelivr typically serves individual files, not ZIP archives
-    // But some repos may have pre-packaged ZIPs available
-    for source in sources.iter().filter(|s| s.source_type != "github_archive") {
-        // Only try if URL ends with .zip (pre-packaged archive)
-        if source.url.ends_with(".zip") {
-            match download_file(&source.url, target_path, on_progress.clone()).await {
-                Ok(_) => {
-                    return Ok(());
-                }
-                Err(e) => {
-                    last_error = Some(format!("{} download failed: {}", source.source_type, e));
-                }
-            }
-        }
+    let url = download_from_sources(
+        client,
+        &candidates,
+        target_path,
+        integrity,
+        partial_dir,
+        &RetryPolicy::default(),
+        health,
+        control,
+        &on_progress,
+    )
+    .await?;
+
+    // Caching is best effort; the download itself succeeded
+    if let Some(CacheLookup {
+        cache,
+        pin: Some(pin),
+        ..
+    }) = cache
+    {
+        let _ = cache.put(&url, pin, target_path);
     }
 
+    Ok(())
+}
+
+/// Source label and URL of every download to try, in order
+fn download_candidates(
+    sources: &[DownloadSource],
+    fallback_url: Option<&str>,
+) -> Vec<(String, String)> {
+    // Prefer github_archive sources (they provide ZIP files)
+    let archive_sources = sources.iter().filter(|s| s.source_type == "github_archive");
+
+    // Then remaining sources (jsdelivr etc) that provide direct ZIP downloads,
+    // e.g. repos with pre-packaged ZIPs
+    let zip_sources = sources
+        .iter()
+        .filter(|s| s.source_type != "github_archive" && s.url.ends_with(".zip"));
+
+    // Then jsDelivr folders, which take one request per file
+    let package_sources = sources
+        .iter()
+        .filter(|s| is_package_source(&s.source_type, &s.url));
+
+    let mut candidates: Vec<(String, String)> = archive_sources
+        .chain(zip_sources)
+        .chain(package_sources)
+        .map(|s| (s.source_type.clone(), s.url.clone()))
+        .collect();
+
     // Fallback to the legacy download_url if provided
     if let Some(url) = fallback_url {
-        match download_file(url, target_path, on_progress).await {
-            Ok(_) => {
-                return Ok(());
-            }
-            Err(e) => {
-                last_error = Some(format!("Fallback download failed: {}", e));
+        candidates.push(("fallback".to_string(), url.to_string()));
+    }
+
+    candidates
+}
+
+/// Whether a source serves a repository folder file by file rather than an archive
+fn is_package_source(source_type: &str, url: &str) -> bool {
+    source_type == "jsdelivr" && !url.ends_with(".zip")
+}
+
+/// Try each candidate in order, returning the URL that was downloaded
+/// Successes and host failures are recorded in `health` along with the throughput.
+#[allow(clippy::too_many_arguments)]
+async fn download_from_sources<F>(
+    client: &reqwest::Client,
+    candidates: &[(String, String)],
+    target_path: &Path,
+    integrity: Option<&ExpectedIntegrity>,
+    partial_dir: Option<&Path>,
+    policy: &RetryPolicy,
+    health: Option<&SourceHealth>,
+    control: &DownloadControl,
+    on_progress: &F,
+) -> Result<String>
+where
+    F: Fn(&TransferProgress) + Sync,
+{
+    if candidates.is_empty() {
+        return Err(AppError::Download(
+            "No download sources available".to_string(),
+        ));
+    }
+
+    let mut failures = Vec::new();
+
+    for (source, url) in candidates {
+        // Tell the UI which source is in use
+        let with_source = |progress: &TransferProgress| {
+            on_progress(&TransferProgress {
+                source: Some(source.clone()),
+                ..progress.clone()
+            })
+        };
+
+        let result = if is_package_source(source, url) {
+            download_package(
+                client,
+                JSDELIVR_API_URL,
+                url,
+                target_path,
+                policy,
+                control,
+                &with_source,
+            )
+            .await
+        } else {
+            download_with_retry(
+                client,
+                url,
+                target_path,
+                integrity,
+                partial_dir,
+                policy,
+                control,
+                &with_source,
+            )
+            .await
+        };
+
+        if let Some(health) = health {
+            match result {
+                Ok(transfer) => health.record(url, true, transfer.rate()),
+                Err((ref e, _)) if is_host_failure(e) => health.record(url, false, None),
+                Err(_) => {}
             }
         }
+
+        match result {
+            Ok(_) => return Ok(url.clone()),
+            Err((AppError::Cancelled, _)) => return Err(AppError::Cancelled),
+            Err((e, attempts)) => failures.push(DownloadAttempt {
+                source: source.clone(),
+                url: url.clone(),
+                attempts,
+                error: e.to_string(),
+            }),
+        }
     }
 
-    Err(AppError::Download(last_error.unwrap_or_else(|| {
-        "All download sources failed".to_string()
-    })))
+    Err(AppError::SourcesFailed(failures))
 }
 
 /// Get the best download URL from sources, preferring github_archive
@@ -127,29 +762,93 @@ pub fn get_best_download_url(
     fallback_url.map(|s| s.to_string())
 }
 
+/// Response to a conditional index request
+#[derive(Debug)]
+pub enum IndexResponse {
+    /// The server sent a (new) copy of the index
+    Modified {
+        data: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+    /// The server answered 304 Not Modified
+    NotModified,
+}
+
+/// Fetch the addon index, sending cache validators when available
+pub async fn fetch_index_conditional(
+    client: &reqwest::Client,
+    url: &str,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> Result<IndexResponse> {
+    let mut request = client.get(url);
+
+    if let Some(etag) = etag {
+        request = request.header("If-None-Match", etag);
+    }
+    if let Some(last_modified) = last_modified {
+        request = request.header("If-Modified-Since", last_modified);
+    }
+
+    let response = request.send().await?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(IndexResponse::NotModified);
+    }
+
+    if !response.status().is_success() {
+        return Err(AppError::Download(format!(
+            "Index request returned HTTP {}",
+            response.status()
+        )));
+    }
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let etag = header("etag");
+    let last_modified = header("last-modified");
+
+    let data = response.text().await?;
+
+    Ok(IndexResponse::Modified {
+        data,
+        etag,
+        last_modified,
+    })
+}
+
+/// Fetch the detached minisign signature published next to an index file
+pub async fn fetch_index_signature(client: &reqwest::Client, index_url: &str) -> Result<String> {
+    let response = client.get(signature_url(index_url)).send().await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::SignatureInvalid(format!(
+            "Signature file not available (HTTP {})",
+            response.status()
+        )));
+    }
+
+    Ok(response.text().await?)
+}
+
 /// Get the download URL for a GitHub release asset
-pub async fn get_github_release_url(repo: &str) -> Result<Option<String>> {
-    let client = reqwest::Client::new();
+pub async fn get_github_release_url(github: &GitHubClient, repo: &str) -> Result<Option<String>> {
     let url = format!("https://api.github.com/repos/{}/releases/latest", repo);
 
-    let response: serde_json::Value = client
-        .get(&url)
-        .header("User-Agent", "eso-addon-manager")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await?
-        .json()
-        .await?;
+    let response = github.get_cached(&url).await?.json()?;
 
     // Look for a .zip asset
     if let Some(assets) = response.get("assets").and_then(|a| a.as_array()) {
         for asset in assets {
             if let Some(name) = asset.get("name").and_then(|n| n.as_str()) {
                 if name.ends_with(".zip") {
-                    return Ok(asset
-                        .get("browser_download_url")
-                        .and_then(|u| u.as_str())
-                        .map(String::from));
+                    return Ok(asset_download_url(github, asset));
                 }
             }
         }
@@ -162,26 +861,137 @@ pub async fn get_github_release_url(repo: &str) -> Result<Option<String>> {
         .map(String::from))
 }
 
+/// URL to download a release asset from
+/// With a token this is the API URL, which also serves assets of private repositories.
+fn asset_download_url(github: &GitHubClient, asset: &serde_json::Value) -> Option<String> {
+    let key = if github.is_authenticated() {
+        "url"
+    } else {
+        "browser_download_url"
+    };
+    asset.get(key).and_then(|u| u.as_str()).map(String::from)
+}
+
 /// Get the download URL for a GitHub branch
-pub async fn get_github_branch_url(repo: &str, branch: &str) -> String {
-    format!(
-        "https://github.com/{}/archive/refs/heads/{}.zip",
-        repo, branch
-    )
+/// With a token the archive comes from the API, which also serves private repositories.
+pub async fn get_github_branch_url(github: &GitHubClient, repo: &str, branch: &str) -> String {
+    if github.is_authenticated() {
+        format!(
+            "https://api.github.com/repos/{}/zipball/refs/heads/{}",
+            repo, branch
+        )
+    } else {
+        format!(
+            "https://github.com/{}/archive/refs/heads/{}.zip",
+            repo, branch
+        )
+    }
+}
+
+/// Get the download URL for a GitHub commit, so the archive matches a recorded SHA
+pub fn get_github_commit_url(github: &GitHubClient, repo: &str, sha: &str) -> String {
+    if github.is_authenticated() {
+        format!("https://api.github.com/repos/{}/zipball/{}", repo, sha)
+    } else {
+        format!("https://github.com/{}/archive/{}.zip", repo, sha)
+    }
+}
+
+/// Abbreviated commit SHA, as GitHub shows it
+pub fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+/// Get the commit SHA at the head of a GitHub branch, or None if there is no such branch
+pub async fn get_github_branch_head(
+    github: &GitHubClient,
+    repo: &str,
+    branch: &str,
+) -> Result<Option<String>> {
+    let url = format!("https://api.github.com/repos/{}/branches/{}", repo, branch);
+
+    let response = github.get_cached(&url).await?;
+
+    if !response.is_success() {
+        return Ok(None);
+    }
+
+    Ok(response
+        .json()?
+        .pointer("/commit/sha")
+        .and_then(|s| s.as_str())
+        .map(String::from))
+}
+
+/// Get the commits from `base` to `head`, or None if GitHub can't compare them
+/// (e.g. `base` is gone after a force push)
+pub async fn compare_github_commits(
+    github: &GitHubClient,
+    repo: &str,
+    base: &str,
+    head: &str,
+) -> Result<Option<CommitRange>> {
+    let url = format!(
+        "https://api.github.com/repos/{}/compare/{}...{}?per_page=100",
+        repo, base, head
+    );
+
+    let response = github.get_cached(&url).await?;
+
+    if !response.is_success() {
+        return Ok(None);
+    }
+
+    Ok(Some(parse_commit_range(base, head, &response.json()?)))
+}
+
+/// Read a commit range from a compare response, which lists commits oldest first
+fn parse_commit_range(base: &str, head: &str, data: &serde_json::Value) -> CommitRange {
+    let text = |value: &serde_json::Value, pointer: &str| {
+        value
+            .pointer(pointer)
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    };
+
+    let commits: Vec<CommitSummary> = data
+        .get("commits")
+        .and_then(|c| c.as_array())
+        .map(|commits| {
+            commits
+                .iter()
+                .filter_map(|commit| {
+                    Some(CommitSummary {
+                        sha: text(commit, "/sha")?,
+                        message: text(commit, "/commit/message").unwrap_or_default(),
+                        author: text(commit, "/commit/author/name"),
+                        date: text(commit, "/commit/author/date"),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let total_commits = data
+        .get("total_commits")
+        .and_then(|t| t.as_u64())
+        .map_or(commits.len() as u32, |t| t as u32);
+
+    CommitRange {
+        from_sha: base.to_string(),
+        to_sha: head.to_string(),
+        total_commits,
+        commits,
+    }
 }
 
 /// Validate that a GitHub repository exists
-pub async fn validate_github_repo(repo: &str) -> Result<bool> {
-    let client = reqwest::Client::new();
+pub async fn validate_github_repo(github: &GitHubClient, repo: &str) -> Result<bool> {
     let url = format!("https://api.github.com/repos/{}", repo);
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "eso-addon-manager")
-        .send()
-        .await?;
+    let response = github.get_cached(&url).await?;
 
-    Ok(response.status().is_success())
+    Ok(response.is_success())
 }
 
 /// GitHub release information
@@ -203,28 +1013,29 @@ pub struct GitHubBranch {
 }
 
 /// List branches for a GitHub repository
-pub async fn list_github_branches(repo: &str, default_branch: &str) -> Result<Vec<GitHubBranch>> {
-    let client = reqwest::Client::new();
+/// Fails with `AppError::RepoNotFound` for a missing repository, `AppError::RateLimited`
+/// when the budget is used up and `AppError::HttpStatus` for other errors, such as a
+/// rejected token.
+pub async fn list_github_branches(
+    github: &GitHubClient,
+    repo: &str,
+    default_branch: &str,
+) -> Result<Vec<GitHubBranch>> {
     let url = format!(
         "https://api.github.com/repos/{}/branches?per_page=100",
         repo
     );
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "eso-addon-manager")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await?;
+    let response = github.get_cached(&url).await?;
 
-    if !response.status().is_success() {
-        return Ok(vec![GitHubBranch {
-            name: default_branch.to_string(),
-            is_default: true,
-        }]);
+    if response.status == StatusCode::NOT_FOUND {
+        return Err(AppError::RepoNotFound(repo.to_string()));
+    }
+    if !response.is_success() {
+        return Err(AppError::HttpStatus(response.status.as_u16(), url));
     }
 
-    let data: serde_json::Value = response.json().await?;
+    let data = response.json()?;
 
     let branches: Vec<GitHubBranch> = data
         .as_array()
@@ -251,22 +1062,19 @@ pub async fn list_github_branches(repo: &str, default_branch: &str) -> Result<Ve
 }
 
 /// Get the latest release information from a GitHub repository
-pub async fn get_github_release_info(repo: &str) -> Result<Option<GitHubReleaseInfo>> {
-    let client = reqwest::Client::new();
+pub async fn get_github_release_info(
+    github: &GitHubClient,
+    repo: &str,
+) -> Result<Option<GitHubReleaseInfo>> {
     let url = format!("https://api.github.com/repos/{}/releases/latest", repo);
 
-    let response = client
-        .get(&url)
-        .header("User-Agent", "eso-addon-manager")
-        .header("Accept", "application/vnd.github.v3+json")
-        .send()
-        .await?;
+    let response = github.get_cached(&url).await?;
 
-    if !response.status().is_success() {
+    if !response.is_success() {
         return Ok(None);
     }
 
-    let data: serde_json::Value = response.json().await?;
+    let data = response.json()?;
 
     let tag_name = data
         .get("tag_name")
@@ -296,12 +1104,7 @@ pub async fn get_github_release_info(repo: &str) -> Result<Option<GitHubReleaseI
                     .map(|n| n.ends_with(".zip"))
                     .unwrap_or(false)
             })
-            .and_then(|asset| {
-                asset
-                    .get("browser_download_url")
-                    .and_then(|u| u.as_str())
-                    .map(String::from)
-            })
+            .and_then(|asset| asset_download_url(github, asset))
     } else {
         None
     };
@@ -326,3 +1129,796 @@ pub async fn get_github_release_info(repo: &str) -> Result<Option<GitHubReleaseI
         published_at,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::cache::ArchiveCache;
+    use crate::services::health::host_of;
+    use crate::services::http::{build_client, HttpConfig};
+    use crate::services::throttle::{Bandwidth, BandwidthConfig, DownloadPriority, RateLimits};
+    use crate::utils::test_server::{request_header, request_path, Response, TestServer};
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_fetch_index_conditional_revalidates_with_etag() {
+        let body = r#"{"addons":[]}"#;
+        // Answer 304 when the expected ETag is sent
+        let server = TestServer::spawn(move |request, _| {
+            if request_header(request, "if-none-match") == Some("\"v1\"") {
+                Response::status("304 Not Modified").header("ETag", "\"v1\"")
+            } else {
+                Response::ok(body)
+                    .header("ETag", "\"v1\"")
+                    .header("Last-Modified", "Wed, 01 Jan 2025 00:00:00 GMT")
+            }
+        })
+        .await;
+        let url = server.url("/index.json");
+
+        let first = fetch_index_conditional(&test_client(), &url, None, None)
+            .await
+            .unwrap();
+        let (etag, last_modified) = match first {
+            IndexResponse::Modified {
+                data,
+                etag,
+                last_modified,
+            } => {
+                assert_eq!(data, body);
+                (etag.unwrap(), last_modified.unwrap())
+            }
+            IndexResponse::NotModified => panic!("expected a full response"),
+        };
+        assert_eq!(etag, "\"v1\"");
+
+        let second =
+            fetch_index_conditional(&test_client(), &url, Some(&etag), Some(&last_modified))
+                .await
+                .unwrap();
+        assert!(matches!(second, IndexResponse::NotModified));
+
+        let requests = server.requests();
+        assert_eq!(request_header(&requests[0], "if-none-match"), None);
+        assert_eq!(
+            request_header(&requests[1], "if-modified-since"),
+            Some("Wed, 01 Jan 2025 00:00:00 GMT")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_index_conditional_rejects_error_status() {
+        let server = TestServer::canned(vec![Response::status("500 Internal Server Error")]).await;
+
+        let result =
+            fetch_index_conditional(&test_client(), &server.url("/index.json"), None, None).await;
+        assert!(matches!(result, Err(AppError::Download(_))));
+    }
+
+    /// Spawn a server that answers each request path with a fixed body (404 for unknown paths)
+    async fn spawn_file_server(files: Vec<(&'static str, Vec<u8>)>) -> TestServer {
+        TestServer::spawn(move |request, _| {
+            match files
+                .iter()
+                .find(|(path, _)| *path == request_path(request))
+            {
+                Some((_, body)) => Response::ok(body.clone()),
+                None => Response::not_found(),
+            }
+        })
+        .await
+    }
+
+    fn test_client() -> reqwest::Client {
+        build_client(&HttpConfig::default()).unwrap()
+    }
+
+    fn integrity_for(body: &[u8]) -> ExpectedIntegrity {
+        ExpectedIntegrity {
+            checksum: Some(format!(
+                "sha256:{}",
+                crate::utils::checksum::sha256_hex(body)
+            )),
+            file_size: Some(body.len() as u64),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_file_verifies_checksum() {
+        let body = b"addon archive bytes".to_vec();
+        let server = spawn_file_server(vec![("/addon.zip", body.clone())]).await;
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+
+        let integrity = integrity_for(&body);
+        download_with_retry(
+            &test_client(),
+            &server.url("/addon.zip"),
+            &target,
+            Some(&integrity),
+            Some(&temp.path().join("partial")),
+            &RetryPolicy::default(),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), body);
+
+        let wrong = integrity_for(b"something else entirely");
+        let result = download_with_retry(
+            &test_client(),
+            &server.url("/addon.zip"),
+            &target,
+            Some(&ExpectedIntegrity {
+                checksum: wrong.checksum,
+                file_size: None,
+            }),
+            Some(&temp.path().join("partial")),
+            &RetryPolicy::default(),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await;
+        assert!(matches!(result, Err((AppError::Integrity(_), _))));
+    }
+
+    #[tokio::test]
+    async fn test_download_file_rejects_size_mismatch() {
+        let body = b"addon archive bytes".to_vec();
+        let server = spawn_file_server(vec![("/addon.zip", body.clone())]).await;
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+
+        let integrity = ExpectedIntegrity {
+            checksum: None,
+            file_size: Some(body.len() as u64 + 1),
+        };
+        let result = download_with_retry(
+            &test_client(),
+            &server.url("/addon.zip"),
+            &target,
+            Some(&integrity),
+            Some(&temp.path().join("partial")),
+            &RetryPolicy::default(),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await;
+        assert!(matches!(result, Err((AppError::Integrity(_), _))));
+    }
+
+    /// Spawn a server for `/addon.zip` that honours `Range`/`If-Range` and drops the
+    /// first `drops` connections after sending `drop_after` bytes of the body
+    async fn spawn_range_server(
+        body: Vec<u8>,
+        etag: &'static str,
+        drop_after: usize,
+        drops: usize,
+    ) -> TestServer {
+        TestServer::spawn(move |request, served| {
+            let offset = request_header(request, "range")
+                .filter(|_| request_header(request, "if-range") == Some(etag))
+                .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok());
+
+            let response = match offset {
+                Some(offset) => Response::status("206 Partial Content")
+                    .header(
+                        "Content-Range",
+                        format!("bytes {}-{}/{}", offset, body.len() - 1, body.len()),
+                    )
+                    .body(&body[offset..]),
+                None => Response::ok(body.clone()),
+            }
+            .header("Accept-Ranges", "bytes")
+            .header("ETag", etag);
+
+            // Cut the connection part way through the body
+            if served < drops {
+                response.cut_after(drop_after)
+            } else {
+                response
+            }
+        })
+        .await
+    }
+
+    fn test_body() -> Vec<u8> {
+        (0..64 * 1024).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_download_resumes_after_dropped_connection() {
+        let body = test_body();
+        let server = spawn_range_server(body.clone(), "\"v1\"", 20_000, 2).await;
+        let url = server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let partial_dir = temp.path().join("partial");
+        let target = temp.path().join("addon.zip");
+        let integrity = integrity_for(&body);
+
+        // Each dropped connection leaves more of the file behind
+        for _ in 0..2 {
+            let result = download_resumable(
+                &test_client(),
+                &url,
+                &target,
+                Some(&integrity),
+                Some(&partial_dir),
+                &DownloadControl::default(),
+                &|_| {},
+            )
+            .await;
+            assert!(result.is_err());
+        }
+        let transfer = download_resumable(
+            &test_client(),
+            &url,
+            &target,
+            Some(&integrity),
+            Some(&partial_dir),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await
+        .unwrap();
+
+        // Only the bytes fetched by the last request count towards its rate
+        assert_eq!(transfer.bytes, body.len() as u64 - 40_000);
+        assert_eq!(std::fs::read(&target).unwrap(), body);
+        assert_eq!(std::fs::read_dir(&partial_dir).unwrap().count(), 0);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(request_header(&requests[0], "range"), None);
+        assert_eq!(request_header(&requests[1], "range"), Some("bytes=20000-"));
+        assert_eq!(request_header(&requests[1], "if-range"), Some("\"v1\""));
+        assert_eq!(request_header(&requests[2], "range"), Some("bytes=40000-"));
+    }
+
+    #[tokio::test]
+    async fn test_download_restarts_when_file_changed() {
+        let body = test_body();
+        let server = spawn_range_server(body.clone(), "\"v2\"", 0, 0).await;
+        let url = server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let partial_dir = temp.path().join("partial");
+        let target = temp.path().join("addon.zip");
+
+        // A partial file left over from an older version of the download
+        let partial = PartialDownload::new(&partial_dir, &url).unwrap();
+        std::fs::write(&partial.data_path, b"stale bytes").unwrap();
+        partial.save_meta(Some(&PartialMeta {
+            url: url.clone(),
+            validator: "\"v1\"".to_string(),
+            total_size: Some(body.len() as u64),
+        }));
+
+        download_resumable(
+            &test_client(),
+            &url,
+            &target,
+            Some(&integrity_for(&body)),
+            Some(&partial_dir),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&target).unwrap(), body);
+        assert_eq!(
+            request_header(&server.requests()[0], "if-range"),
+            Some("\"v1\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_downloads_of_same_url() {
+        let body = test_body();
+        let server = spawn_range_server(body.clone(), "\"v1\"", 0, 0).await;
+        let url = server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let partial_dir = temp.path().join("partial");
+        let targets = [
+            temp.path().join("first.zip"),
+            temp.path().join("second.zip"),
+        ];
+        let client = test_client();
+        let policy = RetryPolicy::default();
+        let control = DownloadControl::default();
+        let on_progress = |_: &TransferProgress| {};
+
+        // Neither download has a checksum that would catch a mixed-up partial file
+        let downloads = targets.iter().map(|target| {
+            download_with_retry(
+                &client,
+                &url,
+                target,
+                None,
+                Some(&partial_dir),
+                &policy,
+                &control,
+                &on_progress,
+            )
+        });
+        let results = futures_util::future::join_all(downloads).await;
+
+        for (result, target) in results.iter().zip(&targets) {
+            assert!(result.is_ok());
+            assert_eq!(std::fs::read(target).unwrap(), body);
+        }
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(std::fs::read_dir(&partial_dir).unwrap().count(), 0);
+    }
+
+    /// Spawn a server that answers with `status` and an HTML page for the first
+    /// `failures` requests and the body afterwards
+    async fn spawn_flaky_server(
+        status: &'static str,
+        failures: usize,
+        body: Vec<u8>,
+    ) -> TestServer {
+        TestServer::spawn(move |_, served| {
+            if served < failures {
+                Response::status(status)
+                    .header("Content-Type", "text/html")
+                    .body("<html>")
+            } else {
+                Response::ok(body.clone())
+            }
+        })
+        .await
+    }
+
+    fn fast_retries() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(20),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_retries_server_errors() {
+        let body = b"addon archive bytes".to_vec();
+        let server = spawn_flaky_server("503 Service Unavailable", 2, body.clone()).await;
+        let url = server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+
+        download_with_retry(
+            &test_client(),
+            &url,
+            &target,
+            None,
+            None,
+            &fast_retries(),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(server.requests().len(), 3);
+        assert_eq!(std::fs::read(&target).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn test_download_retries_dropped_connection() {
+        let body = test_body();
+        let server = spawn_range_server(body.clone(), "\"v1\"", 10_000, 1).await;
+        let url = server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let partial_dir = temp.path().join("partial");
+        let target = temp.path().join("addon.zip");
+
+        download_with_retry(
+            &test_client(),
+            &url,
+            &target,
+            Some(&integrity_for(&body)),
+            Some(&partial_dir),
+            &fast_retries(),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&target).unwrap(), body);
+        assert_eq!(
+            request_header(&server.requests()[1], "range"),
+            Some("bytes=10000-")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_file_is_not_retried_and_failures_are_listed() {
+        let missing_server = spawn_flaky_server("404 Not Found", usize::MAX, Vec::new()).await;
+        let broken_server =
+            spawn_flaky_server("500 Internal Server Error", usize::MAX, Vec::new()).await;
+        let missing = missing_server.url("/addon.zip");
+        let broken = broken_server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+
+        let candidates = vec![
+            ("github_archive".to_string(), missing.clone()),
+            ("fallback".to_string(), broken.clone()),
+        ];
+        let result = download_from_sources(
+            &test_client(),
+            &candidates,
+            &target,
+            None,
+            None,
+            &fast_retries(),
+            None,
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await;
+
+        let Err(AppError::SourcesFailed(failures)) = result else {
+            panic!("expected every source to fail");
+        };
+        assert_eq!(missing_server.requests().len(), 1);
+        assert_eq!(broken_server.requests().len(), 3);
+
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].source, "github_archive");
+        assert_eq!(failures[0].attempts, 1);
+        assert!(failures[0].error.contains("HTTP 404"));
+        assert_eq!(failures[1].url, broken);
+        assert_eq!(failures[1].attempts, 3);
+    }
+
+    #[tokio::test]
+    async fn test_source_outcomes_are_recorded_in_health() {
+        let body = b"addon archive bytes".to_vec();
+        let broken_server =
+            spawn_flaky_server("503 Service Unavailable", usize::MAX, Vec::new()).await;
+        let missing_server = spawn_flaky_server("404 Not Found", usize::MAX, Vec::new()).await;
+        let good_server = spawn_flaky_server("200 OK", 0, body).await;
+        let broken = broken_server.url("/addon.zip");
+        let missing = missing_server.url("/addon.zip");
+        let good = good_server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let health = SourceHealth::new(Vec::new());
+
+        let candidates = vec![
+            ("github_archive".to_string(), broken.clone()),
+            ("jsdelivr".to_string(), missing),
+            ("fallback".to_string(), good.clone()),
+        ];
+        download_from_sources(
+            &test_client(),
+            &candidates,
+            &temp.path().join("addon.zip"),
+            None,
+            None,
+            &fast_retries(),
+            Some(&health),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await
+        .unwrap();
+
+        // A missing file says nothing about its host
+        let outcomes = health.outcomes();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(Some(&outcomes[0].host), host_of(&broken).as_ref());
+        assert!(!outcomes[0].success);
+        assert_eq!(Some(&outcomes[1].host), host_of(&good).as_ref());
+        assert!(outcomes[1].success);
+    }
+
+    #[tokio::test]
+    async fn test_html_page_is_rejected() {
+        let server = spawn_flaky_server("200 OK", 1, Vec::new()).await;
+        let url = server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+
+        let result = download_with_retry(
+            &test_client(),
+            &url,
+            &target,
+            None,
+            None,
+            &fast_retries(),
+            &DownloadControl::default(),
+            &|_| {},
+        )
+        .await;
+        assert!(matches!(result, Err((AppError::Download(_), 1))));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_download_and_removes_partial_file() {
+        // Send part of the body, then stall until the client gives up
+        let server = TestServer::canned(vec![Response::ok(test_body())
+            .header("Accept-Ranges", "bytes")
+            .header("ETag", "\"v1\"")
+            .cut_after(10_000)
+            .stalled()])
+        .await;
+        let url = server.url("/addon.zip");
+
+        let temp = tempfile::tempdir().unwrap();
+        let partial_dir = temp.path().join("partial");
+        let target = temp.path().join("addon.zip");
+        let token = CancellationToken::new();
+
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            canceller.cancel();
+        });
+
+        let result = download_with_retry(
+            &test_client(),
+            &url,
+            &target,
+            None,
+            Some(&partial_dir),
+            &fast_retries(),
+            &DownloadControl {
+                cancel: Some(token),
+                throttle: Throttle::default(),
+            },
+            &|_| {},
+        )
+        .await;
+
+        assert!(matches!(result, Err((AppError::Cancelled, 1))));
+        assert!(!target.exists());
+        assert_eq!(std::fs::read_dir(&partial_dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_download_is_throttled() {
+        let body = test_body()[..24 * 1024].to_vec();
+        let server = spawn_file_server(vec![("/addon.zip", body.clone())]).await;
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+
+        // 16 KiB/s: the first second's worth goes straight through, the rest takes half a second
+        let bandwidth = Bandwidth::new(BandwidthConfig {
+            user: RateLimits {
+                total_kib: None,
+                per_download_kib: Some(16),
+            },
+            ..Default::default()
+        });
+        let control = DownloadControl {
+            cancel: None,
+            throttle: bandwidth.throttle(DownloadPriority::User),
+        };
+
+        let start = std::time::Instant::now();
+        let transfer = download_with_retry(
+            &test_client(),
+            &server.url("/addon.zip"),
+            &target,
+            None,
+            Some(&temp.path().join("partial")),
+            &RetryPolicy::default(),
+            &control,
+            &|_| {},
+        )
+        .await
+        .unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        assert_eq!(std::fs::read(&target).unwrap(), body);
+
+        // Throttle waits aren't held against the host's throughput
+        assert_eq!(transfer.bytes, body.len() as u64);
+        assert!(transfer.elapsed < Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_progress_without_content_length() {
+        let body = test_body();
+
+        // The body ends when the connection closes, like some archive endpoints
+        let server =
+            TestServer::canned(vec![Response::ok(body.clone()).without_content_length()]).await;
+        let url = server.url("/addon.zip");
+
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = events.clone();
+
+        download_with_retry(
+            &test_client(),
+            &url,
+            &target,
+            None,
+            Some(&temp.path().join("partial")),
+            &RetryPolicy::default(),
+            &DownloadControl::default(),
+            &|p: &TransferProgress| log.lock().unwrap().push(p.clone()),
+        )
+        .await
+        .unwrap();
+
+        let events = events.lock().unwrap();
+        let last = events.last().unwrap();
+        assert!(events.len() >= 2);
+        assert_eq!(last.downloaded, body.len() as u64);
+        assert_eq!(last.total, None);
+        assert_eq!(last.url, url);
+        assert_eq!(last.phase, DownloadPhase::Downloading);
+    }
+
+    #[tokio::test]
+    async fn test_download_with_fallback_skips_mismatched_source() {
+        let good = b"the real archive".to_vec();
+        let server = spawn_file_server(vec![
+            ("/tampered.zip", b"a tampered archive".to_vec()),
+            ("/good.zip", good.clone()),
+        ])
+        .await;
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+
+        let sources = vec![DownloadSource {
+            source_type: "github_archive".to_string(),
+            url: server.url("/tampered.zip"),
+            note: None,
+        }];
+        let integrity = integrity_for(&good);
+
+        download_with_fallback(
+            &test_client(),
+            &sources,
+            Some(&server.url("/good.zip")),
+            &target,
+            Some(&temp.path().join("partial")),
+            Some(&integrity),
+            None,
+            None,
+            &DownloadControl::default(),
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), good);
+    }
+
+    #[test]
+    fn test_jsdelivr_folders_are_download_candidates() {
+        let source = |source_type: &str, url: &str| DownloadSource {
+            source_type: source_type.to_string(),
+            url: url.to_string(),
+            note: None,
+        };
+        let sources = vec![
+            source("jsdelivr", "https://cdn.jsdelivr.net/gh/o/r@abc/MyAddon"),
+            source(
+                "jsdelivr",
+                "https://cdn.jsdelivr.net/gh/o/r@abc/MyAddon.zip",
+            ),
+            source("github_archive", "https://github.com/o/r/archive/abc.zip"),
+        ];
+
+        let candidates = download_candidates(&sources, Some("https://example.com/a.zip"));
+        let urls: Vec<_> = candidates.iter().map(|(_, url)| url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://github.com/o/r/archive/abc.zip",
+                "https://cdn.jsdelivr.net/gh/o/r@abc/MyAddon.zip",
+                "https://cdn.jsdelivr.net/gh/o/r@abc/MyAddon",
+                "https://example.com/a.zip",
+            ]
+        );
+        assert!(is_package_source(&candidates[2].0, &candidates[2].1));
+        assert!(!is_package_source(&candidates[1].0, &candidates[1].1));
+    }
+
+    #[tokio::test]
+    async fn test_cached_archive_is_used_instead_of_network() {
+        let body = b"addon archive bytes".to_vec();
+        let server = spawn_flaky_server("200 OK", 0, body.clone()).await;
+        let url = server.url("/addon.zip");
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("addon.zip");
+        let cache = ArchiveCache::new(temp.path().join("cache"), 1024 * 1024);
+        let integrity = integrity_for(&body);
+        let checksum = integrity.checksum.clone().unwrap();
+
+        let lookup = CacheLookup {
+            cache: &cache,
+            pin: Some(&checksum),
+            offline: false,
+        };
+        for _ in 0..2 {
+            download_with_fallback(
+                &test_client(),
+                &[],
+                Some(&url),
+                &target,
+                Some(&temp.path().join("partial")),
+                Some(&integrity),
+                Some(&lookup),
+                None,
+                &DownloadControl::default(),
+                |_| {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(std::fs::read(&target).unwrap(), body);
+        }
+        assert_eq!(server.requests().len(), 1);
+
+        // Offline, only cached archives can be installed
+        let offline = CacheLookup {
+            cache: &cache,
+            pin: Some("sha256:other"),
+            offline: true,
+        };
+        let result = download_with_fallback(
+            &test_client(),
+            &[],
+            Some(&url),
+            &target,
+            Some(&temp.path().join("partial")),
+            None,
+            Some(&offline),
+            None,
+            &DownloadControl::default(),
+            |_| {},
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::Download(_))));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_commit_range_from_compare_response() {
+        let data = serde_json::json!({
+            "status": "ahead",
+            "total_commits": 120,
+            "commits": [
+                {
+                    "sha": "b1",
+                    "commit": {
+                        "message": "Fix tooltip\n\nLonger description",
+                        "author": { "name": "Dev", "date": "2026-01-02T10:00:00Z" }
+                    }
+                },
+                { "sha": "c2", "commit": { "message": "Bump version" } },
+                { "commit": { "message": "Missing SHA is skipped" } }
+            ]
+        });
+
+        let range = parse_commit_range("a0", "c2", &data);
+        assert_eq!(
+            (range.from_sha.as_str(), range.to_sha.as_str()),
+            ("a0", "c2")
+        );
+        assert_eq!(range.total_commits, 120);
+        assert_eq!(range.commits.len(), 2);
+        assert_eq!(
+            range.commits[0].message,
+            "Fix tooltip\n\nLonger description"
+        );
+        assert_eq!(range.commits[0].author.as_deref(), Some("Dev"));
+        assert_eq!(range.commits[1].sha, "c2");
+        assert_eq!(range.commits[1].date, None);
+
+        let github = GitHubClient::default();
+        assert_eq!(
+            get_github_commit_url(&github, "o/r", "c2"),
+            "https://github.com/o/r/archive/c2.zip"
+        );
+        assert_eq!(short_sha("b1e4f2a9c0d3"), "b1e4f2a");
+        assert_eq!(short_sha("c2"), "c2");
+    }
+}
@@ -0,0 +1,635 @@
+use super::api_cache::{ApiCache, CachedResponse};
+use super::http::{client_builder, HttpConfig};
+use super::throttle::DownloadPriority;
+use crate::error::{AppError, Result};
+use crate::utils::checksum::sha256_hex;
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Media type requested from the GitHub REST API
+const GITHUB_API_ACCEPT: &str = "application/vnd.github.v3+json";
+
+/// Endpoint of the GitHub GraphQL API, which only answers authenticated requests
+const GITHUB_GRAPHQL_URL: &str = "https://api.github.com/graphql";
+
+/// Hosts the GitHub client (and with it the token) may be used for
+const GITHUB_HOSTS: [&str; 3] = ["github.com", "api.github.com", "codeload.github.com"];
+
+/// Share of the hourly budget kept for requests the user is waiting on; background
+/// requests such as update checks are deferred once less than this is left
+const BACKGROUND_RESERVE: f64 = 0.1;
+
+/// How long to back off after a secondary rate limit that doesn't say how long to wait
+const SECONDARY_LIMIT_WAIT: chrono::Duration = chrono::Duration::seconds(60);
+
+/// Whether a URL points at GitHub itself, so it may be requested with the GitHub client
+pub fn is_github_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| {
+        url.scheme() == "https" && url.host_str().is_some_and(|h| GITHUB_HOSTS.contains(&h))
+    })
+}
+
+/// HTTP client for the GitHub API and GitHub downloads
+///
+/// Built with the same network settings as the shared client, plus the user's personal
+/// access token when one is stored. Only use it for GitHub URLs (see `is_github_url`);
+/// reqwest drops the token itself when a download redirects to another host.
+/// Downloads default to `application/octet-stream`, which makes the API's release
+/// asset URLs serve the file, so private repositories can be installed as well.
+///
+/// API requests made with `send` keep track of the rate limit budget, which clones
+/// share. Once it is used up requests fail with `AppError::RateLimited` without
+/// reaching GitHub, and clients made with `background` already stop when the budget
+/// runs low, leaving the rest for what the user does.
+///
+/// Requests made with `get_cached` go through the API response cache: they are
+/// revalidated with conditional requests, or for clients made with `cached_for` not
+/// sent at all while the cached response is recent enough.
+#[derive(Clone, Default)]
+pub struct GitHubClient {
+    http: reqwest::Client,
+    authenticated: bool,
+    priority: DownloadPriority,
+    budget: Arc<Mutex<RateBudget>>,
+    cache: Option<ApiCache>,
+    max_age: Duration,
+}
+
+/// Response to a request made through the API response cache
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+impl ApiResponse {
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    pub fn json(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+impl From<CachedResponse> for ApiResponse {
+    fn from(cached: CachedResponse) -> Self {
+        Self {
+            status: StatusCode::OK,
+            body: cached.body,
+        }
+    }
+}
+
+/// Rate limit of one GitHub API resource (`core`, `graphql`, `search`, ...)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimit {
+    pub resource: String,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_at: DateTime<Utc>,
+}
+
+/// What is known about the GitHub API budget, for display
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHubBudget {
+    pub authenticated: bool,
+    pub limits: Vec<RateLimit>,
+    /// Set while GitHub asked to back off after a secondary rate limit
+    pub blocked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct RateBudget {
+    limits: HashMap<String, RateLimit>,
+    blocked_until: Option<DateTime<Utc>>,
+}
+
+impl RateBudget {
+    /// Fail if a request to `resource` would exceed the budget
+    fn check(&self, resource: &str, priority: DownloadPriority, now: DateTime<Utc>) -> Result<()> {
+        if let Some(until) = self.blocked_until.filter(|until| *until > now) {
+            return Err(AppError::RateLimited { reset_at: until });
+        }
+
+        let Some(limit) = self.limits.get(resource).filter(|l| l.reset_at > now) else {
+            return Ok(());
+        };
+        let reserve = match priority {
+            DownloadPriority::User => 0,
+            DownloadPriority::Background => (limit.limit as f64 * BACKGROUND_RESERVE).ceil() as u32,
+        };
+        if limit.remaining <= reserve {
+            return Err(AppError::RateLimited {
+                reset_at: limit.reset_at,
+            });
+        }
+        Ok(())
+    }
+
+    /// Take the budget from a response; returns when to try again if it was rate limited
+    fn observe(
+        &mut self,
+        resource: &str,
+        status: StatusCode,
+        headers: &HeaderMap,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let number = |name: &str| header(name).and_then(|v| v.trim().parse::<i64>().ok());
+
+        let limit = match (
+            number("x-ratelimit-limit"),
+            number("x-ratelimit-remaining"),
+            number("x-ratelimit-reset").and_then(|t| DateTime::from_timestamp(t, 0)),
+        ) {
+            (Some(limit), Some(remaining), Some(reset_at)) => {
+                let resource = header("x-ratelimit-resource").unwrap_or(resource);
+                let limit = RateLimit {
+                    resource: resource.to_string(),
+                    limit: limit.max(0) as u32,
+                    remaining: remaining.max(0) as u32,
+                    reset_at,
+                };
+                self.limits.insert(limit.resource.clone(), limit.clone());
+                Some(limit)
+            }
+            _ => None,
+        };
+
+        if !matches!(
+            status,
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+        ) {
+            return None;
+        }
+
+        // Secondary limits say how long to wait, or at least answer 429
+        let retry_after = number(RETRY_AFTER.as_str()).map(chrono::Duration::seconds);
+        if let Some(wait) = retry_after
+            .or((status == StatusCode::TOO_MANY_REQUESTS).then_some(SECONDARY_LIMIT_WAIT))
+        {
+            let until = now + wait;
+            self.blocked_until = Some(until);
+            return Some(until);
+        }
+
+        // Any other 403 is a plain permission error unless the budget is used up
+        limit
+            .filter(|limit| limit.remaining == 0)
+            .map(|limit| limit.reset_at)
+    }
+}
+
+/// Rate limit resource an API URL counts against
+fn resource_of(url: &reqwest::Url) -> &'static str {
+    let path = url.path();
+    if path == "/graphql" {
+        "graphql"
+    } else if path.starts_with("/search/") {
+        "search"
+    } else {
+        "core"
+    }
+}
+
+impl GitHubClient {
+    pub fn new(config: &HttpConfig, token: Option<&str>) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/octet-stream"));
+
+        let token = token.map(str::trim).filter(|t| !t.is_empty());
+        if let Some(token) = token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| AppError::Custom("GitHub token contains invalid characters".into()))?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        // Each token gets its own cached responses, which may include private repositories
+        let scope = token.map_or_else(|| "anonymous".to_string(), |t| sha256_hex(t.as_bytes()));
+
+        Ok(Self {
+            http: client_builder(config)?.default_headers(headers).build()?,
+            authenticated: token.is_some(),
+            cache: ApiCache::open(&scope),
+            ..Default::default()
+        })
+    }
+
+    /// Use a different API response cache, or none
+    pub fn with_cache(self, cache: Option<ApiCache>) -> Self {
+        Self { cache, ..self }
+    }
+
+    /// A client sharing this one's budget and cache that uses cached responses younger
+    /// than `max_age` without asking GitHub, for data that may be slightly out of date
+    pub fn cached_for(&self, max_age: Duration) -> Self {
+        Self {
+            max_age,
+            ..self.clone()
+        }
+    }
+
+    /// A client sharing this one's budget whose API requests can wait, like update
+    /// checks; they are deferred while the budget is low
+    pub fn background(&self) -> Self {
+        Self {
+            priority: DownloadPriority::Background,
+            ..self.clone()
+        }
+    }
+
+    /// Whether requests carry a personal access token
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
+    /// The underlying client, for downloading GitHub URLs
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// Start a GET request to the GitHub REST API
+    pub fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.http.get(url).header(ACCEPT, GITHUB_API_ACCEPT)
+    }
+
+    /// Send an API request within the rate limit budget
+    ///
+    /// Fails with `AppError::RateLimited` instead of sending when the budget is used up
+    /// (or, for background clients, low), and when GitHub answers with a rate limit.
+    /// Other responses are returned as they are, whatever their status.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let request = request.build()?;
+        let resource = resource_of(request.url());
+        self.lock_budget()?
+            .check(resource, self.priority, Utc::now())?;
+
+        let response = self.http.execute(request).await?;
+
+        let limited = self.lock_budget()?.observe(
+            resource,
+            response.status(),
+            response.headers(),
+            Utc::now(),
+        );
+        match limited {
+            Some(reset_at) => Err(AppError::RateLimited { reset_at }),
+            None => Ok(response),
+        }
+    }
+
+    /// Run a GraphQL query and return its `data`
+    ///
+    /// Requires a token. Errors about single fields (e.g. a repository that doesn't
+    /// exist) leave those fields null; the query only fails when there is no data.
+    pub async fn graphql(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let request = self
+            .http
+            .post(GITHUB_GRAPHQL_URL)
+            .header(ACCEPT, "application/json")
+            .json(&serde_json::json!({ "query": query, "variables": variables }));
+        let response = self.send(request).await?;
+        if !response.status().is_success() {
+            return Err(AppError::HttpStatus(
+                response.status().as_u16(),
+                GITHUB_GRAPHQL_URL.to_string(),
+            ));
+        }
+
+        let mut body: serde_json::Value = response.json().await?;
+        let data = body.get_mut("data").map(serde_json::Value::take);
+        if let Some(data) = data.filter(|d| !d.is_null()) {
+            return Ok(data);
+        }
+
+        let errors = body.get("errors").and_then(|e| e.as_array());
+        let error_type = |kind: &str| {
+            errors.is_some_and(|errors| {
+                errors
+                    .iter()
+                    .any(|e| e.get("type").and_then(|t| t.as_str()) == Some(kind))
+            })
+        };
+        // The GraphQL API reports a used up budget as an error with status 200
+        if error_type("RATE_LIMITED") {
+            let reset_at = self
+                .lock_budget()?
+                .limits
+                .get("graphql")
+                .map(|limit| limit.reset_at)
+                .unwrap_or_else(|| Utc::now() + SECONDARY_LIMIT_WAIT);
+            return Err(AppError::RateLimited { reset_at });
+        }
+
+        let message = errors
+            .and_then(|errors| errors.first())
+            .and_then(|e| e.get("message"))
+            .and_then(|m| m.as_str())
+            .unwrap_or("no data returned");
+        Err(AppError::Custom(format!(
+            "GitHub GraphQL query failed: {}",
+            message
+        )))
+    }
+
+    /// GET an API URL through the response cache
+    ///
+    /// A cached response is revalidated with its ETag or Last-Modified value; a 304
+    /// answer doesn't count against the rate limit. While rate limited, the cached
+    /// response is used regardless of its age. Only successful responses are cached.
+    pub async fn get_cached(&self, url: &str) -> Result<ApiResponse> {
+        let Some(cache) = &self.cache else {
+            let response = self.send(self.get(url)).await?;
+            let status = response.status();
+            let body = response.text().await?;
+            return Ok(ApiResponse { status, body });
+        };
+
+        let cached = cache.get(url);
+        if let Some(entry) = cached
+            .as_ref()
+            .filter(|e| e.is_fresh(self.max_age, Utc::now()))
+        {
+            return Ok(entry.clone().into());
+        }
+
+        let mut request = self.get(url);
+        if let Some(entry) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = match (self.send(request).await, cached) {
+            (Ok(response), cached) => (response, cached),
+            (Err(AppError::RateLimited { .. }), Some(entry)) => return Ok(entry.into()),
+            (Err(e), _) => return Err(e),
+        };
+
+        match response {
+            (response, Some(mut entry)) if response.status() == StatusCode::NOT_MODIFIED => {
+                // Caching is best effort; the cached response is still valid
+                entry.fetched_at = Utc::now();
+                let _ = cache.put(&entry);
+                Ok(entry.into())
+            }
+            (response, _) => {
+                let status = response.status();
+                let header = |name| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|v: &HeaderValue| v.to_str().ok())
+                        .map(String::from)
+                };
+                let etag = header(ETAG);
+                let last_modified = header(LAST_MODIFIED);
+                let body = response.text().await?;
+
+                if status.is_success() {
+                    let _ = cache.put(&CachedResponse {
+                        url: url.to_string(),
+                        etag,
+                        last_modified,
+                        body: body.clone(),
+                        fetched_at: Utc::now(),
+                    });
+                }
+                Ok(ApiResponse { status, body })
+            }
+        }
+    }
+
+    /// The budget as of the last API response
+    pub fn budget(&self) -> GitHubBudget {
+        let now = Utc::now();
+        let budget = self.budget.lock().map(|b| {
+            let mut limits: Vec<_> = b.limits.values().cloned().collect();
+            limits.sort_by(|a, b| a.resource.cmp(&b.resource));
+            (limits, b.blocked_until.filter(|until| *until > now))
+        });
+        let (limits, blocked_until) = budget.unwrap_or_default();
+        GitHubBudget {
+            authenticated: self.authenticated,
+            limits,
+            blocked_until,
+        }
+    }
+
+    /// Ask GitHub for the current budget; this request doesn't count against it
+    pub async fn refresh_budget(&self) -> Result<GitHubBudget> {
+        let url = "https://api.github.com/rate_limit";
+        let response = self.send(self.get(url)).await?;
+        if !response.status().is_success() {
+            return Err(AppError::HttpStatus(
+                response.status().as_u16(),
+                url.to_string(),
+            ));
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        if let Some(resources) = data.get("resources").and_then(|r| r.as_object()) {
+            let mut budget = self.lock_budget()?;
+            for (resource, limit) in resources {
+                let field = |name: &str| limit.get(name).and_then(|v| v.as_i64());
+                if let (Some(total), Some(remaining), Some(reset_at)) = (
+                    field("limit"),
+                    field("remaining"),
+                    field("reset").and_then(|t| DateTime::from_timestamp(t, 0)),
+                ) {
+                    budget.limits.insert(
+                        resource.clone(),
+                        RateLimit {
+                            resource: resource.clone(),
+                            limit: total.max(0) as u32,
+                            remaining: remaining.max(0) as u32,
+                            reset_at,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(self.budget())
+    }
+
+    fn lock_budget(&self) -> Result<std::sync::MutexGuard<'_, RateBudget>> {
+        self.budget
+            .lock()
+            .map_err(|_| AppError::Custom("GitHub rate limit state is unavailable".into()))
+    }
+
+    /// Login of the user the token belongs to; fails if GitHub rejects the token
+    pub async fn authenticated_user(&self) -> Result<String> {
+        let url = "https://api.github.com/user";
+        let response = self.send(self.get(url)).await?;
+        if !response.status().is_success() {
+            return Err(AppError::HttpStatus(
+                response.status().as_u16(),
+                url.to_string(),
+            ));
+        }
+
+        let user: serde_json::Value = response.json().await?;
+        Ok(user
+            .get("login")
+            .and_then(|l| l.as_str())
+            .unwrap_or_default()
+            .to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{request_header, Response, TestServer};
+
+    #[test]
+    fn test_token_is_only_for_github_urls() {
+        assert!(is_github_url("https://api.github.com/repos/o/r"));
+        assert!(is_github_url("https://github.com/o/r/archive/main.zip"));
+        assert!(!is_github_url("http://api.github.com/repos/o/r"));
+        assert!(!is_github_url("https://github.com.example.org/o/r"));
+        assert!(!is_github_url("https://cdn.jsdelivr.net/gh/o/r@main"));
+    }
+
+    #[tokio::test]
+    async fn test_token_is_sent_with_requests() {
+        let server = TestServer::canned(vec![Response::ok("{}")]).await;
+        let url = server.url("/repos/o/r");
+
+        let client = GitHubClient::new(&HttpConfig::default(), Some(" ghp_secret \n")).unwrap();
+        assert!(client.is_authenticated());
+        client.get(&url).send().await.unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(
+            request_header(request, "authorization"),
+            Some("Bearer ghp_secret")
+        );
+        assert_eq!(
+            request_header(request, "accept"),
+            Some("application/vnd.github.v3+json")
+        );
+
+        let anonymous = GitHubClient::new(&HttpConfig::default(), Some("  ")).unwrap();
+        assert!(!anonymous.is_authenticated());
+    }
+
+    #[test]
+    fn test_background_requests_leave_a_reserve() {
+        let now = Utc::now();
+        let reset = now + chrono::Duration::minutes(30);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("60"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("5"));
+        let reset_header = reset.timestamp().to_string();
+        headers.insert(
+            "x-ratelimit-reset",
+            HeaderValue::from_str(&reset_header).unwrap(),
+        );
+
+        let mut budget = RateBudget::default();
+        assert_eq!(budget.observe("core", StatusCode::OK, &headers, now), None);
+
+        // 5 of 60 left: enough for the user, not for update checks
+        assert!(budget.check("core", DownloadPriority::User, now).is_ok());
+        assert!(matches!(
+            budget.check("core", DownloadPriority::Background, now),
+            Err(AppError::RateLimited { .. })
+        ));
+        assert!(budget
+            .check("graphql", DownloadPriority::Background, now)
+            .is_ok());
+
+        // Used up: a 403 now means the budget, not a permission problem
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        let limited = budget.observe("core", StatusCode::FORBIDDEN, &headers, now);
+        assert_eq!(limited.map(|t| t.timestamp()), Some(reset.timestamp()));
+        assert!(budget.check("core", DownloadPriority::User, now).is_err());
+
+        // The budget is back once it resets
+        let later = reset + chrono::Duration::seconds(1);
+        assert!(budget
+            .check("core", DownloadPriority::Background, later)
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_secondary_rate_limit_stops_requests() {
+        let server = TestServer::canned(vec![
+            Response::status("403 Forbidden").header("Retry-After", "30")
+        ])
+        .await;
+        let url = server.url("/repos/o/r");
+
+        let client = GitHubClient::new(&HttpConfig::default(), None).unwrap();
+        let result = client.send(client.get(&url)).await;
+        let Err(AppError::RateLimited { reset_at }) = result else {
+            panic!("expected a rate limit error");
+        };
+        assert!(reset_at > Utc::now() + chrono::Duration::seconds(20));
+
+        // Clones share the budget and don't contact GitHub until the wait is over
+        let clone = client.background();
+        assert!(matches!(
+            clone.send(clone.get(&url)).await,
+            Err(AppError::RateLimited { .. })
+        ));
+        assert_eq!(client.budget().blocked_until, Some(reset_at));
+    }
+
+    #[tokio::test]
+    async fn test_cached_responses_are_revalidated() {
+        // Answers 200 with an ETag, then 304 to requests that send it back
+        let server = TestServer::spawn(|request, _| {
+            if request_header(request, "if-none-match") == Some("\"v1\"") {
+                Response::status("304 Not Modified").header("ETag", "\"v1\"")
+            } else {
+                Response::ok(r#"{"tag_name":"v1"}"#).header("ETag", "\"v1\"")
+            }
+        })
+        .await;
+        let url = server.url("/repos/o/r/releases/latest");
+
+        let temp = tempfile::tempdir().unwrap();
+        let client = GitHubClient::new(&HttpConfig::default(), None)
+            .unwrap()
+            .with_cache(Some(ApiCache::new(temp.path().to_path_buf(), "anonymous")));
+
+        let first = client.get_cached(&url).await.unwrap();
+        let second = client.get_cached(&url).await.unwrap();
+        assert_eq!(second.status, StatusCode::OK);
+        assert_eq!(second.body, first.body);
+        assert_eq!(second.json().unwrap()["tag_name"], "v1");
+
+        // Within the TTL the cached response is used without a request
+        let cached = client.cached_for(Duration::from_secs(60));
+        assert_eq!(cached.get_cached(&url).await.unwrap().body, first.body);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            request_header(&requests[1], "if-none-match"),
+            Some("\"v1\"")
+        );
+    }
+}
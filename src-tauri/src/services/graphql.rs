@@ -0,0 +1,191 @@
+use super::github::GitHubClient;
+use crate::error::Result;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Repositories looked up per GraphQL query, keeping each query well below
+/// GitHub's node and complexity limits
+const REPOS_PER_QUERY: usize = 50;
+
+/// Fields fetched for every repository
+const REPO_STATUS_FRAGMENT: &str = "fragment RepoStatus on Repository {
+  latestRelease { tagName }
+  refs(refPrefix: \"refs/tags/\", first: 1, orderBy: {field: TAG_COMMIT_DATE, direction: DESC}) {
+    nodes { name }
+  }
+  defaultBranchRef { name target { oid } }
+}";
+
+/// A repository to look up, with the branch it is tracked on
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedRepo {
+    /// `owner/name`
+    pub repo: String,
+    /// Branch to report the head of; the default branch when None
+    pub branch: Option<String>,
+}
+
+/// Latest release, tag and branch head of a repository
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepoStatus {
+    pub latest_release_tag: Option<String>,
+    /// Most recently committed tag, whether or not it has a release
+    pub latest_tag: Option<String>,
+    /// Name of the reported branch
+    pub branch: Option<String>,
+    /// Commit SHA at the head of the branch
+    pub head_sha: Option<String>,
+}
+
+/// Look up the status of many repositories with a few GraphQL queries
+///
+/// Needs a token, as the GraphQL API doesn't answer anonymous requests. Repositories
+/// that don't exist or aren't visible to the token are left out of the result.
+pub async fn fetch_repo_statuses(
+    github: &GitHubClient,
+    repos: &[TrackedRepo],
+) -> Result<HashMap<String, RepoStatus>> {
+    let mut statuses = HashMap::new();
+    for chunk in repos.chunks(REPOS_PER_QUERY) {
+        let (query, variables) = build_status_query(chunk);
+        let data = github.graphql(&query, variables).await?;
+        statuses.extend(parse_statuses(chunk, &data));
+    }
+    Ok(statuses)
+}
+
+/// Query with one aliased `repository` field per repo; names are passed as variables
+fn build_status_query(repos: &[TrackedRepo]) -> (String, Value) {
+    let mut parameters = Vec::new();
+    let mut fields = Vec::new();
+    let mut variables = Map::new();
+
+    for (i, tracked) in repos.iter().enumerate() {
+        let (owner, name) = tracked.repo.split_once('/').unwrap_or(("", &tracked.repo));
+        variables.insert(format!("owner{}", i), json!(owner));
+        variables.insert(format!("name{}", i), json!(name));
+        parameters.push(format!("$owner{i}: String!, $name{i}: String!"));
+
+        let branch = match &tracked.branch {
+            Some(branch) => {
+                variables.insert(
+                    format!("branch{}", i),
+                    json!(format!("refs/heads/{}", branch)),
+                );
+                parameters.push(format!("$branch{}: String!", i));
+                format!(
+                    " branch: ref(qualifiedName: $branch{}) {{ target {{ oid }} }}",
+                    i
+                )
+            }
+            None => String::new(),
+        };
+        fields.push(format!(
+            "  r{i}: repository(owner: $owner{i}, name: $name{i}) {{ ...RepoStatus{branch} }}"
+        ));
+    }
+
+    let query = format!(
+        "query({}) {{\n{}\n}}\n{}",
+        parameters.join(", "),
+        fields.join("\n"),
+        REPO_STATUS_FRAGMENT
+    );
+    (query, Value::Object(variables))
+}
+
+fn parse_statuses(repos: &[TrackedRepo], data: &Value) -> HashMap<String, RepoStatus> {
+    let text = |value: &Value, pointer: &str| {
+        value
+            .pointer(pointer)
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    };
+
+    repos
+        .iter()
+        .enumerate()
+        .filter_map(|(i, tracked)| {
+            let repository = data.get(format!("r{}", i)).filter(|r| !r.is_null())?;
+            let (branch, head_sha) = match &tracked.branch {
+                Some(branch) => (Some(branch.clone()), text(repository, "/branch/target/oid")),
+                None => (
+                    text(repository, "/defaultBranchRef/name"),
+                    text(repository, "/defaultBranchRef/target/oid"),
+                ),
+            };
+            let status = RepoStatus {
+                latest_release_tag: text(repository, "/latestRelease/tagName"),
+                latest_tag: text(repository, "/refs/nodes/0/name"),
+                branch,
+                head_sha,
+            };
+            Some((tracked.repo.clone(), status))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracked(repo: &str, branch: Option<&str>) -> TrackedRepo {
+        TrackedRepo {
+            repo: repo.to_string(),
+            branch: branch.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_status_query_passes_names_as_variables() {
+        let repos = vec![tracked("o/a", None), tracked("o/b\"}", Some("dev"))];
+        let (query, variables) = build_status_query(&repos);
+
+        assert!(query.starts_with(
+            "query($owner0: String!, $name0: String!, $owner1: String!, $name1: String!, $branch1: String!)"
+        ));
+        assert!(query.contains("r0: repository(owner: $owner0, name: $name0) { ...RepoStatus }"));
+        assert!(query.contains("branch: ref(qualifiedName: $branch1)"));
+        assert!(!query.contains("b\"}"));
+        assert_eq!(variables["name1"], "b\"}");
+        assert_eq!(variables["branch1"], "refs/heads/dev");
+    }
+
+    #[test]
+    fn test_statuses_are_parsed_per_repo() {
+        let repos = vec![
+            tracked("o/a", None),
+            tracked("o/missing", None),
+            tracked("o/c", Some("dev")),
+        ];
+        let data = json!({
+            "r0": {
+                "latestRelease": { "tagName": "v2.0" },
+                "refs": { "nodes": [{ "name": "v2.1-beta" }] },
+                "defaultBranchRef": { "name": "main", "target": { "oid": "aaa" } }
+            },
+            "r1": null,
+            "r2": {
+                "latestRelease": null,
+                "refs": { "nodes": [] },
+                "defaultBranchRef": { "name": "main", "target": { "oid": "bbb" } },
+                "branch": { "target": { "oid": "ccc" } }
+            }
+        });
+
+        let statuses = parse_statuses(&repos, &data);
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(
+            statuses["o/a"],
+            RepoStatus {
+                latest_release_tag: Some("v2.0".to_string()),
+                latest_tag: Some("v2.1-beta".to_string()),
+                branch: Some("main".to_string()),
+                head_sha: Some("aaa".to_string()),
+            }
+        );
+        assert_eq!(statuses["o/c"].latest_release_tag, None);
+        assert_eq!(statuses["o/c"].branch.as_deref(), Some("dev"));
+        assert_eq!(statuses["o/c"].head_sha.as_deref(), Some("ccc"));
+    }
+}
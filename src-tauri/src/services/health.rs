@@ -0,0 +1,293 @@
+use super::database;
+use crate::error::{AppError, Result};
+use crate::models::HostHealth;
+use crate::utils::retry::is_transient_status;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long a host is skipped after its first failure; doubles with each further one
+const COOLDOWN_BASE: Duration = Duration::from_secs(5 * 60);
+/// Longest a host is skipped for
+const COOLDOWN_MAX: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Weight of the newest sample in the smoothed throughput
+const THROUGHPUT_SMOOTHING: f64 = 0.3;
+
+/// Host a URL is downloaded from, with the port when it isn't the default one
+pub fn host_of(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// Whether a failed download says something about the host rather than the file
+/// Unreachable hosts, server errors and blocked requests count; missing files and
+/// integrity failures don't.
+pub fn is_host_failure(error: &AppError) -> bool {
+    match error {
+        AppError::Network(_) => true,
+        AppError::HttpStatus(status, _) => {
+            is_transient_status(*status) || matches!(status, 403 | 451)
+        }
+        _ => false,
+    }
+}
+
+/// The outcome of downloading from one source
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceOutcome {
+    pub host: String,
+    pub success: bool,
+    pub bytes_per_second: Option<f64>,
+}
+
+impl HostHealth {
+    /// Account for one download from this host
+    pub fn record(&mut self, outcome: &SourceOutcome, now: DateTime<Utc>) {
+        let now = Some(now.to_rfc3339());
+        if outcome.success {
+            self.successes += 1;
+            self.consecutive_failures = 0;
+            self.last_success_at = now;
+            if let Some(sample) = outcome.bytes_per_second {
+                self.bytes_per_second = Some(match self.bytes_per_second {
+                    Some(rate) => rate + THROUGHPUT_SMOOTHING * (sample - rate),
+                    None => sample,
+                });
+            }
+        } else {
+            self.failures += 1;
+            self.consecutive_failures += 1;
+            self.last_failure_at = now;
+        }
+    }
+
+    /// Whether the host failed too recently to be tried before healthy ones
+    pub fn is_cooling_down(&self, now: DateTime<Utc>) -> bool {
+        if self.consecutive_failures == 0 {
+            return false;
+        }
+        let Some(failed_at) = self
+            .last_failure_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        else {
+            return false;
+        };
+
+        let cooldown = COOLDOWN_BASE
+            .saturating_mul(2u32.saturating_pow(self.consecutive_failures - 1))
+            .min(COOLDOWN_MAX);
+        now.signed_duration_since(failed_at)
+            .to_std()
+            .map_or(true, |since| since < cooldown)
+    }
+
+    /// Share of downloads that succeeded, assuming one success and one failure up front
+    /// so a single result doesn't decide everything
+    fn reliability(&self) -> f64 {
+        (self.successes as f64 + 1.0) / ((self.successes + self.failures) as f64 + 2.0)
+    }
+}
+
+/// Host health consulted for one download, collecting the outcome of each source tried
+pub struct SourceHealth {
+    hosts: HashMap<String, HostHealth>,
+    outcomes: Mutex<Vec<SourceOutcome>>,
+}
+
+impl SourceHealth {
+    pub fn new(hosts: Vec<HostHealth>) -> Self {
+        Self {
+            hosts: hosts.into_iter().map(|h| (h.host.clone(), h)).collect(),
+            outcomes: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Load the recorded health of every host
+    pub fn load(conn: &Connection) -> Self {
+        Self::new(database::get_host_health(conn).unwrap_or_default())
+    }
+
+    /// Reorder download candidates (source label and URL) by the health of their hosts
+    ///
+    /// Hosts that failed recently go last, then the most reliable hosts go first, with
+    /// faster hosts first among equally reliable ones. Hosts of similar reliability and
+    /// speed keep their original order, as do hosts without any history.
+    pub fn order(&self, candidates: &mut [(String, String)], now: DateTime<Utc>) {
+        candidates.sort_by_cached_key(|(_, url)| {
+            let Some(health) = host_of(url).and_then(|host| self.hosts.get(&host)) else {
+                return (false, Reverse(10), Reverse(0));
+            };
+            // Reliability in steps of 5%, throughput in factors of 2
+            let reliability = (health.reliability() * 20.0).round() as u32;
+            let speed = health
+                .bytes_per_second
+                .map_or(0, |rate| rate.max(1.0).log2() as u32);
+            (
+                health.is_cooling_down(now),
+                Reverse(reliability),
+                Reverse(speed),
+            )
+        });
+    }
+
+    /// Remember the outcome of downloading from a URL
+    pub fn record(&self, url: &str, success: bool, bytes_per_second: Option<f64>) {
+        let Some(host) = host_of(url) else {
+            return;
+        };
+        if let Ok(mut outcomes) = self.outcomes.lock() {
+            outcomes.push(SourceOutcome {
+                host,
+                success,
+                bytes_per_second,
+            });
+        }
+    }
+
+    /// Outcomes recorded so far
+    pub fn outcomes(&self) -> Vec<SourceOutcome> {
+        self.outcomes
+            .lock()
+            .map(|outcomes| outcomes.clone())
+            .unwrap_or_default()
+    }
+
+    /// Add the recorded outcomes to the stored host health
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        let now = Utc::now();
+        for outcome in self.outcomes() {
+            let mut health =
+                database::get_host_health_for(conn, &outcome.host)?.unwrap_or_else(|| HostHealth {
+                    host: outcome.host.clone(),
+                    ..Default::default()
+                });
+            health.record(&outcome, now);
+            database::save_host_health(conn, &health)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(url: &str) -> (String, String) {
+        ("github_archive".to_string(), url.to_string())
+    }
+
+    fn outcome(host: &str, success: bool) -> SourceOutcome {
+        SourceOutcome {
+            host: host.to_string(),
+            success,
+            bytes_per_second: success.then_some(1_000_000.0),
+        }
+    }
+
+    #[test]
+    fn test_failed_hosts_cool_down() {
+        let now = Utc::now();
+        let mut health = HostHealth::default();
+
+        health.record(&outcome("a.example", false), now);
+        assert!(health.is_cooling_down(now + chrono::Duration::minutes(4)));
+        assert!(!health.is_cooling_down(now + chrono::Duration::minutes(6)));
+
+        // The cooldown doubles with each failure in a row
+        health.record(&outcome("a.example", false), now);
+        assert!(health.is_cooling_down(now + chrono::Duration::minutes(9)));
+
+        health.record(&outcome("a.example", true), now);
+        assert!(!health.is_cooling_down(now));
+        assert_eq!((health.successes, health.failures), (1, 2));
+        assert_eq!(health.bytes_per_second, Some(1_000_000.0));
+    }
+
+    #[test]
+    fn test_candidates_are_ordered_by_health() {
+        let now = Utc::now();
+        let host = |name: &str, successes, failures, rate: Option<f64>| HostHealth {
+            host: name.to_string(),
+            successes,
+            failures,
+            bytes_per_second: rate,
+            ..Default::default()
+        };
+        let mut blocked = host("blocked.example", 3, 1, Some(1_000_000.0));
+        blocked.record(&outcome("blocked.example", false), now);
+
+        let health = SourceHealth::new(vec![
+            blocked,
+            host("flaky.example", 1, 4, Some(1_000_000.0)),
+            host("slow.example", 8, 0, Some(50_000.0)),
+            host("fast.example", 8, 0, Some(2_000_000.0)),
+        ]);
+
+        let mut candidates = vec![
+            candidate("https://blocked.example/a.zip"),
+            candidate("https://flaky.example/a.zip"),
+            candidate("https://unknown.example/a.zip"),
+            candidate("https://slow.example/a.zip"),
+            candidate("https://fast.example/a.zip"),
+        ];
+        health.order(&mut candidates, now);
+
+        let hosts: Vec<_> = candidates
+            .iter()
+            .map(|(_, url)| host_of(url).unwrap())
+            .collect();
+        assert_eq!(
+            hosts,
+            vec![
+                "fast.example",
+                "slow.example",
+                "unknown.example",
+                "flaky.example",
+                "blocked.example",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_outcomes_are_saved_per_host() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+
+        let health = SourceHealth::load(&conn);
+        health.record("https://cdn.example/a.zip", false, None);
+        health.record("http://127.0.0.1:8080/a.zip", true, Some(500.0));
+        health.record("https://cdn.example/b.zip", true, Some(1_000.0));
+        health.save(&conn).unwrap();
+
+        let cdn = database::get_host_health_for(&conn, "cdn.example")
+            .unwrap()
+            .unwrap();
+        assert_eq!((cdn.successes, cdn.failures), (1, 1));
+        assert_eq!(cdn.consecutive_failures, 0);
+        assert_eq!(cdn.bytes_per_second, Some(1_000.0));
+        assert!(cdn.last_failure_at.is_some());
+
+        let hosts = database::get_host_health(&conn).unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[0].host, "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn test_only_host_problems_count_as_failures() {
+        assert!(is_host_failure(&AppError::HttpStatus(503, String::new())));
+        assert!(is_host_failure(&AppError::HttpStatus(403, String::new())));
+        assert!(!is_host_failure(&AppError::HttpStatus(404, String::new())));
+        assert!(!is_host_failure(&AppError::Integrity(
+            "mismatch".to_string()
+        )));
+    }
+}
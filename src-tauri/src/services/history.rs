@@ -0,0 +1,228 @@
+use crate::error::{AppError, Result};
+use crate::models::{InstallHistoryEntry, InstallInfo, InstalledAddon};
+use crate::services::database;
+use chrono::Utc;
+use rusqlite::Connection;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Number of previous installs kept per addon when no setting is stored
+pub const DEFAULT_HISTORY_RETENTION: usize = 3;
+
+/// Get the configured number of installs to retain per addon
+pub fn get_history_retention(conn: &Connection) -> usize {
+    database::get_setting(conn, "history_retention")
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_RETENTION)
+}
+
+/// Retain the archive of a completed install and record it in the history table
+///
+/// Besides this install, the newest `keep` previous installs are retained; older
+/// entries are pruned together with their archives.
+pub fn record_install(
+    conn: &Connection,
+    history_dir: &Path,
+    addon: &InstalledAddon,
+    archive_path: &Path,
+    install_info: Option<&InstallInfo>,
+    keep: usize,
+) -> Result<InstallHistoryEntry> {
+    let slug_dir = history_dir.join(sanitize_file_name(&addon.slug));
+    fs::create_dir_all(&slug_dir)?;
+
+    let now = Utc::now();
+    let retained_path = slug_dir.join(format!(
+        "{}-{}.zip",
+        now.timestamp_millis(),
+        sanitize_file_name(&addon.installed_version)
+    ));
+    fs::copy(archive_path, &retained_path)?;
+
+    let mut entry = InstallHistoryEntry {
+        id: 0,
+        slug: addon.slug.clone(),
+        name: addon.name.clone(),
+        version: addon.installed_version.clone(),
+        source_type: addon.source_type.clone(),
+        source_repo: addon.source_repo.clone(),
+        version_sort_key: addon.version_sort_key,
+        commit_sha: addon.commit_sha.clone(),
+        install_info: install_info.cloned(),
+        archive_path: retained_path.to_string_lossy().to_string(),
+        installed_at: now.to_rfc3339(),
+    };
+
+    entry.id = match database::insert_install_history(conn, &entry) {
+        Ok(id) => id,
+        Err(e) => {
+            let _ = fs::remove_file(&retained_path);
+            return Err(e);
+        }
+    };
+
+    // The entry just recorded is the current install, not one of the previous `keep`
+    prune_history(conn, &addon.slug, keep + 1)?;
+
+    Ok(entry)
+}
+
+/// Remove history entries (and their archives) beyond the newest `keep`
+pub fn prune_history(conn: &Connection, slug: &str, keep: usize) -> Result<()> {
+    let entries = database::get_install_history(conn, slug)?;
+
+    for entry in entries.into_iter().skip(keep) {
+        remove_entry(conn, &entry)?;
+    }
+
+    Ok(())
+}
+
+/// Remove all history entries (and their archives) for an addon
+pub fn clear_history(conn: &Connection, slug: &str) -> Result<()> {
+    prune_history(conn, slug, 0)
+}
+
+/// Find the most recent retained install of a specific version
+pub fn find_entry(conn: &Connection, slug: &str, version: &str) -> Result<InstallHistoryEntry> {
+    let entry = database::get_install_history(conn, slug)?
+        .into_iter()
+        .find(|e| e.version == version)
+        .ok_or_else(|| {
+            AppError::Custom(format!(
+                "No retained install of {} version {}",
+                slug, version
+            ))
+        })?;
+
+    if !Path::new(&entry.archive_path).exists() {
+        return Err(AppError::Custom(format!(
+            "Retained archive for {} version {} is missing",
+            slug, version
+        )));
+    }
+
+    Ok(entry)
+}
+
+fn remove_entry(conn: &Connection, entry: &InstallHistoryEntry) -> Result<()> {
+    let archive = PathBuf::from(&entry.archive_path);
+    if archive.exists() {
+        fs::remove_file(&archive)?;
+    }
+    database::delete_install_history_entry(conn, entry.id)
+}
+
+/// Replace characters that are not safe in file names
+pub(crate) fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SourceType;
+    use tempfile::tempdir;
+
+    fn test_addon(version: &str) -> InstalledAddon {
+        InstalledAddon {
+            id: 1,
+            slug: "test-addon".to_string(),
+            name: "Test Addon".to_string(),
+            installed_version: version.to_string(),
+            source_type: SourceType::Index,
+            source_repo: Some("test/repo".to_string()),
+            installed_at: String::new(),
+            updated_at: String::new(),
+            auto_update: false,
+            manifest_path: String::new(),
+            version_sort_key: None,
+            commit_sha: None,
+            parent_slug: None,
+        }
+    }
+
+    #[test]
+    fn test_record_install_prunes_old_entries() {
+        let temp = tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+
+        let archive = temp.path().join("download.zip");
+        fs::write(&archive, b"zip").unwrap();
+        let history_dir = temp.path().join("history");
+
+        let first =
+            record_install(&conn, &history_dir, &test_addon("1.0.0"), &archive, None, 2).unwrap();
+        for version in ["1.1.0", "1.2.0", "1.3.0"] {
+            record_install(&conn, &history_dir, &test_addon(version), &archive, None, 2).unwrap();
+        }
+
+        // The current install plus the two before it
+        let entries = database::get_install_history(&conn, "test-addon").unwrap();
+        let versions: Vec<_> = entries.iter().map(|e| e.version.as_str()).collect();
+        assert_eq!(versions, vec!["1.3.0", "1.2.0", "1.1.0"]);
+        assert!(!Path::new(&first.archive_path).exists());
+        assert!(find_entry(&conn, "test-addon", "1.0.0").is_err());
+        assert!(find_entry(&conn, "test-addon", "1.1.0").is_ok());
+    }
+
+    #[test]
+    fn test_record_install_keeps_current_with_zero_retention() {
+        let temp = tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+
+        let archive = temp.path().join("download.zip");
+        fs::write(&archive, b"zip").unwrap();
+        let history_dir = temp.path().join("history");
+
+        let first =
+            record_install(&conn, &history_dir, &test_addon("1.0.0"), &archive, None, 0).unwrap();
+        let current =
+            record_install(&conn, &history_dir, &test_addon("1.1.0"), &archive, None, 0).unwrap();
+
+        let entries = database::get_install_history(&conn, "test-addon").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].version, "1.1.0");
+        assert!(!Path::new(&first.archive_path).exists());
+        assert!(Path::new(&current.archive_path).exists());
+    }
+
+    #[test]
+    fn test_clear_history_removes_archives() {
+        let temp = tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+
+        let archive = temp.path().join("download.zip");
+        fs::write(&archive, b"zip").unwrap();
+
+        let entry = record_install(
+            &conn,
+            &temp.path().join("history"),
+            &test_addon("1.0.0"),
+            &archive,
+            None,
+            DEFAULT_HISTORY_RETENTION,
+        )
+        .unwrap();
+
+        clear_history(&conn, "test-addon").unwrap();
+
+        assert!(database::get_install_history(&conn, "test-addon")
+            .unwrap()
+            .is_empty());
+        assert!(!Path::new(&entry.archive_path).exists());
+    }
+}
@@ -0,0 +1,177 @@
+use super::database;
+use crate::error::{AppError, Result};
+use rusqlite::Connection;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Seconds allowed to establish a connection unless configured otherwise
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 15;
+
+/// Seconds allowed between reads unless configured otherwise
+/// There is no overall timeout, since large downloads may take a while.
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 30;
+
+/// Network settings the shared HTTP client is built from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpConfig {
+    pub connect_timeout_secs: Option<u64>,
+    pub read_timeout_secs: Option<u64>,
+    /// HTTP, HTTPS or SOCKS5 proxy for all requests; system proxy settings apply otherwise
+    pub proxy_url: Option<String>,
+    /// PEM file with extra root certificates (e.g. a corporate TLS-inspecting proxy)
+    pub ca_certificate_path: Option<PathBuf>,
+}
+
+/// Load the network settings from the settings table
+pub fn load_http_config(conn: &Connection) -> HttpConfig {
+    let setting = |key| {
+        database::get_setting(conn, key)
+            .ok()
+            .flatten()
+            .filter(|v| !v.trim().is_empty())
+    };
+
+    HttpConfig {
+        connect_timeout_secs: setting("connect_timeout_secs").and_then(|v| v.parse().ok()),
+        read_timeout_secs: setting("read_timeout_secs").and_then(|v| v.parse().ok()),
+        proxy_url: setting("proxy_url"),
+        ca_certificate_path: setting("ca_certificate_path").map(PathBuf::from),
+    }
+}
+
+/// User-Agent sent with every request
+pub fn user_agent() -> String {
+    format!("eso-addon-manager/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Build the HTTP client shared by all network calls
+pub fn build_client(config: &HttpConfig) -> Result<reqwest::Client> {
+    Ok(client_builder(config)?.build()?)
+}
+
+/// Client builder with the network settings applied, for clients that need more options
+pub fn client_builder(config: &HttpConfig) -> Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent())
+        .connect_timeout(Duration::from_secs(
+            config
+                .connect_timeout_secs
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS)
+                .max(1),
+        ))
+        .read_timeout(Duration::from_secs(
+            config
+                .read_timeout_secs
+                .unwrap_or(DEFAULT_READ_TIMEOUT_SECS)
+                .max(1),
+        ));
+
+    if let Some(ref proxy_url) = config.proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url.trim())
+            .map_err(|e| AppError::Custom(format!("Invalid proxy URL '{}': {}", proxy_url, e)))?;
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(ref path) = config.ca_certificate_path {
+        let pem = std::fs::read(path).map_err(|e| {
+            AppError::Custom(format!(
+                "Could not read CA certificates from {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| {
+            AppError::Custom(format!(
+                "Invalid CA certificates in {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        if certificates.is_empty() {
+            return Err(AppError::Custom(format!(
+                "No CA certificates found in {}",
+                path.display()
+            )));
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::{request_header, Response, TestServer};
+
+    #[test]
+    fn test_load_http_config() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        assert_eq!(load_http_config(&conn), HttpConfig::default());
+
+        database::set_setting(&conn, "proxy_url", "socks5://127.0.0.1:1080").unwrap();
+        database::set_setting(&conn, "read_timeout_secs", "60").unwrap();
+        database::set_setting(&conn, "ca_certificate_path", "").unwrap();
+
+        let config = load_http_config(&conn);
+        assert_eq!(config.proxy_url.as_deref(), Some("socks5://127.0.0.1:1080"));
+        assert_eq!(config.read_timeout_secs, Some(60));
+        assert_eq!(config.ca_certificate_path, None);
+        assert!(build_client(&config).is_ok());
+    }
+
+    #[test]
+    fn test_build_client_rejects_bad_settings() {
+        let temp = tempfile::tempdir().unwrap();
+
+        let bad_proxy = HttpConfig {
+            proxy_url: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(build_client(&bad_proxy).is_err());
+
+        let missing_ca = HttpConfig {
+            ca_certificate_path: Some(temp.path().join("missing.pem")),
+            ..Default::default()
+        };
+        assert!(build_client(&missing_ca).is_err());
+
+        let empty_ca_path = temp.path().join("empty.pem");
+        std::fs::write(&empty_ca_path, "not a certificate").unwrap();
+        let empty_ca = HttpConfig {
+            ca_certificate_path: Some(empty_ca_path),
+            ..Default::default()
+        };
+        assert!(build_client(&empty_ca).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy_with_user_agent() {
+        let proxy = TestServer::canned(vec![Response::ok("ok")]).await;
+
+        let client = build_client(&HttpConfig {
+            proxy_url: Some(proxy.url("")),
+            ..Default::default()
+        })
+        .unwrap();
+        let body = client
+            .get("http://addons.example/index.json")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "ok");
+
+        let request = &proxy.requests()[0];
+        assert!(request.starts_with("GET http://addons.example/index.json"));
+        assert_eq!(
+            request_header(request, "user-agent"),
+            Some(user_agent().as_str())
+        );
+    }
+}
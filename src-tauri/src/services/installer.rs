@@ -1,69 +1,265 @@
 use crate::error::{AppError, Result};
 use crate::models::InstallInfo;
-use crate::utils::manifest::find_manifests;
-use crate::utils::zip::{extract_archive, extract_archive_with_options, find_addon_root};
+use crate::utils::manifest::{find_manifests, parse_manifest};
+use crate::utils::zip::{
+    extract_archive, extract_archive_with_options, find_addon_roots, validate_install_info,
+};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use tempfile::TempDir;
 
+/// Prefix for staging directories created next to the AddOns folder
+const STAGING_PREFIX: &str = ".eso-addon-manager-staging-";
+
+/// An addon folder found in an archive
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedAddon {
+    /// Folder the addon is installed into (named after its manifest file)
+    pub folder_name: String,
+    pub title: String,
+    pub version: Option<String>,
+    /// The archive's main addon; the others are installed as linked addons
+    pub primary: bool,
+    /// A folder with this name already exists and will be replaced
+    pub replaces_existing: bool,
+}
+
+/// The addon folders an archive will install, main addon first
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallPlan {
+    pub addons: Vec<PlannedAddon>,
+}
+
+/// Folders installed from an archive: the main addon and any bundled addons
+#[derive(Debug, Clone)]
+pub struct ArchiveInstall {
+    pub primary: PathBuf,
+    pub linked: Vec<PathBuf>,
+}
+
+/// An addon root extracted into the staging directory
+struct StagedAddon {
+    path: PathBuf,
+    folder_name: String,
+}
+
 /// Install an addon from a downloaded archive using explicit install info from the index
 pub fn install_from_archive_with_info(
     archive_path: &Path,
     addon_dir: &Path,
     install_info: &InstallInfo,
 ) -> Result<PathBuf> {
+    // Index-supplied paths must stay inside the AddOns directory
+    validate_install_info(install_info)?;
+
     // Target path using the explicit target_folder from install info
     let target_path = addon_dir.join(&install_info.target_folder);
 
-    // Remove existing addon if present
-    if target_path.exists() {
-        fs::remove_dir_all(&target_path)?;
-    }
-
-    // Create target directory
-    fs::create_dir_all(&target_path)?;
+    // Extract into a staging directory so a failed extraction never touches the installed addon
+    let staging = create_staging_dir(addon_dir)?;
+    let staged_path = staging.path().join(&install_info.target_folder);
+    fs::create_dir_all(&staged_path)?;
 
-    // Extract directly to target path with install options (handles extract_path and excludes)
-    extract_archive_with_options(archive_path, &target_path, Some(install_info))?;
+    // Extract with install options (handles extract_path and excludes)
+    extract_archive_with_options(archive_path, &staged_path, Some(install_info))?;
 
     // Verify the addon was extracted correctly by checking for manifest
-    if !has_addon_content(&target_path) {
+    if !has_addon_content(&staged_path) {
         return Err(AppError::InvalidManifest(
             "No addon manifest found after extraction".into(),
         ));
     }
 
+    swap_into_place(&[(staged_path, target_path.clone())], staging.path())?;
+
     Ok(target_path)
 }
 
-/// Install an addon from a downloaded archive (legacy fallback for custom repos without install info)
-pub fn install_from_archive(archive_path: &Path, addon_dir: &Path) -> Result<PathBuf> {
-    // Create a temporary directory for extraction
-    let temp_dir = TempDir::new()?;
+/// Install every addon in a downloaded archive (fallback for custom repos without install info)
+///
+/// Archives often bundle several addons (an addon plus its libraries, or a suite of
+/// modules). Each one is installed into its own folder; the folder matching
+/// `name_hint` (or else the first non-library folder) is the main addon. Bundled
+/// folders named in `skip_folders` are left untouched.
+pub fn install_from_archive(
+    archive_path: &Path,
+    addon_dir: &Path,
+    name_hint: Option<&str>,
+    skip_folders: &[String],
+) -> Result<ArchiveInstall> {
+    // Extract into a staging directory next to the AddOns folder
+    let staging = create_staging_dir(addon_dir)?;
+    let mut staged = stage_archive(archive_path, staging.path(), name_hint)?;
+
+    let primary = staged.remove(0);
+    staged.retain(|addon| {
+        !skip_folders
+            .iter()
+            .any(|skip| skip.eq_ignore_ascii_case(&addon.folder_name))
+    });
+
+    let folders: Vec<(PathBuf, PathBuf)> = std::iter::once(&primary)
+        .chain(&staged)
+        .map(|addon| (addon.path.clone(), addon_dir.join(&addon.folder_name)))
+        .collect();
+
+    swap_into_place(&folders, staging.path())?;
+
+    let mut targets = folders.into_iter().map(|(_, target)| target);
+    Ok(ArchiveInstall {
+        primary: targets.next().expect("primary folder"),
+        linked: targets.collect(),
+    })
+}
+
+/// Describe the addon folders an archive would install without touching the AddOns folder
+pub fn plan_archive_install(
+    archive_path: &Path,
+    addon_dir: &Path,
+    name_hint: Option<&str>,
+) -> Result<InstallPlan> {
+    let staging = create_staging_dir(addon_dir)?;
+    let staged = stage_archive(archive_path, staging.path(), name_hint)?;
+
+    let addons = staged
+        .iter()
+        .enumerate()
+        .map(|(i, addon)| {
+            let manifest = get_manifest_path(&addon.path).and_then(|p| parse_manifest(&p).ok());
+            PlannedAddon {
+                folder_name: addon.folder_name.clone(),
+                title: manifest
+                    .as_ref()
+                    .map(|m| m.title.clone())
+                    .unwrap_or_else(|| addon.folder_name.clone()),
+                version: manifest.and_then(|m| m.version),
+                primary: i == 0,
+                replaces_existing: addon_dir.join(&addon.folder_name).exists(),
+            }
+        })
+        .collect();
+
+    Ok(InstallPlan { addons })
+}
+
+/// Extract an archive into the staging directory and locate its addon roots, main addon first
+fn stage_archive(
+    archive_path: &Path,
+    staging_dir: &Path,
+    name_hint: Option<&str>,
+) -> Result<Vec<StagedAddon>> {
+    let extract_dir = staging_dir.join("extracted");
+    fs::create_dir_all(&extract_dir)?;
 
     // Extract the archive
-    extract_archive(archive_path, temp_dir.path())?;
+    extract_archive(archive_path, &extract_dir)?;
+
+    // Find the addon roots (may be in subdirectories)
+    let roots = find_addon_roots(&extract_dir);
+    if roots.is_empty() {
+        return Err(AppError::InvalidManifest(
+            "No addon manifest found in archive".into(),
+        ));
+    }
+
+    let mut staged: Vec<StagedAddon> = Vec::new();
+    for path in roots {
+        // Get the addon name from the manifest filename, not the folder name
+        // This handles cases like "WarMask-1.3.0/" containing "WarMask.txt"
+        let folder_name = get_addon_name_from_manifest(&path)?;
+        if staged
+            .iter()
+            .any(|s| s.folder_name.eq_ignore_ascii_case(&folder_name))
+        {
+            return Err(AppError::InvalidManifest(format!(
+                "Archive contains more than one addon named {}",
+                folder_name
+            )));
+        }
+        staged.push(StagedAddon { path, folder_name });
+    }
 
-    // Find the addon root (may be in a subdirectory)
-    let addon_root = find_addon_root(temp_dir.path())
-        .ok_or_else(|| AppError::InvalidManifest("No addon manifest found in archive".into()))?;
+    let primary = primary_index(&staged, name_hint);
+    let main = staged.remove(primary);
+    staged.insert(0, main);
+
+    Ok(staged)
+}
+
+/// Pick the main addon: the folder matching the hint, else the first that is not a library
+fn primary_index(staged: &[StagedAddon], name_hint: Option<&str>) -> usize {
+    name_hint
+        .and_then(|hint| {
+            staged
+                .iter()
+                .position(|s| s.folder_name.eq_ignore_ascii_case(hint))
+        })
+        .or_else(|| {
+            staged
+                .iter()
+                .position(|s| !s.folder_name.to_lowercase().starts_with("lib"))
+        })
+        .unwrap_or(0)
+}
 
-    // Get the addon name from the manifest filename, not the folder name
-    // This handles cases like "WarMask-1.3.0/" containing "WarMask.txt"
-    let addon_name = get_addon_name_from_manifest(&addon_root)?;
+/// Create a staging directory on the same filesystem as the AddOns folder
+/// The directory (and anything left in it) is removed when the returned guard is dropped
+fn create_staging_dir(addon_dir: &Path) -> Result<TempDir> {
+    // The AddOns folder may not exist yet on a fresh game install or a custom path
+    fs::create_dir_all(addon_dir)?;
+
+    let staging_root = addon_dir.parent().unwrap_or(addon_dir);
+    let staging = tempfile::Builder::new()
+        .prefix(STAGING_PREFIX)
+        .tempdir_in(staging_root)?;
+    Ok(staging)
+}
 
-    // Target path in the ESO addons directory
-    let target_path = addon_dir.join(&addon_name);
+/// Atomically replace each target folder with its fully extracted staged folder
+/// Existing addons are renamed to backups inside the staging directory first; if any
+/// folder cannot be moved into place, every folder swapped so far is restored.
+fn swap_into_place(folders: &[(PathBuf, PathBuf)], staging_dir: &Path) -> Result<()> {
+    let mut swapped: Vec<(&Path, Option<PathBuf>)> = Vec::new();
+
+    for (i, (staged_path, target_path)) in folders.iter().enumerate() {
+        let backup = if target_path.exists() {
+            let backup_path = staging_dir.join(format!("backup-{}", i));
+            if let Err(e) = fs::rename(target_path, &backup_path) {
+                return Err(restore_swapped(&swapped, e.into()));
+            }
+            Some(backup_path)
+        } else {
+            None
+        };
+        swapped.push((target_path, backup));
 
-    // Remove existing addon if present
-    if target_path.exists() {
-        fs::remove_dir_all(&target_path)?;
+        if let Err(e) = fs::rename(staged_path, target_path) {
+            return Err(restore_swapped(&swapped, e.into()));
+        }
     }
 
-    // Copy addon to target directory
-    copy_dir_recursive(&addon_root, &target_path)?;
+    Ok(())
+}
 
-    Ok(target_path)
+/// Put the previous installs back after a failed swap; the staging guard cleans up the rest
+fn restore_swapped(swapped: &[(&Path, Option<PathBuf>)], error: AppError) -> AppError {
+    for (target_path, backup) in swapped.iter().rev() {
+        // The new folder is absent if its own rename was the one that failed
+        let _ = fs::remove_dir_all(target_path);
+
+        if let Some(backup_path) = backup {
+            if let Err(restore_err) = fs::rename(backup_path, target_path) {
+                return AppError::Custom(format!(
+                    "Install failed ({}) and the previous version could not be restored: {}",
+                    error, restore_err
+                ));
+            }
+        }
+    }
+
+    error
 }
 
 /// Check if a directory contains addon content (manifest file)
@@ -95,25 +291,50 @@ pub fn uninstall_addon(addon_path: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Recursively copy a directory
-fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
-    fs::create_dir_all(dst)?;
-
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
+/// Remove the listed files (relative to the addon folder) and any directories left empty
+/// Files not in the list, such as ones the user added, are kept along with their folders.
+pub fn uninstall_files(addon_path: &Path, files: &[&str]) -> Result<()> {
+    for file in files {
+        // Inventory paths are plain relative paths; never follow anything else
+        let relative = Path::new(file);
+        if !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            continue;
+        }
 
-        if src_path.is_dir() {
-            copy_dir_recursive(&src_path, &dst_path)?;
-        } else {
-            fs::copy(&src_path, &dst_path)?;
+        let path = addon_path.join(relative);
+        if path.is_file() {
+            fs::remove_file(&path)?;
         }
     }
 
+    remove_empty_dirs(addon_path)?;
     Ok(())
 }
 
+/// Remove empty directories bottom-up, including `dir` itself; returns whether it was removed
+fn remove_empty_dirs(dir: &Path) -> Result<bool> {
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+
+    let mut empty = true;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // Use the entry's own type so symlinked directories are never descended into
+        if !(entry.file_type()?.is_dir() && remove_empty_dirs(&entry.path())?) {
+            empty = false;
+        }
+    }
+
+    if empty {
+        fs::remove_dir(dir)?;
+    }
+    Ok(empty)
+}
+
 /// Get the manifest file path for an addon
 /// ESO addons can use either .txt or .addon extension for manifests
 pub fn get_manifest_path(addon_path: &Path) -> Option<PathBuf> {
@@ -194,6 +415,58 @@ mod tests {
     use std::fs::File;
     use std::io::Write;
     use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+
+    /// Build a stored (uncompressed) zip so tests can corrupt entry data in place
+    fn write_archive(path: &Path, entries: &[(&str, &str)]) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in entries {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    /// Flip the bytes of an entry's content so its CRC check fails while extracting
+    fn corrupt_entry(path: &Path, content: &str) {
+        let mut bytes = fs::read(path).unwrap();
+        let needle = content.as_bytes();
+        let pos = bytes
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .unwrap();
+        for b in &mut bytes[pos..pos + needle.len()] {
+            *b ^= 0xFF;
+        }
+        fs::write(path, bytes).unwrap();
+    }
+
+    fn install_info(target_folder: &str) -> InstallInfo {
+        InstallInfo {
+            method: "github_archive".to_string(),
+            extract_path: None,
+            target_folder: target_folder.to_string(),
+            excludes: vec![],
+        }
+    }
+
+    fn setup_existing_addon(addon_dir: &Path) -> PathBuf {
+        let existing = addon_dir.join("TestAddon");
+        fs::create_dir_all(&existing).unwrap();
+        fs::write(existing.join("TestAddon.txt"), "## Title: Test Addon\n").unwrap();
+        fs::write(existing.join("old.lua"), "-- old version").unwrap();
+        existing
+    }
+
+    fn staging_dirs_left(addon_dir: &Path) -> usize {
+        fs::read_dir(addon_dir.parent().unwrap())
+            .unwrap()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().starts_with(STAGING_PREFIX))
+            .count()
+    }
 
     #[test]
     fn test_get_manifest_path() {
@@ -209,4 +482,252 @@ mod tests {
         assert!(result.is_some());
         assert_eq!(result.unwrap(), manifest_path);
     }
+
+    #[test]
+    fn test_install_with_info_replaces_existing_addon() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        let existing = setup_existing_addon(&addon_dir);
+
+        let archive = temp.path().join("addon.zip");
+        write_archive(
+            &archive,
+            &[
+                ("TestAddon-main/TestAddon.txt", "## Title: Test Addon\n"),
+                ("TestAddon-main/new.lua", "-- new version"),
+            ],
+        );
+
+        let installed =
+            install_from_archive_with_info(&archive, &addon_dir, &install_info("TestAddon"))
+                .unwrap();
+
+        assert_eq!(installed, existing);
+        assert!(installed.join("new.lua").exists());
+        assert!(!installed.join("old.lua").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
+
+    #[test]
+    fn test_install_with_info_keeps_existing_on_corrupt_archive() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        let existing = setup_existing_addon(&addon_dir);
+
+        // The second entry fails its CRC check after the first has already been written
+        let archive = temp.path().join("addon.zip");
+        write_archive(
+            &archive,
+            &[
+                ("TestAddon-main/TestAddon.txt", "## Title: Test Addon\n"),
+                (
+                    "TestAddon-main/broken.lua",
+                    "-- this content gets corrupted",
+                ),
+            ],
+        );
+        corrupt_entry(&archive, "-- this content gets corrupted");
+
+        let result =
+            install_from_archive_with_info(&archive, &addon_dir, &install_info("TestAddon"));
+
+        assert!(result.is_err());
+        assert!(existing.join("old.lua").exists());
+        assert!(!existing.join("broken.lua").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
+
+    #[test]
+    fn test_install_with_info_keeps_existing_without_manifest() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        let existing = setup_existing_addon(&addon_dir);
+
+        let archive = temp.path().join("addon.zip");
+        write_archive(
+            &archive,
+            &[("TestAddon-main/README.md", "no manifest here")],
+        );
+
+        let result =
+            install_from_archive_with_info(&archive, &addon_dir, &install_info("TestAddon"));
+
+        assert!(matches!(result, Err(AppError::InvalidManifest(_))));
+        assert!(existing.join("TestAddon.txt").exists());
+        assert!(existing.join("old.lua").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
+
+    #[test]
+    fn test_install_with_info_rejects_escaping_target_folder() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        fs::create_dir_all(&addon_dir).unwrap();
+
+        let archive = temp.path().join("addon.zip");
+        write_archive(
+            &archive,
+            &[("TestAddon-main/TestAddon.txt", "## Title: Test Addon\n")],
+        );
+
+        let result =
+            install_from_archive_with_info(&archive, &addon_dir, &install_info("../Escaped"));
+
+        assert!(matches!(result, Err(AppError::UnsafeArchive(_))));
+        assert!(!temp.path().join("Escaped").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
+
+    #[test]
+    fn test_install_from_archive_keeps_existing_on_corrupt_archive() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        let existing = setup_existing_addon(&addon_dir);
+
+        let archive = temp.path().join("addon.zip");
+        write_archive(
+            &archive,
+            &[
+                ("TestAddon-1.2.0/TestAddon.txt", "## Title: Test Addon\n"),
+                (
+                    "TestAddon-1.2.0/broken.lua",
+                    "-- this content gets corrupted",
+                ),
+            ],
+        );
+        corrupt_entry(&archive, "-- this content gets corrupted");
+
+        assert!(install_from_archive(&archive, &addon_dir, None, &[]).is_err());
+        assert!(existing.join("old.lua").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
+
+    #[test]
+    fn test_install_creates_missing_addon_dir() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("Elder Scrolls Online/live/AddOns");
+
+        let archive = temp.path().join("addon.zip");
+        write_archive(
+            &archive,
+            &[("TestAddon-main/TestAddon.txt", "## Title: Test Addon\n")],
+        );
+
+        let installed =
+            install_from_archive_with_info(&archive, &addon_dir, &install_info("TestAddon"))
+                .unwrap();
+
+        assert_eq!(installed, addon_dir.join("TestAddon"));
+        assert!(installed.join("TestAddon.txt").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
+
+    #[test]
+    fn test_install_from_archive_uses_manifest_name() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        fs::create_dir_all(&addon_dir).unwrap();
+
+        let archive = temp.path().join("addon.zip");
+        write_archive(
+            &archive,
+            &[
+                ("WarMask-1.3.0/WarMask.txt", "## Title: War Mask\n"),
+                ("WarMask-1.3.0/WarMask.lua", "-- code"),
+            ],
+        );
+
+        let installed = install_from_archive(&archive, &addon_dir, None, &[]).unwrap();
+
+        assert_eq!(installed.primary, addon_dir.join("WarMask"));
+        assert!(installed.linked.is_empty());
+        assert!(installed.primary.join("WarMask.lua").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
+
+    /// A suite archive wrapping a main addon, a module and a bundled library
+    fn write_suite_archive(path: &Path) {
+        write_archive(
+            path,
+            &[
+                ("Suite-main/LibSuite/LibSuite.txt", "## Title: Lib Suite\n"),
+                (
+                    "Suite-main/Suite/Suite.txt",
+                    "## Title: Suite\n## Version: 3.1\n",
+                ),
+                (
+                    "Suite-main/SuiteModule/SuiteModule.txt",
+                    "## Title: Suite Module\n",
+                ),
+                ("Suite-main/README.md", "readme"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_install_from_archive_installs_every_addon() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        fs::create_dir_all(&addon_dir).unwrap();
+        let archive = temp.path().join("suite.zip");
+        write_suite_archive(&archive);
+
+        let installed = install_from_archive(&archive, &addon_dir, Some("suite"), &[]).unwrap();
+
+        assert_eq!(installed.primary, addon_dir.join("Suite"));
+        assert_eq!(
+            installed.linked,
+            vec![addon_dir.join("LibSuite"), addon_dir.join("SuiteModule")]
+        );
+        assert!(addon_dir.join("LibSuite/LibSuite.txt").exists());
+        assert!(addon_dir.join("SuiteModule/SuiteModule.txt").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
+
+    #[test]
+    fn test_install_from_archive_skips_managed_folders() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        let managed = addon_dir.join("LibSuite");
+        fs::create_dir_all(&managed).unwrap();
+        fs::write(
+            managed.join("LibSuite.txt"),
+            "## Title: Lib Suite (newer)\n",
+        )
+        .unwrap();
+        let archive = temp.path().join("suite.zip");
+        write_suite_archive(&archive);
+
+        let installed =
+            install_from_archive(&archive, &addon_dir, None, &["libsuite".to_string()]).unwrap();
+
+        // Without a hint the first non-library addon is the main one
+        assert_eq!(installed.primary, addon_dir.join("Suite"));
+        assert_eq!(installed.linked, vec![addon_dir.join("SuiteModule")]);
+        assert_eq!(
+            fs::read_to_string(managed.join("LibSuite.txt")).unwrap(),
+            "## Title: Lib Suite (newer)\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_archive_install() {
+        let temp = tempdir().unwrap();
+        let addon_dir = temp.path().join("AddOns");
+        fs::create_dir_all(addon_dir.join("SuiteModule")).unwrap();
+        let archive = temp.path().join("suite.zip");
+        write_suite_archive(&archive);
+
+        let plan = plan_archive_install(&archive, &addon_dir, Some("suite")).unwrap();
+
+        let folders: Vec<_> = plan.addons.iter().map(|a| a.folder_name.as_str()).collect();
+        assert_eq!(folders, vec!["Suite", "LibSuite", "SuiteModule"]);
+        assert!(plan.addons[0].primary);
+        assert_eq!(plan.addons[0].version.as_deref(), Some("3.1"));
+        assert_eq!(plan.addons[1].title, "Lib Suite");
+        assert!(!plan.addons[1].replaces_existing);
+        assert!(plan.addons[2].replaces_existing);
+        assert!(!addon_dir.join("Suite").exists());
+        assert_eq!(staging_dirs_left(&addon_dir), 0);
+    }
 }
@@ -0,0 +1,391 @@
+use super::history::sanitize_file_name;
+use super::{database, installer, linked};
+use crate::error::Result;
+use crate::models::{AddonFile, InstalledAddon, VerifyResult};
+use crate::utils::checksum::{ChecksumAlgorithm, StreamHasher};
+use chrono::Utc;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Folder an installed addon lives in, derived from its manifest path
+pub fn addon_root(addon: &InstalledAddon) -> Option<PathBuf> {
+    Path::new(&addon.manifest_path)
+        .parent()
+        .map(Path::to_path_buf)
+}
+
+/// Hash every file under an addon folder, sorted by relative path
+pub fn scan_files(addon_path: &Path) -> Result<Vec<AddonFile>> {
+    let mut files = Vec::new();
+    collect_files(addon_path, addon_path, &mut files)?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<AddonFile>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            let (size, sha256) = hash_file(&path)?;
+            files.push(AddonFile {
+                path: relative_path(root, &path),
+                size,
+                sha256,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Size and SHA-256 of a file, read in chunks
+pub fn hash_file(path: &Path) -> Result<(u64, String)> {
+    let mut file = File::open(path)?;
+    let mut hasher = StreamHasher::new(ChecksumAlgorithm::Sha256);
+    let mut buffer = [0u8; 64 * 1024];
+    let mut size = 0u64;
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+
+    Ok((size, hasher.finalize_hex()))
+}
+
+/// Path of `path` relative to `root`, with '/' separators on every platform
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Record the file inventory of an installed addon, replacing any previous one
+pub fn record_inventory(conn: &Connection, addon: &InstalledAddon) -> Result<()> {
+    let Some(root) = addon_root(addon) else {
+        return Ok(());
+    };
+    let files = scan_files(&root)?;
+    database::replace_addon_files(conn, &addon.slug, &files)
+}
+
+/// An installed addon and the files recorded for it at install
+///
+/// Loaded up front so the files on disk can be hashed without holding the database.
+#[derive(Debug, Clone)]
+pub struct AddonInventory {
+    pub addon: InstalledAddon,
+    pub files: Vec<AddonFile>,
+}
+
+/// Load the inventory recorded for an installed addon
+pub fn load_inventory(conn: &Connection, addon: &InstalledAddon) -> Result<AddonInventory> {
+    Ok(AddonInventory {
+        addon: addon.clone(),
+        files: database::get_addon_files(conn, &addon.slug)?,
+    })
+}
+
+/// Load the inventories of an addon and the addons installed from the same archive
+pub fn load_inventories(conn: &Connection, slug: &str) -> Result<Vec<AddonInventory>> {
+    let Some(addon) = database::get_installed_by_slug(conn, slug)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut addons = vec![addon];
+    addons.extend(database::get_linked_addons(conn, slug)?);
+    addons
+        .iter()
+        .map(|addon| load_inventory(conn, addon))
+        .collect()
+}
+
+/// Compare an installed addon's files on disk with the inventory recorded at install
+pub fn verify_addon(conn: &Connection, addon: &InstalledAddon) -> Result<VerifyResult> {
+    verify_inventory(&load_inventory(conn, addon)?)
+}
+
+/// Compare an addon's files on disk with a loaded inventory
+pub fn verify_inventory(inventory: &AddonInventory) -> Result<VerifyResult> {
+    let AddonInventory {
+        addon,
+        files: recorded,
+    } = inventory;
+    let mut result = VerifyResult {
+        slug: addon.slug.clone(),
+        name: addon.name.clone(),
+        has_inventory: !recorded.is_empty(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        modified: Vec::new(),
+    };
+
+    let Some(root) = addon_root(addon).filter(|_| result.has_inventory) else {
+        return Ok(result);
+    };
+
+    let on_disk: HashMap<String, u64> = if root.is_dir() {
+        list_files(&root)?
+    } else {
+        HashMap::new()
+    };
+
+    for file in recorded {
+        match on_disk.get(&file.path) {
+            None => result.removed.push(file.path.clone()),
+            // Only hash when the size matches; a size change is already a modification
+            Some(&size) => {
+                if size != file.size || hash_file(&root.join(&file.path))?.1 != file.sha256 {
+                    result.modified.push(file.path.clone());
+                }
+            }
+        }
+    }
+
+    let mut added: Vec<String> = on_disk
+        .into_keys()
+        .filter(|path| !recorded.iter().any(|f| &f.path == path))
+        .collect();
+    added.sort();
+    result.added = added;
+
+    Ok(result)
+}
+
+/// Relative paths and sizes of every file under an addon folder, without hashing
+fn list_files(addon_path: &Path) -> Result<HashMap<String, u64>> {
+    let mut files = HashMap::new();
+    collect_sizes(addon_path, addon_path, &mut files)?;
+    Ok(files)
+}
+
+fn collect_sizes(root: &Path, dir: &Path, files: &mut HashMap<String, u64>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            collect_sizes(root, &path, files)?;
+        } else if file_type.is_file() {
+            files.insert(relative_path(root, &path), entry.metadata()?.len());
+        }
+    }
+    Ok(())
+}
+
+/// Files of an addon and its linked addons that an update would lose, as "Folder/path"
+/// These are installed files edited locally and files the user added to the folders.
+pub fn local_modifications(conn: &Connection, slug: &str) -> Result<Vec<String>> {
+    changed_files(&load_inventories(conn, slug)?)
+}
+
+/// Files that an update would lose, from the inventories of an addon and its linked addons
+pub fn changed_files(inventories: &[AddonInventory]) -> Result<Vec<String>> {
+    let mut changed = Vec::new();
+
+    for inventory in inventories {
+        let folder = linked::addon_folder(&inventory.addon).unwrap_or_default();
+        let result = verify_inventory(inventory)?;
+        for path in result.modified.iter().chain(&result.added) {
+            changed.push(format!("{}/{}", folder, path));
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Copy the files an update would lose into `patches_dir/<slug>/<timestamp>/<Folder>/`
+/// Takes the inventories from `load_inventories`. Returns the backup directory, or
+/// None when there was nothing to back up.
+pub fn backup_local_changes(
+    inventories: &[AddonInventory],
+    slug: &str,
+    patches_dir: &Path,
+) -> Result<Option<PathBuf>> {
+    let backup_dir = patches_dir
+        .join(sanitize_file_name(slug))
+        .join(Utc::now().format("%Y%m%d-%H%M%S").to_string());
+    let mut copied = 0;
+
+    for inventory in inventories {
+        let addon = &inventory.addon;
+        let (Some(root), Some(folder)) = (addon_root(addon), linked::addon_folder(addon)) else {
+            continue;
+        };
+
+        let result = verify_inventory(inventory)?;
+        for path in result.modified.iter().chain(&result.added) {
+            let target = backup_dir.join(&folder).join(path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(root.join(path), &target)?;
+            copied += 1;
+        }
+    }
+
+    Ok((copied > 0).then_some(backup_dir))
+}
+
+/// Remove an installed addon's files from disk
+///
+/// With `keep_user_files`, only the files recorded at install time are removed and
+/// anything the user added stays behind. Without a recorded inventory the whole
+/// folder is removed.
+pub fn remove_addon_files(
+    conn: &Connection,
+    addon: &InstalledAddon,
+    keep_user_files: bool,
+) -> Result<()> {
+    remove_inventory_files(&load_inventory(conn, addon)?, keep_user_files)
+}
+
+/// Remove an addon's files from disk using a loaded inventory, like `remove_addon_files`
+pub fn remove_inventory_files(inventory: &AddonInventory, keep_user_files: bool) -> Result<()> {
+    let Some(root) = addon_root(&inventory.addon) else {
+        return Ok(());
+    };
+
+    if !keep_user_files || inventory.files.is_empty() {
+        return installer::uninstall_addon(&root);
+    }
+
+    let paths: Vec<&str> = inventory.files.iter().map(|f| f.path.as_str()).collect();
+    installer::uninstall_files(&root, &paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SourceType;
+    use tempfile::tempdir;
+
+    fn install_test_addon(conn: &Connection, addon_dir: &Path) -> InstalledAddon {
+        let root = addon_dir.join("TestAddon");
+        fs::create_dir_all(root.join("libs")).unwrap();
+        fs::write(root.join("TestAddon.txt"), "## Title: Test Addon\n").unwrap();
+        fs::write(root.join("main.lua"), "-- main").unwrap();
+        fs::write(root.join("libs/lib.lua"), "-- lib").unwrap();
+
+        let addon = database::insert_installed(
+            conn,
+            "test-addon",
+            "Test Addon",
+            "1.0",
+            SourceType::Index,
+            None,
+            root.join("TestAddon.txt").to_string_lossy().as_ref(),
+            None,
+            None,
+        )
+        .unwrap();
+        record_inventory(conn, &addon).unwrap();
+        addon
+    }
+
+    #[test]
+    fn test_record_inventory_and_detect_modifications() {
+        let temp = tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        let addon = install_test_addon(&conn, temp.path());
+
+        let files = database::get_addon_files(&conn, "test-addon").unwrap();
+        let paths: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["TestAddon.txt", "libs/lib.lua", "main.lua"]);
+        assert_eq!(files[2].size, 7);
+        assert!(!verify_addon(&conn, &addon).unwrap().has_changes());
+
+        // Same size, different contents
+        let root = temp.path().join("TestAddon");
+        fs::write(root.join("main.lua"), "-- edit").unwrap();
+        fs::remove_file(root.join("libs/lib.lua")).unwrap();
+        fs::write(root.join("libs/extra.lua"), "-- mine").unwrap();
+
+        let result = verify_addon(&conn, &addon).unwrap();
+        assert!(result.has_inventory);
+        assert_eq!(result.modified, vec!["main.lua"]);
+        assert_eq!(result.removed, vec!["libs/lib.lua"]);
+        assert_eq!(result.added, vec!["libs/extra.lua"]);
+        assert_eq!(
+            local_modifications(&conn, "test-addon").unwrap(),
+            vec!["TestAddon/main.lua", "TestAddon/libs/extra.lua"]
+        );
+    }
+
+    #[test]
+    fn test_verify_without_inventory() {
+        let temp = tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        let addon = install_test_addon(&conn, temp.path());
+        database::replace_addon_files(&conn, &addon.slug, &[]).unwrap();
+
+        let result = verify_addon(&conn, &addon).unwrap();
+        assert!(!result.has_inventory);
+        assert!(!result.has_changes());
+    }
+
+    #[test]
+    fn test_backup_local_changes() {
+        let temp = tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        install_test_addon(&conn, temp.path());
+        let patches = temp.path().join("patches");
+
+        let inventories = load_inventories(&conn, "test-addon").unwrap();
+        assert!(backup_local_changes(&inventories, "test-addon", &patches)
+            .unwrap()
+            .is_none());
+
+        let root = temp.path().join("TestAddon");
+        fs::write(root.join("libs/lib.lua"), "-- patched lib").unwrap();
+
+        let backup = backup_local_changes(&inventories, "test-addon", &patches)
+            .unwrap()
+            .unwrap();
+        assert!(backup.starts_with(patches.join("test-addon")));
+        assert_eq!(
+            fs::read_to_string(backup.join("TestAddon/libs/lib.lua")).unwrap(),
+            "-- patched lib"
+        );
+        assert!(!backup.join("TestAddon/main.lua").exists());
+    }
+
+    #[test]
+    fn test_remove_addon_files_keeps_user_files() {
+        let temp = tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        let addon = install_test_addon(&conn, temp.path());
+
+        let root = temp.path().join("TestAddon");
+        fs::create_dir_all(root.join("custom")).unwrap();
+        fs::write(root.join("custom/profile.lua"), "-- mine").unwrap();
+
+        remove_addon_files(&conn, &addon, true).unwrap();
+
+        assert!(root.join("custom/profile.lua").exists());
+        assert!(!root.join("main.lua").exists());
+        assert!(!root.join("libs").exists());
+
+        remove_addon_files(&conn, &addon, false).unwrap();
+        assert!(!root.exists());
+    }
+}
@@ -0,0 +1,427 @@
+use super::downloader::{download_with_retry, DownloadControl, Transfer};
+use crate::error::{AppError, Result};
+use crate::models::{DownloadPhase, TransferProgress};
+use crate::utils::checksum::ExpectedIntegrity;
+use crate::utils::progress::ProgressTracker;
+use crate::utils::retry::RetryPolicy;
+use crate::utils::zip::{create_archive, is_safe_relative_path};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures_util::{stream, TryStreamExt};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// jsDelivr data API endpoint listing the files of GitHub repositories
+pub const JSDELIVR_API_URL: &str = "https://data.jsdelivr.com/v1/packages/gh";
+
+/// Number of files of one package downloaded at the same time
+const PARALLEL_FILES: usize = 8;
+
+/// A GitHub repository served file by file through jsDelivr
+///
+/// Parsed from a CDN URL of the form `https://cdn.jsdelivr.net/gh/<owner>/<repo>@<ref>/<path>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsDelivrPackage {
+    /// Everything before `/gh/`, normally `https://cdn.jsdelivr.net`
+    cdn_base: String,
+    pub owner: String,
+    pub repo: String,
+    pub git_ref: String,
+    /// Folder within the repository to download, empty for the whole repository
+    pub path: String,
+}
+
+impl JsDelivrPackage {
+    pub fn parse(url: &str) -> Option<Self> {
+        let (cdn_base, rest) = url.split_once("/gh/")?;
+        let rest = rest.split(['?', '#']).next()?;
+        let (owner, rest) = rest.split_once('/')?;
+        let (repo_ref, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let (repo, git_ref) = repo_ref.split_once('@')?;
+
+        if owner.is_empty() || repo.is_empty() || git_ref.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            cdn_base: cdn_base.to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            git_ref: git_ref.to_string(),
+            path: path.trim_matches('/').to_string(),
+        })
+    }
+
+    fn listing_url(&self, api_url: &str) -> String {
+        format!(
+            "{}/{}/{}@{}?structure=flat",
+            api_url.trim_end_matches('/'),
+            self.owner,
+            self.repo,
+            self.git_ref
+        )
+    }
+
+    fn file_url(&self, name: &str) -> String {
+        format!(
+            "{}/gh/{}/{}@{}/{}",
+            self.cdn_base, self.owner, self.repo, self.git_ref, name
+        )
+    }
+
+    /// Name of the archive root folder, the same as GitHub uses (`<repo>-<ref>`)
+    fn root_folder(&self) -> String {
+        format!("{}-{}", self.repo, self.git_ref.replace('/', "-"))
+    }
+
+    /// Whether a repository file lies within the package's folder
+    fn contains(&self, name: &str) -> bool {
+        self.path.is_empty()
+            || name
+                .strip_prefix(&self.path)
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/// File listing returned by the jsDelivr data API with `structure=flat`
+#[derive(Debug, Deserialize)]
+struct PackageListing {
+    files: Vec<ListedFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedFile {
+    /// Path within the repository, starting with `/`
+    name: String,
+    /// Base64-encoded SHA-256 of the file
+    hash: Option<String>,
+    size: Option<u64>,
+}
+
+impl ListedFile {
+    /// Size and checksum to verify the downloaded file against
+    fn integrity(&self) -> ExpectedIntegrity {
+        let checksum = self
+            .hash
+            .as_deref()
+            .and_then(|hash| BASE64.decode(hash).ok())
+            .filter(|digest| digest.len() == 32)
+            .map(|digest| {
+                let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+                format!("sha256:{}", hex)
+            });
+
+        ExpectedIntegrity {
+            checksum,
+            file_size: self.size,
+        }
+    }
+}
+
+/// Download a repository folder from jsDelivr file by file and pack it into an archive
+///
+/// The files are listed with the jsDelivr data API, downloaded in parallel into a
+/// staging tree and verified against the hashes in the listing. The tree is then
+/// packed into `target_path` laid out like a GitHub source archive, so the installer
+/// applies `extract_path` and `excludes` to it as usual. Each request is retried on
+/// transient failures. Returns the combined transfer of the files; on failure, returns
+/// the error and the number of attempts made.
+pub(crate) async fn download_package<F>(
+    client: &reqwest::Client,
+    api_url: &str,
+    url: &str,
+    target_path: &Path,
+    policy: &RetryPolicy,
+    control: &DownloadControl,
+    on_progress: &F,
+) -> std::result::Result<Transfer, (AppError, u32)>
+where
+    F: Fn(&TransferProgress) + Sync,
+{
+    let package = JsDelivrPackage::parse(url).ok_or_else(|| {
+        (
+            AppError::Download(format!("Not a jsDelivr GitHub URL: {}", url)),
+            0,
+        )
+    })?;
+    let staging = tempfile::tempdir().map_err(|e| (e.into(), 0))?;
+
+    let files = fetch_listing(client, api_url, &package, staging.path(), policy, control).await?;
+    if files.is_empty() {
+        return Err((
+            AppError::Download(format!(
+                "jsDelivr lists no files under {:?} in {}/{}@{}",
+                package.path, package.owner, package.repo, package.git_ref
+            )),
+            1,
+        ));
+    }
+
+    let tree = staging.path().join("files");
+    let total: u64 = files.iter().filter_map(|f| f.size).sum();
+    let progress = Mutex::new((
+        vec![0u64; files.len()],
+        ProgressTracker::new(url, Some(total), 0),
+    ));
+
+    // Report the combined bytes of all files as one transfer
+    let report = |index: usize, downloaded: u64| {
+        if let Ok(mut progress) = progress.lock() {
+            let (counts, tracker) = &mut *progress;
+            counts[index] = downloaded;
+            if let Some(update) = tracker.update(counts.iter().sum()) {
+                on_progress(&update);
+            }
+        }
+    };
+
+    // Read times are summed across the parallel files, so the rate is per connection
+    // like that of an archive download
+    let transferred = Mutex::new(Transfer::default());
+
+    stream::iter(files.iter().enumerate().map(Ok))
+        .try_for_each_concurrent(PARALLEL_FILES, |(index, file)| {
+            let name = file.name.trim_start_matches('/');
+            let path = tree.join(name);
+            let file_url = package.file_url(name);
+            let integrity = file.integrity();
+            let report = &report;
+            let transferred = &transferred;
+
+            async move {
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| (e.into(), 0))?;
+                }
+                download_with_retry(
+                    client,
+                    &file_url,
+                    &path,
+                    Some(&integrity),
+                    None,
+                    policy,
+                    control,
+                    &|p: &TransferProgress| report(index, p.downloaded),
+                )
+                .await
+                .map(|transfer| {
+                    if let Ok(mut transferred) = transferred.lock() {
+                        *transferred += transfer;
+                    }
+                })
+            }
+        })
+        .await?;
+
+    if let Ok((_, tracker)) = progress.into_inner() {
+        on_progress(&tracker.report(total, DownloadPhase::Downloading));
+    }
+
+    create_archive(&tree, &package.root_folder(), target_path).map_err(|e| (e, 1))?;
+    Ok(transferred.into_inner().unwrap_or_default())
+}
+
+/// Fetch the files of the package's folder from the jsDelivr data API
+async fn fetch_listing(
+    client: &reqwest::Client,
+    api_url: &str,
+    package: &JsDelivrPackage,
+    staging: &Path,
+    policy: &RetryPolicy,
+    control: &DownloadControl,
+) -> std::result::Result<Vec<ListedFile>, (AppError, u32)> {
+    let listing_path = staging.join("listing.json");
+    download_with_retry(
+        client,
+        &package.listing_url(api_url),
+        &listing_path,
+        None,
+        None,
+        policy,
+        control,
+        &|_: &TransferProgress| {},
+    )
+    .await?;
+
+    let parse = || -> Result<Vec<ListedFile>> {
+        let listing: PackageListing = serde_json::from_slice(&std::fs::read(&listing_path)?)?;
+        let mut files = Vec::new();
+        for file in listing.files {
+            let name = file.name.trim_start_matches('/');
+            if !package.contains(name) {
+                continue;
+            }
+            if !is_safe_relative_path(name) {
+                return Err(AppError::UnsafeArchive(format!(
+                    "invalid file name in jsDelivr listing: {:?}",
+                    file.name
+                )));
+            }
+            files.push(file);
+        }
+        Ok(files)
+    };
+
+    parse().map_err(|e| (e, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::InstallInfo;
+    use crate::services::http::{build_client, HttpConfig};
+    use crate::utils::checksum::sha256_hex;
+    use crate::utils::test_server::{request_path, Response, TestServer};
+    use crate::utils::zip::extract_archive_with_options;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_parse_package_url() {
+        let package =
+            JsDelivrPackage::parse("https://cdn.jsdelivr.net/gh/owner/MyAddon@v1.2.0/MyAddon/")
+                .unwrap();
+        assert_eq!(package.owner, "owner");
+        assert_eq!(package.repo, "MyAddon");
+        assert_eq!(package.git_ref, "v1.2.0");
+        assert_eq!(package.path, "MyAddon");
+        assert_eq!(
+            package.listing_url(JSDELIVR_API_URL),
+            "https://data.jsdelivr.com/v1/packages/gh/owner/MyAddon@v1.2.0?structure=flat"
+        );
+        assert_eq!(
+            package.file_url("MyAddon/MyAddon.txt"),
+            "https://cdn.jsdelivr.net/gh/owner/MyAddon@v1.2.0/MyAddon/MyAddon.txt"
+        );
+        assert!(package.contains("MyAddon/lib/a.lua"));
+        assert!(!package.contains("MyAddonExtra/a.lua"));
+
+        let whole = JsDelivrPackage::parse("https://cdn.jsdelivr.net/gh/owner/repo@main").unwrap();
+        assert_eq!(whole.path, "");
+        assert!(whole.contains("README.md"));
+
+        assert!(JsDelivrPackage::parse("https://cdn.jsdelivr.net/gh/owner/repo/").is_none());
+        assert!(JsDelivrPackage::parse("https://example.com/addon.zip").is_none());
+    }
+
+    /// Serve a jsDelivr-like API listing `listed` and a CDN serving `files`
+    async fn spawn_jsdelivr(
+        listed: &[(&'static str, &[u8])],
+        files: Vec<(&'static str, Vec<u8>)>,
+    ) -> TestServer {
+        let listing = serde_json::json!({
+            "files": listed
+                .iter()
+                .map(|(name, body)| serde_json::json!({
+                    "name": format!("/{}", name),
+                    "hash": BASE64.encode(Sha256::digest(body)),
+                    "size": body.len(),
+                }))
+                .collect::<Vec<_>>()
+        })
+        .to_string();
+
+        TestServer::spawn(move |request, _| {
+            let path = request_path(request);
+            let body = if path.starts_with("/v1/packages/gh/owner/repo@abc123") {
+                Some(listing.clone().into_bytes())
+            } else {
+                path.strip_prefix("/gh/owner/repo@abc123/")
+                    .and_then(|name| files.iter().find(|(n, _)| *n == name))
+                    .map(|(_, body)| body.clone())
+            };
+
+            match body {
+                Some(body) => Response::ok(body),
+                None => Response::not_found(),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_download_package_installs_like_an_archive() {
+        let files: Vec<(&'static str, Vec<u8>)> = vec![
+            ("README.md", b"readme".to_vec()),
+            ("MyAddon/MyAddon.txt", b"## Title: MyAddon".to_vec()),
+            ("MyAddon/MyAddon.lua", b"MyAddon = {}".to_vec()),
+            ("MyAddon/.github/ci.yml", b"on: push".to_vec()),
+        ];
+        let listed: Vec<_> = files.iter().map(|(n, b)| (*n, b.as_slice())).collect();
+        let server = spawn_jsdelivr(&listed, files.clone()).await;
+
+        let temp = tempfile::tempdir().unwrap();
+        let archive = temp.path().join("addon.zip");
+        let client = build_client(&HttpConfig::default()).unwrap();
+        let reports = Mutex::new(Vec::new());
+
+        download_package(
+            &client,
+            &server.url("/v1/packages/gh"),
+            &server.url("/gh/owner/repo@abc123/MyAddon"),
+            &archive,
+            &RetryPolicy::default(),
+            &DownloadControl::default(),
+            &|p: &TransferProgress| reports.lock().unwrap().push(p.downloaded),
+        )
+        .await
+        .unwrap();
+
+        // The listing plus the three files under MyAddon/
+        assert_eq!(server.requests().len(), 4);
+        assert_eq!(reports.lock().unwrap().last(), Some(&37));
+
+        let info = InstallInfo {
+            method: "branch".to_string(),
+            extract_path: Some("MyAddon".to_string()),
+            target_folder: "MyAddon".to_string(),
+            excludes: vec![".github".to_string()],
+        };
+        let out = temp.path().join("out");
+        extract_archive_with_options(&archive, &out, Some(&info)).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(out.join("MyAddon.lua")).unwrap(),
+            "MyAddon = {}"
+        );
+        assert!(out.join("MyAddon.txt").exists());
+        assert!(!out.join(".github").exists());
+        assert!(!out.join("README.md").exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_package_verifies_file_hashes() {
+        let listed = ListedFile {
+            name: "/MyAddon/MyAddon.lua".to_string(),
+            hash: Some(BASE64.encode(Sha256::digest(b"MyAddon = {}"))),
+            size: Some(12),
+        };
+        assert_eq!(
+            listed.integrity().checksum.unwrap(),
+            format!("sha256:{}", sha256_hex(b"MyAddon = {}"))
+        );
+
+        // The CDN serves different content than the listing describes
+        let server = spawn_jsdelivr(
+            &[("MyAddon/MyAddon.lua", b"MyAddon = {}")],
+            vec![("MyAddon/MyAddon.lua", b"Evil = {}!!!".to_vec())],
+        )
+        .await;
+
+        let temp = tempfile::tempdir().unwrap();
+        let client = build_client(&HttpConfig::default()).unwrap();
+        let result = download_package(
+            &client,
+            &server.url("/v1/packages/gh"),
+            &server.url("/gh/owner/repo@abc123/MyAddon"),
+            &temp.path().join("addon.zip"),
+            &RetryPolicy::default(),
+            &DownloadControl::default(),
+            &|_: &TransferProgress| {},
+        )
+        .await;
+        assert!(matches!(result, Err((AppError::Integrity(_), 1))));
+        assert!(!temp.path().join("addon.zip").exists());
+    }
+}
@@ -0,0 +1,184 @@
+use super::{database, installer, inventory};
+use crate::error::Result;
+use crate::models::{InstalledAddon, SourceType};
+use crate::utils::manifest::parse_manifest;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// Slug for an addon folder installed from `parent_slug`'s archive
+pub fn linked_slug(parent_slug: &str, folder_name: &str) -> String {
+    format!("{}/{}", parent_slug, folder_name.to_lowercase())
+}
+
+/// Folder name of an installed addon, derived from its manifest path
+pub fn addon_folder(addon: &InstalledAddon) -> Option<String> {
+    Path::new(&addon.manifest_path)
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .map(String::from)
+}
+
+/// Folders owned by managed addons outside `slug`'s own set
+/// Bundled copies of these are not installed over the separately managed addon.
+pub fn foreign_folders(conn: &Connection, slug: &str) -> Result<Vec<String>> {
+    Ok(database::get_all_installed(conn)?
+        .iter()
+        .filter(|a| a.source_type != SourceType::Local)
+        .filter(|a| a.slug != slug && a.parent_slug.as_deref() != Some(slug))
+        .filter_map(addon_folder)
+        .collect())
+}
+
+/// Record the bundled addon folders installed alongside `parent` as linked rows
+///
+/// Linked addons from a previous install that the new archive no longer contains
+/// are removed from disk and the database. Auto-imported local rows for the
+/// installed folders are replaced by the linked rows.
+pub fn record_linked(
+    conn: &Connection,
+    parent: &InstalledAddon,
+    folders: &[PathBuf],
+) -> Result<Vec<InstalledAddon>> {
+    let folder_names: Vec<String> = folders
+        .iter()
+        .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(String::from))
+        .collect();
+
+    for previous in database::get_linked_addons(conn, &parent.slug)? {
+        let still_bundled = addon_folder(&previous)
+            .map(|f| folder_names.iter().any(|n| n.eq_ignore_ascii_case(&f)))
+            .unwrap_or(false);
+        if !still_bundled {
+            if let Some(dir) = Path::new(&previous.manifest_path).parent() {
+                installer::uninstall_addon(dir)?;
+            }
+            database::delete_installed(conn, &previous.slug)?;
+        }
+    }
+
+    let installed = database::get_all_installed(conn)?;
+    let mut linked = Vec::new();
+
+    for (folder, folder_name) in folders.iter().zip(&folder_names) {
+        let Some(manifest_path) = installer::get_manifest_path(folder) else {
+            continue;
+        };
+        let manifest = parse_manifest(&manifest_path).ok();
+
+        // The folder was auto-imported as a local addon before this install
+        for local in installed.iter().filter(|a| {
+            a.source_type == SourceType::Local
+                && addon_folder(a).is_some_and(|f| f.eq_ignore_ascii_case(folder_name))
+        }) {
+            database::delete_installed(conn, &local.slug)?;
+        }
+
+        linked.push(database::insert_linked_installed(
+            conn,
+            parent,
+            &linked_slug(&parent.slug, folder_name),
+            manifest
+                .as_ref()
+                .map(|m| m.title.as_str())
+                .unwrap_or(folder_name),
+            manifest
+                .as_ref()
+                .and_then(|m| m.version.as_deref())
+                .unwrap_or(&parent.installed_version),
+            manifest_path.to_string_lossy().as_ref(),
+        )?);
+    }
+
+    Ok(linked)
+}
+
+/// Remove every addon installed from `parent_slug`'s archive from disk and the database
+/// With `keep_user_files`, files the user added to those folders are left behind.
+pub fn uninstall_linked(conn: &Connection, parent_slug: &str, keep_user_files: bool) -> Result<()> {
+    for addon in database::get_linked_addons(conn, parent_slug)? {
+        inventory::remove_addon_files(conn, &addon, keep_user_files)?;
+        database::delete_installed(conn, &addon.slug)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write_addon(addon_dir: &Path, folder: &str, title: &str) -> PathBuf {
+        let path = addon_dir.join(folder);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(
+            path.join(format!("{}.txt", folder)),
+            format!("## Title: {}\n## Version: 2.0\n", title),
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_record_and_uninstall_linked() {
+        let temp = tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+
+        let main = write_addon(temp.path(), "Suite", "Suite");
+        let parent = database::insert_installed(
+            &conn,
+            "suite",
+            "Suite",
+            "1.0",
+            SourceType::Github,
+            Some("author/suite"),
+            main.join("Suite.txt").to_string_lossy().as_ref(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        // A previously auto-imported copy of the bundled library is taken over
+        let lib = write_addon(temp.path(), "LibSuite", "Lib Suite");
+        database::insert_installed(
+            &conn,
+            "libsuite",
+            "Lib Suite",
+            "1.0",
+            SourceType::Local,
+            None,
+            lib.join("LibSuite.txt").to_string_lossy().as_ref(),
+            None,
+            None,
+        )
+        .unwrap();
+        let module = write_addon(temp.path(), "SuiteModule", "Suite Module");
+
+        let linked = record_linked(&conn, &parent, &[lib.clone(), module.clone()]).unwrap();
+        assert_eq!(linked.len(), 2);
+        assert_eq!(linked[0].slug, "suite/libsuite");
+        assert_eq!(linked[0].installed_version, "2.0");
+        assert_eq!(linked[0].parent_slug.as_deref(), Some("suite"));
+        assert_eq!(linked[0].source_type, SourceType::Github);
+        assert!(database::get_installed_by_slug(&conn, "libsuite")
+            .unwrap()
+            .is_none());
+
+        // A reinstall without the module drops it from disk and the database
+        record_linked(&conn, &parent, std::slice::from_ref(&lib)).unwrap();
+        assert!(!module.exists());
+        assert_eq!(
+            database::get_linked_addons(&conn, "suite").unwrap().len(),
+            1
+        );
+
+        uninstall_linked(&conn, "suite", false).unwrap();
+        assert!(!lib.exists());
+        assert!(main.exists());
+        assert!(database::get_linked_addons(&conn, "suite")
+            .unwrap()
+            .is_empty());
+    }
+}
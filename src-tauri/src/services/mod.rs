@@ -1,11 +1,39 @@
+pub mod api_cache;
+pub mod cache;
+pub mod catalog;
 pub mod database;
 pub mod downloader;
+pub mod github;
+pub mod graphql;
+pub mod health;
+pub mod history;
+pub mod http;
 pub mod installer;
+pub mod inventory;
+pub mod jsdelivr;
+pub mod linked;
+pub mod queue;
 pub mod resolver;
 pub mod scanner;
+pub mod secrets;
+pub mod throttle;
 
+pub use api_cache::*;
+pub use cache::*;
+pub use catalog::*;
 pub use database::*;
 pub use downloader::*;
+pub use github::*;
+pub use graphql::*;
+pub use health::*;
+pub use history::*;
+pub use http::*;
 pub use installer::*;
+pub use inventory::*;
+pub use jsdelivr::*;
+pub use linked::*;
+pub use queue::*;
 pub use resolver::*;
 pub use scanner::*;
+pub use secrets::*;
+pub use throttle::*;
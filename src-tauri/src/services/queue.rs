@@ -0,0 +1,304 @@
+use super::database;
+use crate::models::InstalledAddon;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{oneshot, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+/// Number of queued downloads that run at the same time unless configured otherwise
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 2;
+
+/// Outcome of a queued install, delivered to whoever is waiting on the job
+pub type JobResult = Result<InstalledAddon, String>;
+
+/// Get the configured number of concurrent downloads (at least 1)
+pub fn get_max_concurrent_downloads(conn: &Connection) -> usize {
+    database::get_setting(conn, "max_concurrent_downloads")
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_DOWNLOADS)
+        .max(1)
+}
+
+/// A running install or update, identified by its download job id
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub id: i64,
+    pub token: CancellationToken,
+}
+
+impl Operation {
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+/// In-memory side of the download queue
+///
+/// Jobs themselves are persisted in the downloads table; this tracks the cancellation
+/// token of every operation in this session, limits how many queued jobs run at
+/// once, and hands results to callers waiting on a job.
+pub struct DownloadQueue {
+    slots: Arc<Semaphore>,
+    operations: Mutex<HashMap<i64, CancellationToken>>,
+    waiters: Mutex<HashMap<i64, oneshot::Sender<JobResult>>>,
+}
+
+impl DownloadQueue {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            operations: Mutex::new(HashMap::new()),
+            waiters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Register an operation so it can be cancelled from now on
+    pub fn begin(&self, id: i64) -> Operation {
+        let token = CancellationToken::new();
+        if let Ok(mut operations) = self.operations.lock() {
+            if let Some(previous) = operations.insert(id, token.clone()) {
+                previous.cancel();
+            }
+        }
+        Operation { id, token }
+    }
+
+    /// Run a queued operation once a slot is free
+    /// Returns None when it was cancelled while waiting; once started, the task is
+    /// expected to watch the operation's token itself so it can clean up.
+    pub async fn run<F: Future>(&self, operation: &Operation, task: F) -> Option<F::Output> {
+        let _permit = tokio::select! {
+            permit = self.slots.clone().acquire_owned() => permit.ok(),
+            _ = operation.token.cancelled() => return None,
+        };
+        Some(task.await)
+    }
+
+    /// Request cancellation of an operation, returning whether it is running in this session
+    pub fn cancel(&self, id: i64) -> bool {
+        let token = self
+            .operations
+            .lock()
+            .ok()
+            .and_then(|operations| operations.get(&id).cloned());
+        match token {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether an operation is queued or running in this session
+    pub fn is_active(&self, id: i64) -> bool {
+        self.operations
+            .lock()
+            .map(|operations| operations.contains_key(&id))
+            .unwrap_or(false)
+    }
+
+    /// Wait for the result of a job
+    pub fn wait(&self, id: i64) -> oneshot::Receiver<JobResult> {
+        let (sender, receiver) = oneshot::channel();
+        if let Ok(mut waiters) = self.waiters.lock() {
+            waiters.insert(id, sender);
+        }
+        receiver
+    }
+
+    /// Mark an operation as done and pass its result to the waiter, if any
+    pub fn finish(&self, id: i64, result: JobResult) {
+        if let Ok(mut operations) = self.operations.lock() {
+            operations.remove(&id);
+        }
+        let waiter = self
+            .waiters
+            .lock()
+            .ok()
+            .and_then(|mut waiters| waiters.remove(&id));
+        if let Some(waiter) = waiter {
+            let _ = waiter.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DownloadStatus;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_queue_limits_concurrency() {
+        let queue = Arc::new(DownloadQueue::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..5)
+            .map(|id| {
+                let queue = queue.clone();
+                let running = running.clone();
+                let peak = peak.clone();
+                let operation = queue.begin(id);
+                tokio::spawn(async move {
+                    queue
+                        .run(&operation, async {
+                            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                            peak.fetch_max(now, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            running.fetch_sub(1, Ordering::SeqCst);
+                        })
+                        .await
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            assert!(task.await.unwrap().is_some());
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_waiting_and_running_operations() {
+        let queue = Arc::new(DownloadQueue::new(1));
+
+        // The first operation holds the only slot until it is cancelled
+        let first = queue.begin(1);
+        let running = {
+            let queue = queue.clone();
+            let operation = first.clone();
+            tokio::spawn(async move {
+                queue
+                    .run(&operation, async {
+                        operation.token.cancelled().await;
+                        "stopped"
+                    })
+                    .await
+            })
+        };
+
+        let second = queue.begin(2);
+        let waiting = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.run(&second, async { "ran" }).await })
+        };
+
+        assert!(queue.is_active(2));
+        assert!(queue.cancel(2));
+        assert_eq!(waiting.await.unwrap(), None);
+
+        assert!(queue.cancel(1));
+        assert!(first.is_cancelled());
+        assert_eq!(running.await.unwrap(), Some("stopped"));
+
+        let waiter = queue.wait(1);
+        queue.finish(1, Err("Operation cancelled".to_string()));
+        assert!(!queue.is_active(1));
+        assert!(!queue.cancel(1));
+        assert_eq!(waiter.await.unwrap().unwrap_err(), "Operation cancelled");
+    }
+
+    #[test]
+    fn test_download_jobs_persist_until_cleared() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+
+        let first = database::insert_download(&conn, "a", "Addon A", "1.0", Some("{}")).unwrap();
+        let second = database::insert_download(&conn, "b", "Addon B", "2.0", Some("{}")).unwrap();
+        assert_eq!(first.status, DownloadStatus::Pending);
+        assert!(first.created_at.is_some());
+
+        // Interrupted jobs are picked up again, oldest first
+        database::set_download_status(&conn, first.id, DownloadStatus::Downloading, 0.0, None)
+            .unwrap();
+        assert_eq!(
+            database::get_unfinished_downloads(&conn).unwrap(),
+            vec![first.id, second.id]
+        );
+
+        database::set_download_status(&conn, first.id, DownloadStatus::Complete, 1.0, None)
+            .unwrap();
+        let done = database::get_download(&conn, first.id).unwrap().unwrap();
+        assert!(done.started_at.is_some());
+        assert!(done.completed_at.is_some());
+
+        database::set_download_status(&conn, second.id, DownloadStatus::Failed, 0.0, Some("x"))
+            .unwrap();
+        database::reset_download(&conn, second.id).unwrap();
+        let retried = database::get_download(&conn, second.id).unwrap().unwrap();
+        assert_eq!(retried.status, DownloadStatus::Pending);
+        assert!(retried.error.is_none());
+
+        assert_eq!(database::delete_finished_downloads(&conn).unwrap(), 1);
+        let remaining = database::get_downloads(&conn).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name, "Addon B");
+    }
+
+    #[test]
+    fn test_download_progress_is_saved_while_running() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+
+        let job = database::insert_download(&conn, "a", "Addon A", "1.0", Some("{}")).unwrap();
+        database::set_download_status(&conn, job.id, DownloadStatus::Downloading, 0.0, None)
+            .unwrap();
+        database::set_download_progress(&conn, job.id, 0.4).unwrap();
+        let running = database::get_download(&conn, job.id).unwrap().unwrap();
+        assert_eq!(running.progress, 0.4);
+
+        // A late progress report doesn't change a finished job
+        database::set_download_status(&conn, job.id, DownloadStatus::Failed, 0.0, Some("x"))
+            .unwrap();
+        database::set_download_progress(&conn, job.id, 0.9).unwrap();
+        let failed = database::get_download(&conn, job.id).unwrap().unwrap();
+        assert_eq!(failed.progress, 0.0);
+    }
+
+    #[test]
+    fn test_active_download_per_addon() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        assert_eq!(database::get_active_download(&conn, "a").unwrap(), None);
+
+        let job = database::insert_download(&conn, "a", "Addon A", "1.0", Some("{}")).unwrap();
+        database::insert_download(&conn, "b", "Addon B", "1.0", Some("{}")).unwrap();
+        assert_eq!(
+            database::get_active_download(&conn, "a").unwrap(),
+            Some(job.id)
+        );
+
+        // Extracting jobs are still active; finished ones are not
+        database::set_download_status(&conn, job.id, DownloadStatus::Extracting, 1.0, None)
+            .unwrap();
+        assert_eq!(
+            database::get_active_download(&conn, "a").unwrap(),
+            Some(job.id)
+        );
+        database::set_download_status(&conn, job.id, DownloadStatus::Complete, 1.0, None).unwrap();
+        assert_eq!(database::get_active_download(&conn, "a").unwrap(), None);
+    }
+
+    #[test]
+    fn test_max_concurrent_downloads_setting() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        assert_eq!(
+            get_max_concurrent_downloads(&conn),
+            DEFAULT_MAX_CONCURRENT_DOWNLOADS
+        );
+
+        database::set_setting(&conn, "max_concurrent_downloads", "0").unwrap();
+        assert_eq!(get_max_concurrent_downloads(&conn), 1);
+
+        database::set_setting(&conn, "max_concurrent_downloads", "4").unwrap();
+        assert_eq!(get_max_concurrent_downloads(&conn), 4);
+    }
+}
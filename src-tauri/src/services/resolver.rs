@@ -309,6 +309,7 @@ mod tests {
             version_info: None,
             download_sources: vec![],
             last_updated: None,
+            provenance: None,
         }
     }
 
@@ -320,6 +321,8 @@ mod tests {
             addon_count: 1,
             addons: vec![create_test_addon("test-addon", "Test Addon", vec![])],
             fetched_at: None,
+            fetch_source: None,
+            source_errors: vec![],
         };
 
         let result = resolve_dependencies("test-addon", &index, &[]);
@@ -340,6 +343,8 @@ mod tests {
                 create_test_addon("lib-addon", "Lib Addon", vec![]),
             ],
             fetched_at: None,
+            fetch_source: None,
+            source_errors: vec![],
         };
 
         let result = resolve_dependencies("test-addon", &index, &[]);
@@ -362,6 +367,8 @@ mod tests {
                 vec!["unknown-lib"],
             )],
             fetched_at: None,
+            fetch_source: None,
+            source_errors: vec![],
         };
 
         let result = resolve_dependencies("test-addon", &index, &[]);
@@ -0,0 +1,178 @@
+use super::database;
+use crate::error::{AppError, Result};
+use crate::utils::paths::get_secret_key_path;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use rusqlite::Connection;
+use std::fs;
+use std::path::PathBuf;
+
+/// Name of the stored GitHub personal access token
+pub const GITHUB_TOKEN: &str = "github_token";
+
+/// Get the stored GitHub personal access token, if any
+pub fn load_github_token(conn: &Connection) -> Option<String> {
+    SecretStore::open()?.get(conn, GITHUB_TOKEN).ok().flatten()
+}
+
+/// Secrets kept in the settings table, encrypted with AES-256-GCM
+///
+/// The key lives in its own file in the app data directory (readable only by the
+/// user on Unix), so the database alone never reveals a secret. Each value is stored
+/// as base64 of the nonce followed by the ciphertext, under `secret:<name>`, with the
+/// name as associated data so a value can't be swapped for another secret's.
+pub struct SecretStore {
+    key_path: PathBuf,
+}
+
+impl SecretStore {
+    pub fn new(key_path: PathBuf) -> Self {
+        Self { key_path }
+    }
+
+    /// The secret store of this installation
+    pub fn open() -> Option<Self> {
+        Some(Self::new(get_secret_key_path()?))
+    }
+
+    /// Decrypt a stored secret
+    pub fn get(&self, conn: &Connection, name: &str) -> Result<Option<String>> {
+        let Some(stored) = database::get_setting(conn, &setting_key(name))? else {
+            return Ok(None);
+        };
+
+        let mut data = BASE64
+            .decode(stored)
+            .map_err(|_| AppError::Secret(format!("{} is not valid base64", name)))?;
+        if data.len() < NONCE_LEN {
+            return Err(AppError::Secret(format!("{} is truncated", name)));
+        }
+        let mut ciphertext = data.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&data)
+            .map_err(|_| AppError::Secret("invalid nonce".to_string()))?;
+
+        let plaintext = self
+            .key(false)?
+            .open_in_place(nonce, Aad::from(name.as_bytes()), &mut ciphertext)
+            .map_err(|_| AppError::Secret(format!("{} could not be decrypted", name)))?;
+        let value = String::from_utf8(plaintext.to_vec())
+            .map_err(|_| AppError::Secret(format!("{} is not valid UTF-8", name)))?;
+        Ok(Some(value))
+    }
+
+    /// Encrypt and store a secret, or remove it when `value` is None
+    pub fn set(&self, conn: &Connection, name: &str, value: Option<&str>) -> Result<()> {
+        let Some(value) = value else {
+            return database::delete_setting(conn, &setting_key(name));
+        };
+
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| AppError::Secret("no random numbers available".to_string()))?;
+
+        let mut ciphertext = value.as_bytes().to_vec();
+        self.key(true)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(name.as_bytes()),
+                &mut ciphertext,
+            )
+            .map_err(|_| AppError::Secret(format!("{} could not be encrypted", name)))?;
+
+        let mut stored = nonce.to_vec();
+        stored.extend_from_slice(&ciphertext);
+        database::set_setting(conn, &setting_key(name), &BASE64.encode(stored))
+    }
+
+    /// Load the encryption key, generating it first if `create` is set and there is none
+    fn key(&self, create: bool) -> Result<LessSafeKey> {
+        let bytes = match fs::read(&self.key_path) {
+            Ok(bytes) => bytes,
+            Err(e) if create && e.kind() == std::io::ErrorKind::NotFound => self.create_key()?,
+            Err(e) => return Err(e.into()),
+        };
+
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| AppError::Secret("invalid key file".to_string()))?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    fn create_key(&self) -> Result<Vec<u8>> {
+        let mut key = vec![0u8; AES_256_GCM.key_len()];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| AppError::Secret("no random numbers available".to_string()))?;
+
+        if let Some(parent) = self.key_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.key_path, &key)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.key_path, fs::Permissions::from_mode(0o600))?;
+        }
+
+        Ok(key)
+    }
+}
+
+fn setting_key(name: &str) -> String {
+    format!("secret:{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_stored_encrypted() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let store = SecretStore::new(temp.path().join("secret.key"));
+
+        assert_eq!(store.get(&conn, GITHUB_TOKEN).unwrap(), None);
+        store
+            .set(&conn, GITHUB_TOKEN, Some("ghp_example123"))
+            .unwrap();
+        assert_eq!(
+            store.get(&conn, GITHUB_TOKEN).unwrap().as_deref(),
+            Some("ghp_example123")
+        );
+
+        // Only ciphertext reaches the database
+        let stored = database::get_setting(&conn, "secret:github_token")
+            .unwrap()
+            .unwrap();
+        assert!(!stored.contains("ghp_example123"));
+
+        // A different key can't decrypt it
+        let other = SecretStore::new(temp.path().join("other.key"));
+        other.set(&conn, "unrelated", Some("x")).unwrap();
+        assert!(other.get(&conn, GITHUB_TOKEN).is_err());
+
+        store.set(&conn, GITHUB_TOKEN, None).unwrap();
+        assert_eq!(store.get(&conn, GITHUB_TOKEN).unwrap(), None);
+    }
+
+    #[test]
+    fn test_secret_is_bound_to_its_name() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let store = SecretStore::new(temp.path().join("secret.key"));
+
+        store.set(&conn, GITHUB_TOKEN, Some("token")).unwrap();
+        let stored = database::get_setting(&conn, "secret:github_token")
+            .unwrap()
+            .unwrap();
+        database::set_setting(&conn, "secret:other", &stored).unwrap();
+
+        assert!(store.get(&conn, "other").is_err());
+    }
+}
@@ -0,0 +1,257 @@
+use super::database;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Who started a download, which decides the bandwidth limits that apply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadPriority {
+    /// Installs and updates the user asked for
+    #[default]
+    User,
+    /// Automatic and bulk updates
+    Background,
+}
+
+/// Rate limits for one priority, in KiB/s (None for unlimited)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimits {
+    /// Combined rate of all downloads with this priority
+    pub total_kib: Option<u64>,
+    /// Rate of each single download
+    pub per_download_kib: Option<u64>,
+}
+
+/// Bandwidth limits for user-initiated and background downloads
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BandwidthConfig {
+    pub user: RateLimits,
+    pub background: RateLimits,
+}
+
+/// Load the bandwidth limits from the settings table
+pub fn load_bandwidth_config(conn: &Connection) -> BandwidthConfig {
+    let limit = |key| {
+        database::get_setting(conn, key)
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse().ok())
+            .filter(|&kib: &u64| kib > 0)
+    };
+
+    BandwidthConfig {
+        user: RateLimits {
+            total_kib: limit("download_rate_limit_kib"),
+            per_download_kib: limit("download_rate_limit_per_download_kib"),
+        },
+        background: RateLimits {
+            total_kib: limit("background_rate_limit_kib"),
+            per_download_kib: limit("background_rate_limit_per_download_kib"),
+        },
+    }
+}
+
+/// Token bucket limiting the bytes per second passed through it
+///
+/// Clones share the same bucket. Transfers may run ahead by up to one second's worth
+/// of bytes; beyond that, `acquire` waits until the bucket has refilled.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    bytes_per_second: Option<u64>,
+    /// Bytes that may be transferred right away; negative while transfers are ahead
+    available: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_second: Option<u64>) -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                bytes_per_second,
+                available: bytes_per_second.unwrap_or(0) as f64,
+                refilled: Instant::now(),
+            })),
+        }
+    }
+
+    /// Change the rate; transfers already using this limiter pick it up right away
+    pub fn set_rate(&self, bytes_per_second: Option<u64>) {
+        if let Ok(mut bucket) = self.bucket.lock() {
+            bucket.bytes_per_second = bytes_per_second;
+            bucket.available = bucket.available.min(bytes_per_second.unwrap_or(0) as f64);
+            bucket.refilled = Instant::now();
+        }
+    }
+
+    /// Account for `bytes` transferred, waiting if the rate has been exceeded
+    pub async fn acquire(&self, bytes: u64) {
+        let wait = self
+            .bucket
+            .lock()
+            .map(|mut bucket| bucket.take(bytes, Instant::now()))
+            .unwrap_or_default();
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+impl Bucket {
+    /// Take `bytes` from the bucket, returning how long to wait to get back within the rate
+    fn take(&mut self, bytes: u64, now: Instant) -> Duration {
+        let Some(rate) = self.bytes_per_second.filter(|&r| r > 0) else {
+            return Duration::ZERO;
+        };
+        let rate = rate as f64;
+
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.available = (self.available + elapsed * rate).min(rate) - bytes as f64;
+        self.refilled = now;
+
+        if self.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available / rate)
+        }
+    }
+}
+
+/// The rate limiters a single download passes through
+#[derive(Debug, Clone, Default)]
+pub struct Throttle {
+    limiters: Vec<RateLimiter>,
+}
+
+impl Throttle {
+    /// Account for `bytes` downloaded, waiting while any limit is exceeded
+    pub async fn acquire(&self, bytes: u64) {
+        for limiter in &self.limiters {
+            limiter.acquire(bytes).await;
+        }
+    }
+}
+
+/// Bandwidth limits shared by all downloads in this session
+pub struct Bandwidth {
+    config: Mutex<BandwidthConfig>,
+    user: RateLimiter,
+    background: RateLimiter,
+}
+
+impl Bandwidth {
+    pub fn new(config: BandwidthConfig) -> Self {
+        Self {
+            user: RateLimiter::new(to_bytes(config.user.total_kib)),
+            background: RateLimiter::new(to_bytes(config.background.total_kib)),
+            config: Mutex::new(config),
+        }
+    }
+
+    /// Apply new limits; running downloads pick up the combined limits right away
+    pub fn configure(&self, config: BandwidthConfig) {
+        self.user.set_rate(to_bytes(config.user.total_kib));
+        self.background
+            .set_rate(to_bytes(config.background.total_kib));
+        if let Ok(mut current) = self.config.lock() {
+            *current = config;
+        }
+    }
+
+    /// Throttle for a new download with the given priority
+    pub fn throttle(&self, priority: DownloadPriority) -> Throttle {
+        let config = self.config.lock().map(|c| c.clone()).unwrap_or_default();
+        let (shared, limits) = match priority {
+            DownloadPriority::User => (&self.user, config.user),
+            DownloadPriority::Background => (&self.background, config.background),
+        };
+
+        let mut limiters = vec![shared.clone()];
+        if let Some(rate) = to_bytes(limits.per_download_kib) {
+            limiters.push(RateLimiter::new(Some(rate)));
+        }
+        Throttle { limiters }
+    }
+}
+
+fn to_bytes(kib: Option<u64>) -> Option<u64> {
+    kib.map(|k| k.saturating_mul(1024))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_waits_once_rate_is_exceeded() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            bytes_per_second: Some(1000),
+            available: 1000.0,
+            refilled: start,
+        };
+
+        // One second's worth may go through right away
+        assert_eq!(bucket.take(1000, start), Duration::ZERO);
+        assert_eq!(bucket.take(500, start), Duration::from_millis(500));
+
+        // Half a second later the debt is paid off, so the next bytes wait again
+        let later = start + Duration::from_millis(500);
+        assert_eq!(bucket.take(250, later), Duration::from_millis(250));
+
+        bucket.bytes_per_second = None;
+        assert_eq!(bucket.take(1_000_000, later), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_throttle_uses_limits_for_priority() {
+        let bandwidth = Bandwidth::new(BandwidthConfig {
+            user: RateLimits::default(),
+            background: RateLimits {
+                total_kib: Some(64),
+                per_download_kib: Some(16),
+            },
+        });
+
+        assert_eq!(bandwidth.throttle(DownloadPriority::User).limiters.len(), 1);
+        let throttle = bandwidth.throttle(DownloadPriority::Background);
+        assert_eq!(throttle.limiters.len(), 2);
+
+        // 16 KiB/s per download: the first second is free, the next 8 KiB take half a second
+        let start = Instant::now();
+        throttle.acquire(16 * 1024).await;
+        throttle.acquire(8 * 1024).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(450), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+
+        // Unlimited user downloads are not slowed down
+        let start = Instant::now();
+        bandwidth
+            .throttle(DownloadPriority::User)
+            .acquire(100 * 1024 * 1024)
+            .await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_load_bandwidth_config() {
+        let conn = Connection::open_in_memory().unwrap();
+        database::run_migrations(&conn).unwrap();
+        assert_eq!(load_bandwidth_config(&conn), BandwidthConfig::default());
+
+        database::set_setting(&conn, "background_rate_limit_kib", "512").unwrap();
+        database::set_setting(&conn, "download_rate_limit_per_download_kib", "0").unwrap();
+
+        let config = load_bandwidth_config(&conn);
+        assert_eq!(config.background.total_kib, Some(512));
+        assert_eq!(config.user.per_download_kib, None);
+    }
+}
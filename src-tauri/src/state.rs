@@ -1,13 +1,82 @@
+use crate::services::github::{is_github_url, GitHubClient};
+use crate::services::http::{build_client, load_http_config, HttpConfig};
+use crate::services::queue::{get_max_concurrent_downloads, DownloadQueue};
+use crate::services::secrets::load_github_token;
+use crate::services::throttle::{load_bandwidth_config, Bandwidth};
 use rusqlite::Connection;
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
 
 /// Application state shared across Tauri commands
 pub struct AppState {
     pub db: Mutex<Connection>,
+    pub queue: DownloadQueue,
+    pub bandwidth: Bandwidth,
+    http: RwLock<reqwest::Client>,
+    github: RwLock<GitHubClient>,
 }
 
 impl AppState {
     pub fn new(db: Connection) -> Self {
-        Self { db: Mutex::new(db) }
+        let queue = DownloadQueue::new(get_max_concurrent_downloads(&db));
+        let bandwidth = Bandwidth::new(load_bandwidth_config(&db));
+
+        // Fall back to defaults if the saved network settings no longer work
+        // (e.g. the CA file was removed), so the app can still start
+        let config = load_http_config(&db);
+        let http = build_client(&config)
+            .or_else(|_| build_client(&HttpConfig::default()))
+            .unwrap_or_default();
+        let token = load_github_token(&db);
+        let github = GitHubClient::new(&config, token.as_deref())
+            .or_else(|_| GitHubClient::new(&HttpConfig::default(), token.as_deref()))
+            .unwrap_or_default();
+
+        Self {
+            db: Mutex::new(db),
+            queue,
+            bandwidth,
+            http: RwLock::new(http),
+            github: RwLock::new(github),
+        }
+    }
+
+    /// The shared HTTP client; clones share one connection pool
+    pub fn http_client(&self) -> reqwest::Client {
+        self.http
+            .read()
+            .map(|client| client.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the shared HTTP client after the network settings changed
+    pub fn set_http_client(&self, client: reqwest::Client) {
+        if let Ok(mut http) = self.http.write() {
+            *http = client;
+        }
+    }
+
+    /// The client for GitHub API requests, carrying the stored token if any
+    pub fn github_client(&self) -> GitHubClient {
+        self.github
+            .read()
+            .map(|client| client.clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the GitHub client after the network settings or the token changed
+    pub fn set_github_client(&self, client: GitHubClient) {
+        if let Ok(mut github) = self.github.write() {
+            *github = client;
+        }
+    }
+
+    /// Client to download a URL with; GitHub URLs use the GitHub client so private
+    /// repositories can be downloaded, while the token never goes anywhere else
+    pub fn download_client(&self, url: &str) -> reqwest::Client {
+        if is_github_url(url) {
+            self.github_client().http().clone()
+        } else {
+            self.http_client()
+        }
     }
 }
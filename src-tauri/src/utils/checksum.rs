@@ -0,0 +1,201 @@
+use crate::error::{AppError, Result};
+use crate::models::AddonRelease;
+use sha2::{Digest, Sha256, Sha512};
+use std::io::Read;
+use std::path::Path;
+
+/// Expected integrity of a download, taken from the index release metadata
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedIntegrity {
+    /// Checksum string, e.g. "sha256:<hex>" (a bare 64-char hex digest is treated as SHA-256)
+    pub checksum: Option<String>,
+    /// Expected file size in bytes
+    pub file_size: Option<u64>,
+}
+
+impl ExpectedIntegrity {
+    /// Build the expected integrity from an index release
+    pub fn from_release(release: &AddonRelease) -> Self {
+        Self {
+            checksum: release.checksum.clone(),
+            file_size: release.file_size,
+        }
+    }
+
+    /// Returns true if there is nothing to verify
+    pub fn is_empty(&self) -> bool {
+        self.checksum.is_none() && self.file_size.is_none()
+    }
+}
+
+/// Supported checksum algorithms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+/// Parse a checksum string into its algorithm and lowercase hex digest
+///
+/// Accepts "sha256:<hex>", "sha512:<hex>" (also with "-" as separator) and a bare
+/// hex digest whose length identifies the algorithm.
+pub fn parse_checksum(checksum: &str) -> Result<(ChecksumAlgorithm, String)> {
+    let checksum = checksum.trim();
+    let (algorithm, digest) = match checksum.split_once([':', '-']) {
+        Some((prefix, digest)) => {
+            let algorithm = match prefix.to_lowercase().as_str() {
+                "sha256" | "sha-256" => ChecksumAlgorithm::Sha256,
+                "sha512" | "sha-512" => ChecksumAlgorithm::Sha512,
+                other => {
+                    return Err(AppError::Integrity(format!(
+                        "Unsupported checksum algorithm: {}",
+                        other
+                    )))
+                }
+            };
+            (algorithm, digest)
+        }
+        None => match checksum.len() {
+            64 => (ChecksumAlgorithm::Sha256, checksum),
+            128 => (ChecksumAlgorithm::Sha512, checksum),
+            _ => {
+                return Err(AppError::Integrity(format!(
+                    "Unrecognized checksum format: {}",
+                    checksum
+                )))
+            }
+        },
+    };
+
+    let digest = digest.to_lowercase();
+    let expected_len = match algorithm {
+        ChecksumAlgorithm::Sha256 => 64,
+        ChecksumAlgorithm::Sha512 => 128,
+    };
+    if digest.len() != expected_len || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::Integrity(format!(
+            "Malformed checksum digest: {}",
+            digest
+        )));
+    }
+
+    Ok((algorithm, digest))
+}
+
+/// Incremental hasher used while streaming a download
+pub enum StreamHasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl StreamHasher {
+    pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => StreamHasher::Sha256(Sha256::new()),
+            ChecksumAlgorithm::Sha512 => StreamHasher::Sha512(Sha512::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            StreamHasher::Sha256(h) => h.update(data),
+            StreamHasher::Sha512(h) => h.update(data),
+        }
+    }
+
+    /// Finish hashing and return the lowercase hex digest
+    pub fn finalize_hex(self) -> String {
+        match self {
+            StreamHasher::Sha256(h) => to_hex(&h.finalize()),
+            StreamHasher::Sha512(h) => to_hex(&h.finalize()),
+        }
+    }
+}
+
+/// Check a file on disk against the expected size and checksum
+pub fn verify_file(path: &Path, integrity: &ExpectedIntegrity) -> Result<()> {
+    if let Some(expected) = integrity.file_size {
+        let actual = std::fs::metadata(path)?.len();
+        if actual != expected {
+            return Err(AppError::Integrity(format!(
+                "expected {} bytes but found {}",
+                expected, actual
+            )));
+        }
+    }
+
+    if let Some(ref checksum) = integrity.checksum {
+        let (algorithm, expected) = parse_checksum(checksum)?;
+        let actual = hash_file(path, algorithm)?;
+        if actual != expected {
+            return Err(AppError::Integrity(format!(
+                "checksum mismatch (expected {}, got {})",
+                expected, actual
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Hash a file and return the lowercase hex digest
+pub fn hash_file(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = StreamHasher::new(algorithm);
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize_hex())
+}
+
+/// Hash a byte slice with SHA-256 and return the lowercase hex digest
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    #[test]
+    fn test_parse_checksum_prefixes() {
+        let (algorithm, digest) = parse_checksum(&format!("sha256:{}", TEST_SHA256)).unwrap();
+        assert_eq!(algorithm, ChecksumAlgorithm::Sha256);
+        assert_eq!(digest, TEST_SHA256);
+
+        let (algorithm, _) =
+            parse_checksum(&format!("SHA256-{}", TEST_SHA256.to_uppercase())).unwrap();
+        assert_eq!(algorithm, ChecksumAlgorithm::Sha256);
+
+        let (algorithm, _) = parse_checksum(TEST_SHA256).unwrap();
+        assert_eq!(algorithm, ChecksumAlgorithm::Sha256);
+    }
+
+    #[test]
+    fn test_parse_checksum_rejects_unknown() {
+        assert!(parse_checksum("md5:d41d8cd98f00b204e9800998ecf8427e").is_err());
+        assert!(parse_checksum("sha256:abc").is_err());
+    }
+
+    #[test]
+    fn test_stream_hasher_matches_digest() {
+        let mut hasher = StreamHasher::new(ChecksumAlgorithm::Sha256);
+        hasher.update(b"te");
+        hasher.update(b"st");
+        assert_eq!(hasher.finalize_hex(), TEST_SHA256);
+        assert_eq!(sha256_hex(b"test"), TEST_SHA256);
+    }
+}
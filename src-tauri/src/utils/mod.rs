@@ -1,8 +1,15 @@
+pub mod checksum;
 pub mod manifest;
 pub mod paths;
+pub mod progress;
+pub mod retry;
+pub mod signature;
 pub mod version;
 pub mod zip;
 
 pub use manifest::*;
 pub use paths::*;
 pub use version::*;
+
+#[cfg(test)]
+pub(crate) mod test_server;
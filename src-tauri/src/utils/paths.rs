@@ -119,6 +119,36 @@ pub fn get_database_path() -> Option<PathBuf> {
     get_app_data_path().map(|p| p.join("eso-addon-manager.db"))
 }
 
+/// Get the directory where archives of previous installs are retained
+pub fn get_history_path() -> Option<PathBuf> {
+    get_app_data_path().map(|p| p.join("history"))
+}
+
+/// Get the directory where locally changed addon files are backed up before an update
+pub fn get_patches_path() -> Option<PathBuf> {
+    get_app_data_path().map(|p| p.join("patches"))
+}
+
+/// Get the directory where downloaded archives are cached for reinstalls and offline use
+pub fn get_archive_cache_path() -> Option<PathBuf> {
+    get_app_data_path().map(|p| p.join("cache"))
+}
+
+/// Get the directory where interrupted downloads are kept so they can be resumed
+pub fn get_partial_downloads_path() -> Option<PathBuf> {
+    get_app_data_path().map(|p| p.join("partial"))
+}
+
+/// Get the directory where GitHub API responses are cached
+pub fn get_api_cache_path() -> Option<PathBuf> {
+    get_app_data_path().map(|p| p.join("api_cache"))
+}
+
+/// Get the file holding the key that encrypts stored secrets
+pub fn get_secret_key_path() -> Option<PathBuf> {
+    get_app_data_path().map(|p| p.join("secret.key"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,126 @@
+use crate::models::{DownloadPhase, TransferProgress};
+use std::time::{Duration, Instant};
+
+/// Minimum time between progress events for one transfer
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Weight of the newest sample in the smoothed transfer rate
+const RATE_SMOOTHING: f64 = 0.3;
+
+/// Turns a stream of byte counts into throttled progress reports with rate and ETA
+pub struct ProgressTracker {
+    url: String,
+    total: Option<u64>,
+    interval: Duration,
+    /// Time and byte count of the last report
+    last_sample: (Instant, u64),
+    reported: bool,
+    rate: Option<f64>,
+}
+
+impl ProgressTracker {
+    /// Track a transfer that starts at `downloaded` bytes (non-zero when resuming)
+    pub fn new(url: &str, total: Option<u64>, downloaded: u64) -> Self {
+        Self {
+            url: url.to_string(),
+            total: total.filter(|&t| t > 0),
+            interval: PROGRESS_INTERVAL,
+            last_sample: (Instant::now(), downloaded),
+            reported: false,
+            rate: None,
+        }
+    }
+
+    /// Progress to report after `downloaded` bytes, or None if one was reported too recently
+    /// The first call always reports, so the UI learns about the transfer right away.
+    pub fn update(&mut self, downloaded: u64) -> Option<TransferProgress> {
+        self.update_at(Instant::now(), downloaded)
+    }
+
+    fn update_at(&mut self, now: Instant, downloaded: u64) -> Option<TransferProgress> {
+        let elapsed = now.saturating_duration_since(self.last_sample.0);
+        if self.reported && elapsed < self.interval {
+            return None;
+        }
+
+        // The first interval may include connection setup, so it doesn't count towards the rate
+        if self.reported && !elapsed.is_zero() {
+            let bytes = downloaded.saturating_sub(self.last_sample.1) as f64;
+            let sample = bytes / elapsed.as_secs_f64();
+            self.rate = Some(match self.rate {
+                Some(rate) => rate + RATE_SMOOTHING * (sample - rate),
+                None => sample,
+            });
+        }
+
+        self.last_sample = (now, downloaded);
+        self.reported = true;
+        Some(self.report(downloaded, DownloadPhase::Downloading))
+    }
+
+    /// Progress at `downloaded` bytes in the given phase, regardless of throttling
+    pub fn report(&self, downloaded: u64, phase: DownloadPhase) -> TransferProgress {
+        let eta_secs = match (self.total, self.rate) {
+            (Some(total), Some(rate)) if rate > 0.0 && phase == DownloadPhase::Downloading => {
+                Some((total.saturating_sub(downloaded) as f64 / rate).ceil() as u64)
+            }
+            _ => None,
+        };
+
+        TransferProgress {
+            url: self.url.clone(),
+            source: None,
+            phase,
+            downloaded,
+            total: self.total,
+            bytes_per_second: self.rate,
+            eta_secs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_are_throttled_with_rate_and_eta() {
+        let mut tracker = ProgressTracker::new("https://example.com/a.zip", Some(10_000), 0);
+        let start = tracker.last_sample.0;
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // The first update is reported immediately, without a rate yet
+        let first = tracker.update_at(at(10), 100).unwrap();
+        assert_eq!(first.downloaded, 100);
+        assert_eq!(first.bytes_per_second, None);
+        assert_eq!(first.fraction(), Some(0.01));
+
+        assert!(tracker.update_at(at(100), 500).is_none());
+        assert!(tracker.update_at(at(200), 900).is_none());
+
+        // 1000 bytes in half a second
+        let second = tracker.update_at(at(510), 1_100).unwrap();
+        assert_eq!(second.bytes_per_second, Some(2_000.0));
+        assert_eq!(second.eta_secs, Some(5));
+
+        let done = tracker.report(10_000, DownloadPhase::Verifying);
+        assert_eq!(done.fraction(), Some(1.0));
+        assert_eq!(done.eta_secs, None);
+    }
+
+    #[test]
+    fn test_unknown_size_still_reports_bytes() {
+        let mut tracker = ProgressTracker::new("https://example.com/a.zip", None, 0);
+        let start = tracker.last_sample.0;
+
+        tracker.update_at(start, 0).unwrap();
+        let report = tracker
+            .update_at(start + Duration::from_secs(1), 4_096)
+            .unwrap();
+        assert_eq!(report.downloaded, 4_096);
+        assert_eq!(report.total, None);
+        assert_eq!(report.fraction(), None);
+        assert_eq!(report.bytes_per_second, Some(4_096.0));
+        assert_eq!(report.eta_secs, None);
+    }
+}
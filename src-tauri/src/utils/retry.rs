@@ -0,0 +1,124 @@
+use crate::error::AppError;
+use std::collections::hash_map::RandomState;
+use std::error::Error as _;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// How often and how long to wait before retrying a transient failure
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts per URL, including the first
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (0 for the first retry)
+    /// Exponential backoff with jitter: a random point in the upper half of the window,
+    /// so clients that failed together don't retry together.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let window = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        let half = window / 2;
+        let jitter = random_u64() % (half.as_millis() as u64 + 1);
+        half + Duration::from_millis(jitter)
+    }
+}
+
+/// A random number from the standard library's per-process hash keys
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Whether a failed request is worth retrying
+/// Server errors, rate limiting, timeouts and dropped connections are transient;
+/// missing files, integrity failures and local errors are not.
+pub fn is_transient(error: &AppError) -> bool {
+    match error {
+        AppError::HttpStatus(status, _) => is_transient_status(*status),
+        AppError::Network(e) => is_transient_network(e),
+        _ => false,
+    }
+}
+
+/// HTTP statuses that may succeed on a later attempt
+pub fn is_transient_status(status: u16) -> bool {
+    matches!(status, 408 | 425 | 429) || (500..600).contains(&status)
+}
+
+fn is_transient_network(error: &reqwest::Error) -> bool {
+    if error.is_timeout() {
+        return true;
+    }
+
+    // Connection-level I/O errors anywhere in the chain (resets, refusals, cut-off bodies)
+    let mut source = error.source();
+    while let Some(e) = source {
+        if let Some(io) = e.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io.kind(),
+                ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::ConnectionRefused
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::UnexpectedEof
+                    | ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+            );
+        }
+        source = e.source();
+    }
+
+    // A body that ended early means the connection dropped mid-transfer
+    error.is_body()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(800),
+        };
+
+        for _ in 0..20 {
+            let first = policy.delay(0);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+            let third = policy.delay(2);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+
+            let capped = policy.delay(10);
+            assert!(capped >= Duration::from_millis(400) && capped <= Duration::from_millis(800));
+        }
+    }
+
+    #[test]
+    fn test_status_classification() {
+        for status in [408, 429, 500, 502, 503] {
+            assert!(is_transient(&AppError::HttpStatus(status, String::new())));
+        }
+        for status in [400, 403, 404, 410] {
+            assert!(!is_transient(&AppError::HttpStatus(status, String::new())));
+        }
+        assert!(!is_transient(&AppError::Integrity("mismatch".into())));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::error::{AppError, Result};
+use minisign_verify::{PublicKey, Signature};
+
+/// Build the URL of the detached minisign signature published next to a file
+pub fn signature_url(url: &str) -> String {
+    format!("{}.minisig", url)
+}
+
+/// Check that a minisign public key (base64, as printed by `minisign -G`) is well formed
+pub fn validate_public_key(public_key: &str) -> Result<()> {
+    PublicKey::from_base64(public_key.trim())
+        .map(|_| ())
+        .map_err(|e| AppError::SignatureInvalid(format!("Invalid public key: {}", e)))
+}
+
+/// Verify a detached minisign signature over `data`
+pub fn verify_signature(data: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let public_key = PublicKey::from_base64(public_key.trim())
+        .map_err(|e| AppError::SignatureInvalid(format!("Invalid public key: {}", e)))?;
+    let signature = Signature::decode(signature.trim())
+        .map_err(|e| AppError::SignatureInvalid(format!("Invalid signature file: {}", e)))?;
+
+    public_key
+        .verify(data, &signature, false)
+        .map_err(|e| AppError::SignatureInvalid(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vector from minisign: prehashed signature of the bytes "test"
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+
+    #[test]
+    fn test_verify_signature_accepts_valid_data() {
+        assert!(verify_signature(b"test", SIGNATURE, PUBLIC_KEY).is_ok());
+    }
+
+    #[test]
+    fn test_verify_signature_rejects_tampered_data() {
+        let result = verify_signature(b"tampered", SIGNATURE, PUBLIC_KEY);
+        assert!(matches!(result, Err(AppError::SignatureInvalid(_))));
+    }
+
+    #[test]
+    fn test_validate_public_key() {
+        assert!(validate_public_key(PUBLIC_KEY).is_ok());
+        assert!(validate_public_key("not-a-key").is_err());
+    }
+}
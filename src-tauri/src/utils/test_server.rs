@@ -0,0 +1,171 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// A canned HTTP response served by `TestServer`
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    content_length: bool,
+    /// Send only this many bytes of the body
+    cut_after: Option<usize>,
+    /// Keep the connection open after sending instead of closing it
+    stall: bool,
+}
+
+impl Response {
+    /// A response with the given status line (e.g. "404 Not Found") and no body
+    pub fn status(status: &str) -> Self {
+        Self {
+            status: status.to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            content_length: true,
+            cut_after: None,
+            stall: false,
+        }
+    }
+
+    /// A 200 response with a body
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self::status("200 OK").body(body)
+    }
+
+    pub fn not_found() -> Self {
+        Self::status("404 Not Found")
+    }
+
+    pub fn header(mut self, name: &str, value: impl ToString) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Leave out Content-Length, so the body ends when the connection closes
+    pub fn without_content_length(mut self) -> Self {
+        self.content_length = false;
+        self
+    }
+
+    /// Drop the connection after sending `bytes` bytes of the body
+    pub fn cut_after(mut self, bytes: usize) -> Self {
+        self.cut_after = Some(bytes);
+        self
+    }
+
+    /// Send what there is to send, then hang instead of finishing the response
+    pub fn stalled(mut self) -> Self {
+        self.stall = true;
+        self
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {}\r\n", self.status);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if self.content_length {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        if !self.stall {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+
+        let sent = self
+            .cut_after
+            .unwrap_or(self.body.len())
+            .min(self.body.len());
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(&self.body[..sent]);
+        bytes
+    }
+}
+
+/// Local HTTP server for tests that records every request it receives
+///
+/// Each connection carries one request, whose raw text (request line and headers)
+/// is recorded before it is answered.
+pub struct TestServer {
+    base_url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    /// Answer requests with `responses` in order, repeating the last one after that
+    pub async fn canned(responses: Vec<Response>) -> Self {
+        assert!(!responses.is_empty(), "a test server needs a response");
+        Self::spawn(move |_, index| responses[index.min(responses.len() - 1)].clone()).await
+    }
+
+    /// Answer each request with the response `respond` builds from the raw request
+    /// and the number of requests received before it
+    pub async fn spawn<F>(respond: F) -> Self
+    where
+        F: Fn(&str, usize) -> Response + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let log = requests.clone();
+        let respond = Arc::new(respond);
+        let received = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let log = log.clone();
+                let respond = respond.clone();
+                let received = received.clone();
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                    log.lock().unwrap().push(request.clone());
+
+                    let response = respond(&request, received.fetch_add(1, Ordering::SeqCst));
+                    let _ = socket.write_all(&response.to_bytes()).await;
+                    if response.stall {
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                    }
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        Self { base_url, requests }
+    }
+
+    /// URL of `path` on this server
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Raw text of every request received so far
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Path of a raw request, from its request line
+pub fn request_path(request: &str) -> &str {
+    request.split_whitespace().nth(1).unwrap_or("/")
+}
+
+/// Value of a header in a raw request, matching its name case-insensitively
+pub fn request_header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request.lines().find_map(|line| {
+        let (header, value) = line.split_once(':')?;
+        header
+            .trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
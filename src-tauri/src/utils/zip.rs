@@ -1,9 +1,40 @@
-use crate::error::Result;
+use crate::error::{AppError, Result};
 use crate::models::InstallInfo;
 use std::fs::{self, File};
-use std::io;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
+/// Unix file type bits of an entry's mode
+const S_IFMT: u32 = 0o170000;
+const S_IFREG: u32 = 0o100000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Entries smaller than this are exempt from the compression ratio check,
+/// since small text files legitimately compress very well
+const RATIO_CHECK_MIN_SIZE: u64 = 1024 * 1024;
+
+/// Limits applied while extracting an archive to guard against zip bombs
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractionLimits {
+    /// Maximum total uncompressed size of all extracted files, in bytes
+    pub max_total_size: u64,
+    /// Maximum number of entries in the archive
+    pub max_entries: usize,
+    /// Maximum ratio of uncompressed to compressed size for a single entry
+    pub max_compression_ratio: u64,
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        Self {
+            max_total_size: 1024 * 1024 * 1024,
+            max_entries: 50_000,
+            max_compression_ratio: 200,
+        }
+    }
+}
+
 /// Extract a ZIP archive to the target directory
 pub fn extract_archive(archive_path: &Path, target_dir: &Path) -> Result<Vec<String>> {
     extract_archive_with_options(archive_path, target_dir, None)
@@ -15,10 +46,42 @@ pub fn extract_archive_with_options(
     target_dir: &Path,
     install_info: Option<&InstallInfo>,
 ) -> Result<Vec<String>> {
+    extract_archive_with_limits(
+        archive_path,
+        target_dir,
+        install_info,
+        &ExtractionLimits::default(),
+    )
+}
+
+/// Extract a ZIP archive with install options, enforcing the given extraction limits
+///
+/// Symlinks and special files (devices, FIFOs, sockets) are refused and file
+/// permissions are clamped to 0644/0755 regardless of the mode stored in the archive.
+pub fn extract_archive_with_limits(
+    archive_path: &Path,
+    target_dir: &Path,
+    install_info: Option<&InstallInfo>,
+    limits: &ExtractionLimits,
+) -> Result<Vec<String>> {
+    if let Some(info) = install_info {
+        validate_install_info(info)?;
+    }
+
     let file = File::open(archive_path)?;
     let mut archive = zip::ZipArchive::new(file)?;
     let mut extracted_paths = Vec::new();
 
+    if archive.len() > limits.max_entries {
+        return Err(AppError::UnsafeArchive(format!(
+            "archive has {} entries (limit {})",
+            archive.len(),
+            limits.max_entries
+        )));
+    }
+
+    let mut total_size: u64 = 0;
+
     // Get exclude patterns and extract path from install info
     let empty_excludes = Vec::new();
     let excludes = install_info.map(|i| &i.excludes).unwrap_or(&empty_excludes);
@@ -75,6 +138,8 @@ pub fn extract_archive_with_options(
             continue;
         }
 
+        check_entry_type(&adjusted_path, file.unix_mode())?;
+
         let outpath = target_dir.join(&adjusted_path);
 
         if file.is_dir() {
@@ -82,20 +147,34 @@ pub fn extract_archive_with_options(
                 fs::create_dir_all(&outpath)?;
             }
         } else {
+            check_compression_ratio(&adjusted_path, file.size(), file.compressed_size(), limits)?;
+
+            let remaining = limits.max_total_size.saturating_sub(total_size);
+            if file.size() > remaining {
+                return Err(total_size_exceeded(limits));
+            }
+
             if let Some(parent) = outpath.parent() {
                 if !parent.exists() {
                     fs::create_dir_all(parent)?;
                 }
             }
             let mut outfile = File::create(&outpath)?;
-            io::copy(&mut file, &mut outfile)?;
+
+            // Never trust the declared size: stop as soon as the budget is exceeded
+            let written = io::copy(&mut (&mut file).take(remaining + 1), &mut outfile)?;
+            if written > remaining {
+                return Err(total_size_exceeded(limits));
+            }
+            total_size += written;
         }
 
-        // Set permissions on Unix
+        // Set permissions on Unix, clamped to sane defaults
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
             if let Some(mode) = file.unix_mode() {
+                let mode = clamp_mode(mode, file.is_dir());
                 fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
             }
         }
@@ -108,6 +187,144 @@ pub fn extract_archive_with_options(
     Ok(extracted_paths)
 }
 
+/// Pack the files of a directory into a ZIP archive under a single `root_folder`
+///
+/// The result is laid out like a GitHub source archive, so it can be extracted with
+/// the same install options. Returns the number of files packed.
+pub fn create_archive(source_dir: &Path, root_folder: &str, archive_path: &Path) -> Result<usize> {
+    let mut files = Vec::new();
+    collect_files(source_dir, &mut files)?;
+    files.sort();
+
+    let mut writer = zip::ZipWriter::new(File::create(archive_path)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    for path in &files {
+        let relative = path.strip_prefix(source_dir).unwrap_or(path);
+        let name: Vec<_> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        writer.start_file(format!("{}/{}", root_folder, name.join("/")), options)?;
+        io::copy(&mut File::open(path)?, &mut writer)?;
+    }
+
+    writer.finish()?;
+    Ok(files.len())
+}
+
+/// Recursively list the regular files below a directory
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Refuse symlinks and special files; only regular files and directories are extracted
+fn check_entry_type(path: &Path, unix_mode: Option<u32>) -> Result<()> {
+    let Some(mode) = unix_mode else {
+        return Ok(());
+    };
+
+    match mode & S_IFMT {
+        0 | S_IFREG | S_IFDIR => Ok(()),
+        S_IFLNK => Err(AppError::UnsafeArchive(format!(
+            "symlink entries are not allowed: {}",
+            path.display()
+        ))),
+        _ => Err(AppError::UnsafeArchive(format!(
+            "special file entries are not allowed: {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Refuse entries whose declared sizes imply an implausible compression ratio
+fn check_compression_ratio(
+    path: &Path,
+    size: u64,
+    compressed_size: u64,
+    limits: &ExtractionLimits,
+) -> Result<()> {
+    if size < RATIO_CHECK_MIN_SIZE {
+        return Ok(());
+    }
+
+    let ratio = size / compressed_size.max(1);
+    if ratio > limits.max_compression_ratio {
+        return Err(AppError::UnsafeArchive(format!(
+            "{} has a compression ratio of {}:1 (limit {}:1)",
+            path.display(),
+            ratio,
+            limits.max_compression_ratio
+        )));
+    }
+
+    Ok(())
+}
+
+fn total_size_exceeded(limits: &ExtractionLimits) -> AppError {
+    AppError::UnsafeArchive(format!(
+        "uncompressed size exceeds the limit of {} bytes",
+        limits.max_total_size
+    ))
+}
+
+/// Clamp an archive entry's mode to 0755 for directories and executables, 0644 otherwise
+#[cfg_attr(not(unix), allow(dead_code))]
+fn clamp_mode(mode: u32, is_dir: bool) -> u32 {
+    if is_dir || mode & 0o111 != 0 {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+/// Validate that the index-supplied install paths cannot escape the AddOns directory
+///
+/// `target_folder` must be a single folder name and `extract_path` a relative path
+/// without `.` or `..` components.
+pub fn validate_install_info(info: &InstallInfo) -> Result<()> {
+    let target = info.target_folder.as_str();
+    if !is_safe_relative_path(target) || target.contains(['/', '\\']) {
+        return Err(AppError::UnsafeArchive(format!(
+            "invalid target folder: {:?}",
+            info.target_folder
+        )));
+    }
+
+    if let Some(ref extract_path) = info.extract_path {
+        if !is_safe_relative_path(extract_path) {
+            return Err(AppError::UnsafeArchive(format!(
+                "invalid extract path: {:?}",
+                extract_path
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that a path from the index is relative and made only of plain names
+/// Both separators are checked so a Windows-style path is rejected on every platform.
+pub(crate) fn is_safe_relative_path(path: &str) -> bool {
+    if path.is_empty() || path.starts_with(['/', '\\']) || path.contains(':') {
+        return false;
+    }
+
+    path.split(['/', '\\'])
+        .filter(|segment| !segment.is_empty())
+        .all(|segment| segment != "." && segment != "..")
+}
+
 /// Check if a path should be excluded based on glob patterns
 fn should_exclude(path: &Path, excludes: &[String]) -> bool {
     for component in path.components() {
@@ -151,40 +368,45 @@ fn matches_glob_pattern(name: &str, pattern: &str) -> bool {
     false
 }
 
-/// Find the root addon directory inside an extracted archive
-/// Some archives have the addon in a subdirectory (up to 2 levels deep)
-pub fn find_addon_root(extracted_dir: &Path) -> Option<std::path::PathBuf> {
-    // First, check if there's a manifest directly in the extracted dir
+/// Find every addon root directory inside an extracted archive
+///
+/// An archive with a manifest at its top level is a single addon. Otherwise each
+/// first-level folder with a manifest is an addon, and folders without one (such as
+/// a "repo-main/" wrapper) are searched one level deeper, so archives bundling
+/// several addons return all of them. Example directories are skipped and the
+/// result is sorted by path.
+pub fn find_addon_roots(extracted_dir: &Path) -> Vec<PathBuf> {
     if has_manifest(extracted_dir) {
-        return Some(extracted_dir.to_path_buf());
+        return vec![extracted_dir.to_path_buf()];
     }
 
-    // Check first-level subdirectories
-    if let Ok(entries) = fs::read_dir(extracted_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() && !is_example_dir(&path) {
-                if has_manifest(&path) {
-                    return Some(path);
-                }
-                // Check second-level subdirectories (for repos like LibAddonMenu)
-                // Skip example directories
-                if let Ok(sub_entries) = fs::read_dir(&path) {
-                    for sub_entry in sub_entries.flatten() {
-                        let sub_path = sub_entry.path();
-                        if sub_path.is_dir()
-                            && !is_example_dir(&sub_path)
-                            && has_manifest(&sub_path)
-                        {
-                            return Some(sub_path);
-                        }
-                    }
-                }
-            }
+    let mut roots = Vec::new();
+
+    for path in sub_dirs(extracted_dir) {
+        if has_manifest(&path) {
+            roots.push(path);
+            continue;
         }
+
+        // Check second-level subdirectories (for repos like LibAddonMenu)
+        roots.extend(sub_dirs(&path).into_iter().filter(|p| has_manifest(p)));
     }
 
-    None
+    roots.sort();
+    roots
+}
+
+/// List the non-example subdirectories of a directory
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir() && !is_example_dir(path))
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 /// Check if a directory looks like an example/test addon that should be skipped
@@ -219,3 +441,216 @@ fn has_manifest(dir: &Path) -> bool {
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    /// Write an archive whose entries are added by `build`, returning its path
+    fn build_archive(dir: &Path, build: impl FnOnce(&mut ZipWriter<File>)) -> PathBuf {
+        let path = dir.join("archive.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        build(&mut writer);
+        writer.finish().unwrap();
+        path
+    }
+
+    fn stored() -> SimpleFileOptions {
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored)
+    }
+
+    fn install_info(target_folder: &str, extract_path: Option<&str>) -> InstallInfo {
+        InstallInfo {
+            method: "release".to_string(),
+            extract_path: extract_path.map(String::from),
+            target_folder: target_folder.to_string(),
+            excludes: vec![],
+        }
+    }
+
+    fn assert_unsafe(result: Result<Vec<String>>) {
+        match result {
+            Err(AppError::UnsafeArchive(_)) => {}
+            other => panic!("expected unsafe archive error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_too_many_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = build_archive(tmp.path(), |w| {
+            for i in 0..3 {
+                w.start_file(format!("root/file{}.lua", i), stored())
+                    .unwrap();
+                w.write_all(b"x").unwrap();
+            }
+        });
+        let limits = ExtractionLimits {
+            max_entries: 2,
+            ..Default::default()
+        };
+
+        let result = extract_archive_with_limits(&archive, &tmp.path().join("out"), None, &limits);
+        assert_unsafe(result);
+    }
+
+    #[test]
+    fn test_rejects_total_size_over_limit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = build_archive(tmp.path(), |w| {
+            w.start_file("root/a.lua", stored()).unwrap();
+            w.write_all(&[b'a'; 16]).unwrap();
+            w.start_file("root/b.lua", stored()).unwrap();
+            w.write_all(&[b'b'; 16]).unwrap();
+        });
+        let limits = ExtractionLimits {
+            max_total_size: 24,
+            ..Default::default()
+        };
+
+        let result = extract_archive_with_limits(&archive, &tmp.path().join("out"), None, &limits);
+        assert_unsafe(result);
+    }
+
+    #[test]
+    fn test_rejects_high_compression_ratio() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = build_archive(tmp.path(), |w| {
+            let options =
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            w.start_file("root/bomb.lua", options).unwrap();
+            w.write_all(&vec![0u8; 4 * 1024 * 1024]).unwrap();
+        });
+
+        let result = extract_archive(&archive, &tmp.path().join("out"));
+        assert_unsafe(result);
+        assert!(!tmp.path().join("out/bomb.lua").exists());
+    }
+
+    #[test]
+    fn test_rejects_symlink_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = build_archive(tmp.path(), |w| {
+            w.add_symlink("root/passwd", "/etc/passwd", stored())
+                .unwrap();
+        });
+
+        let result = extract_archive(&archive, &tmp.path().join("out"));
+        assert_unsafe(result);
+        assert!(fs::symlink_metadata(tmp.path().join("out/passwd")).is_err());
+    }
+
+    #[test]
+    fn test_rejects_special_files() {
+        let path = Path::new("dev");
+        assert!(check_entry_type(path, Some(0o020644)).is_err()); // character device
+        assert!(check_entry_type(path, Some(0o060644)).is_err()); // block device
+        assert!(check_entry_type(path, Some(0o010644)).is_err()); // FIFO
+        assert!(check_entry_type(path, Some(0o100644)).is_ok());
+        assert!(check_entry_type(path, Some(0o040755)).is_ok());
+        assert!(check_entry_type(path, None).is_ok());
+    }
+
+    #[test]
+    fn test_skips_path_traversal_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = build_archive(tmp.path(), |w| {
+            w.start_file("root/../../evil.lua", stored()).unwrap();
+            w.write_all(b"evil").unwrap();
+            w.start_file("/abs/evil.lua", stored()).unwrap();
+            w.write_all(b"evil").unwrap();
+            w.start_file("root/Addon.lua", stored()).unwrap();
+            w.write_all(b"ok").unwrap();
+        });
+        let out = tmp.path().join("a/b/out");
+
+        extract_archive(&archive, &out).unwrap();
+
+        assert!(out.join("Addon.lua").exists());
+        assert!(!tmp.path().join("a/evil.lua").exists());
+        assert!(!tmp.path().join("evil.lua").exists());
+        assert!(!out.join("evil.lua").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clamps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = build_archive(tmp.path(), |w| {
+            w.start_file("root/open.lua", stored().unix_permissions(0o777))
+                .unwrap();
+            w.write_all(b"x").unwrap();
+            w.start_file("root/private.lua", stored().unix_permissions(0o600))
+                .unwrap();
+            w.write_all(b"x").unwrap();
+        });
+        let out = tmp.path().join("out");
+
+        extract_archive(&archive, &out).unwrap();
+
+        let mode = |name: &str| fs::metadata(out.join(name)).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode("open.lua"), 0o755);
+        assert_eq!(mode("private.lua"), 0o644);
+        assert_eq!(clamp_mode(0o104777, false), 0o755);
+        assert_eq!(clamp_mode(0o700, true), 0o755);
+    }
+
+    #[test]
+    fn test_validate_install_info() {
+        assert!(validate_install_info(&install_info("MyAddon", None)).is_ok());
+        assert!(validate_install_info(&install_info("MyAddon", Some("src/MyAddon/"))).is_ok());
+
+        for target in [
+            "",
+            ".",
+            "..",
+            "../MyAddon",
+            "/MyAddon",
+            "Libs/MyAddon",
+            "..\\MyAddon",
+            "C:MyAddon",
+        ] {
+            assert!(
+                validate_install_info(&install_info(target, None)).is_err(),
+                "target folder {:?} should be rejected",
+                target
+            );
+        }
+
+        for extract_path in [
+            "../src",
+            "src/../../x",
+            "/src",
+            "\\src",
+            "C:\\src",
+            "src/./x",
+        ] {
+            assert!(
+                validate_install_info(&install_info("MyAddon", Some(extract_path))).is_err(),
+                "extract path {:?} should be rejected",
+                extract_path
+            );
+        }
+    }
+
+    #[test]
+    fn test_extraction_rejects_unsafe_install_info() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = build_archive(tmp.path(), |w| {
+            w.start_file("root/Addon.lua", stored()).unwrap();
+            w.write_all(b"ok").unwrap();
+        });
+
+        let result = extract_archive_with_options(
+            &archive,
+            &tmp.path().join("out"),
+            Some(&install_info("MyAddon", Some("../.."))),
+        );
+        assert_unsafe(result);
+    }
+}
@@ -323,7 +323,7 @@ function GitHubView() {
 }
 
 function UpdatesView() {
-  const { updates, checkUpdates } = useAddonStore();
+  const { updates, checkUpdates, updateAddon } = useAddonStore();
 
   useEffect(() => {
     checkUpdates();
@@ -354,9 +354,13 @@ function UpdatesView() {
                       <h3 className="font-semibold text-gray-100">{update.name}</h3>
                       <p className="text-sm text-gray-400">
                         {update.currentVersion} → {update.newVersion}
+                        {update.commitRange &&
+                          ` (${update.commitRange.totalCommits} new commits)`}
                       </p>
                     </div>
-                    <Button size="sm">Update</Button>
+                    <Button size="sm" onClick={() => updateAddon(update)}>
+                      Update
+                    </Button>
                   </div>
                 </div>
               ))}
@@ -1,7 +1,27 @@
 import { invoke } from '@tauri-apps/api/core';
-import type { InstalledAddon, UpdateInfo, ScannedAddon, VersionTracking, DependencyResult } from '../types/addon';
-import type { AddonIndex, IndexStats, InstallInfo, DownloadSource } from '../types/index';
-import type { CustomRepo, GitHubRepoInfo, GitHubBranchInfo, GitHubReleaseInfo, RepoPreview } from '../types/github';
+import type {
+  InstalledAddon,
+  UpdateInfo,
+  ScannedAddon,
+  VersionTracking,
+  DependencyResult,
+  InstallHistoryEntry,
+  InstallPlan,
+  VerifyResult,
+  InstallRequest,
+  DownloadJob,
+  CacheStats,
+  DownloadPriority,
+} from '../types/addon';
+import type { AddonIndex, IndexStats, InstallInfo, DownloadSource, IndexSource } from '../types/index';
+import type {
+  CustomRepo,
+  GitHubRepoInfo,
+  GitHubBranchInfo,
+  GitHubReleaseInfo,
+  RepoPreview,
+  GitHubBudget,
+} from '../types/github';
 import type { AppSettings } from '../types/settings';
 
 // ============================================================================
@@ -21,7 +41,10 @@ export async function installAddon(
   sourceRepo?: string,
   installInfo?: InstallInfo,
   versionTracking?: VersionTracking,
-  downloadSources?: DownloadSource[]
+  downloadSources?: DownloadSource[],
+  overwriteLocalChanges?: boolean,
+  backupLocalChanges?: boolean,
+  priority?: DownloadPriority
 ): Promise<InstalledAddon> {
   return invoke('install_addon', {
     slug,
@@ -33,11 +56,14 @@ export async function installAddon(
     installInfo,
     versionTracking,
     downloadSources,
+    overwriteLocalChanges,
+    backupLocalChanges,
+    priority,
   });
 }
 
-export async function uninstallAddon(slug: string): Promise<void> {
-  return invoke('uninstall_addon', { slug });
+export async function uninstallAddon(slug: string, keepUserFiles?: boolean): Promise<void> {
+  return invoke('uninstall_addon', { slug, keepUserFiles });
 }
 
 export async function scanLocalAddons(): Promise<ScannedAddon[]> {
@@ -60,6 +86,31 @@ export async function resolveAddonDependencies(slug: string): Promise<Dependency
   return invoke('resolve_addon_dependencies', { slug });
 }
 
+export async function verifyAddon(slug: string): Promise<VerifyResult> {
+  return invoke('verify_addon', { slug });
+}
+
+export async function verifyAll(): Promise<VerifyResult[]> {
+  return invoke('verify_all');
+}
+
+export async function backupAddonChanges(slug: string): Promise<string | null> {
+  return invoke('backup_addon_changes', { slug });
+}
+
+export async function getAddonHistory(slug: string): Promise<InstallHistoryEntry[]> {
+  return invoke('get_addon_history', { slug });
+}
+
+export async function rollbackAddon(
+  slug: string,
+  version: string,
+  overwriteLocalChanges?: boolean,
+  backupLocalChanges?: boolean
+): Promise<InstalledAddon> {
+  return invoke('rollback_addon', { slug, version, overwriteLocalChanges, backupLocalChanges });
+}
+
 // ============================================================================
 // GitHub Commands
 // ============================================================================
@@ -87,9 +138,25 @@ export async function getGitHubRepoInfo(repo: string): Promise<GitHubRepoInfo> {
 export async function installFromGitHub(
   repo: string,
   releaseType?: string,
-  branch?: string
+  branch?: string,
+  overwriteLocalChanges?: boolean,
+  backupLocalChanges?: boolean
 ): Promise<InstalledAddon> {
-  return invoke('install_from_github', { repo, releaseType, branch });
+  return invoke('install_from_github', {
+    repo,
+    releaseType,
+    branch,
+    overwriteLocalChanges,
+    backupLocalChanges,
+  });
+}
+
+export async function previewGitHubInstall(
+  repo: string,
+  releaseType?: string,
+  branch?: string
+): Promise<InstallPlan> {
+  return invoke('preview_github_install', { repo, releaseType, branch });
 }
 
 export async function getGitHubRelease(repo: string): Promise<GitHubReleaseInfo | null> {
@@ -104,6 +171,20 @@ export async function listGitHubBranches(repo: string): Promise<GitHubBranchInfo
   return invoke('list_github_branches', { repo });
 }
 
+/** Store a GitHub personal access token (empty to remove it); returns the token's login */
+export async function setGitHubToken(token?: string): Promise<string | null> {
+  return invoke('set_github_token', { token });
+}
+
+export async function hasGitHubToken(): Promise<boolean> {
+  return invoke('has_github_token');
+}
+
+/** GitHub API rate limit budget; `refresh` asks GitHub instead of using the last response */
+export async function getGitHubRateLimit(refresh?: boolean): Promise<GitHubBudget> {
+  return invoke('get_github_rate_limit', { refresh });
+}
+
 // Re-export types for convenience
 export type { GitHubReleaseInfo, GitHubBranchInfo, RepoPreview };
 
@@ -123,6 +204,67 @@ export async function getIndexStats(): Promise<IndexStats> {
   return invoke('get_index_stats');
 }
 
+export async function getIndexSources(): Promise<IndexSource[]> {
+  return invoke('get_index_sources');
+}
+
+export async function addIndexSource(
+  url: string,
+  name?: string,
+  publicKey?: string
+): Promise<IndexSource> {
+  return invoke('add_index_source', { url, name, publicKey });
+}
+
+export async function setIndexSourceKey(id: number, publicKey?: string): Promise<IndexSource> {
+  return invoke('set_index_source_key', { id, publicKey });
+}
+
+export async function removeIndexSource(id: number): Promise<void> {
+  return invoke('remove_index_source', { id });
+}
+
+export async function reorderIndexSources(ids: number[]): Promise<IndexSource[]> {
+  return invoke('reorder_index_sources', { ids });
+}
+
+// ============================================================================
+// Download Queue Commands
+// ============================================================================
+
+export async function queueInstall(request: InstallRequest): Promise<DownloadJob> {
+  return invoke('queue_install', { request });
+}
+
+export async function getDownloads(): Promise<DownloadJob[]> {
+  return invoke('get_downloads');
+}
+
+export async function cancelDownload(id: number): Promise<DownloadJob> {
+  return invoke('cancel_download', { id });
+}
+
+export async function cancelOperation(id: number): Promise<DownloadJob> {
+  return invoke('cancel_operation', { id });
+}
+
+export async function retryDownload(id: number): Promise<DownloadJob> {
+  return invoke('retry_download', { id });
+}
+
+export async function clearDownloads(): Promise<number> {
+  return invoke('clear_downloads');
+}
+
+export async function getCacheStats(): Promise<CacheStats> {
+  return invoke('get_cache_stats');
+}
+
+/** Delete every cached archive, returning the number of bytes freed */
+export async function clearCache(): Promise<number> {
+  return invoke('clear_cache');
+}
+
 // ============================================================================
 // Settings Commands
 // ============================================================================
@@ -15,6 +15,7 @@ interface AddonStore {
   // Actions
   fetchInstalled: () => Promise<void>;
   installAddon: (slug: string, name: string, version: string, downloadUrl: string, installInfo?: InstallInfo, versionTracking?: VersionTracking, downloadSources?: DownloadSource[]) => Promise<void>;
+  updateAddon: (update: UpdateInfo) => Promise<void>;
   uninstallAddon: (slug: string) => Promise<void>;
   checkUpdates: () => Promise<void>;
   scanLocalAddons: () => Promise<void>;
@@ -70,6 +71,29 @@ export const useAddonStore = create<AddonStore>((set, get) => ({
     }
   },
 
+  updateAddon: async (update) => {
+    try {
+      await api.installAddon(
+        update.slug,
+        update.name,
+        update.newVersion,
+        update.downloadUrl,
+        update.sourceType,
+        update.sourceRepo,
+        update.installInfo,
+        // Record the new version so the next check compares against it
+        update.versionTracking,
+        update.downloadSources
+      );
+      set((state) => ({
+        updates: state.updates.filter((u) => u.slug !== update.slug),
+      }));
+      await get().fetchInstalled();
+    } catch (e) {
+      set({ error: String(e) });
+    }
+  },
+
   uninstallAddon: async (slug) => {
     try {
       await api.uninstallAddon(slug);
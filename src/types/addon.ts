@@ -14,6 +14,56 @@ export interface InstalledAddon {
   versionSortKey?: number;
   /** Commit SHA for branch-based version tracking */
   commitSha?: string;
+  /** Slug of the addon this folder was installed with (bundled addons only) */
+  parentSlug?: string;
+}
+
+/** An addon folder found in an archive */
+export interface PlannedAddon {
+  /** Folder the addon is installed into (named after its manifest file) */
+  folderName: string;
+  title: string;
+  version?: string;
+  /** The archive's main addon; the others are installed as linked addons */
+  primary: boolean;
+  /** A folder with this name already exists and will be replaced */
+  replacesExisting: boolean;
+}
+
+/** The addon folders an archive will install, main addon first */
+export interface InstallPlan {
+  addons: PlannedAddon[];
+}
+
+/** Comparison of an installed addon's files on disk with its recorded inventory */
+export interface VerifyResult {
+  slug: string;
+  name: string;
+  /** False when no inventory was recorded (e.g. installed before tracking or auto-imported) */
+  hasInventory: boolean;
+  /** Files on disk that were not part of the install */
+  added: string[];
+  /** Installed files missing from disk */
+  removed: string[];
+  /** Installed files whose contents changed */
+  modified: string[];
+}
+
+/** A previous install retained for rollback */
+export interface InstallHistoryEntry {
+  id: number;
+  slug: string;
+  name: string;
+  version: string;
+  sourceType: 'index' | 'github' | 'local';
+  sourceRepo?: string;
+  versionSortKey?: number;
+  commitSha?: string;
+  /** Installation info used for this install (index addons only) */
+  installInfo?: import('./index').InstallInfo;
+  /** Path to the retained archive in the app data directory */
+  archivePath: string;
+  installedAt: string;
 }
 
 /** Version tracking info for simplified update detection */
@@ -49,14 +99,96 @@ export interface UpdateInfo {
   installInfo?: import('./index').InstallInfo;
   /** Multiple download sources with fallback (jsDelivr CDN -> GitHub archive) */
   downloadSources?: import('./index').DownloadSource[];
+  /** Installed files were edited or added locally and would be lost by updating */
+  hasLocalChanges: boolean;
+  /** New commits for GitHub addons tracking a branch */
+  commitRange?: CommitRange;
+  /** Version tracking of the new version, to pass unchanged with the install */
+  versionTracking?: VersionTracking;
+}
+
+/** Commits between the installed and the latest commit of a branch */
+export interface CommitRange {
+  fromSha: string;
+  toSha: string;
+  /** Number of commits in the range, which can be more than are listed */
+  totalCommits: number;
+  /** Oldest first */
+  commits: CommitSummary[];
+}
+
+/** A commit listed in a CommitRange */
+export interface CommitSummary {
+  sha: string;
+  message: string;
+  author?: string;
+  date?: string;
 }
 
 /** Download progress event */
 export interface DownloadProgress {
   slug: string;
-  status: 'pending' | 'downloading' | 'extracting' | 'complete' | 'failed';
+  status: 'pending' | 'downloading' | 'extracting' | 'complete' | 'failed' | 'cancelled';
+  progress: number;
+  error?: string;
+  /** Id of the install or update, for cancelOperation */
+  operationId?: number;
+  phase?: DownloadPhase;
+  bytesDownloaded?: number;
+  /** Missing when the server doesn't report the size */
+  totalBytes?: number;
+  bytesPerSecond?: number;
+  etaSecs?: number;
+  /** Type of the download source in use (e.g. "github_archive") */
+  source?: string;
+  url?: string;
+}
+
+export type DownloadPhase = 'resolving' | 'downloading' | 'verifying' | 'extracting' | 'installing';
+
+/** Install request stored with a queued download */
+export interface InstallRequest {
+  slug: string;
+  name: string;
+  version: string;
+  downloadUrl: string;
+  sourceType?: string;
+  sourceRepo?: string;
+  installInfo?: import('./index').InstallInfo;
+  versionTracking?: VersionTracking;
+  downloadSources?: import('./index').DownloadSource[];
+  overwriteLocalChanges?: boolean;
+  backupLocalChanges?: boolean;
+  /** Background downloads (auto and bulk updates) use their own bandwidth limits */
+  priority?: DownloadPriority;
+  /** Retained archive installed instead of downloading, for rollbacks */
+  archivePath?: string;
+}
+
+export type DownloadPriority = 'user' | 'background';
+
+/** A queued install or update */
+export interface DownloadJob {
+  id: number;
+  slug: string;
+  name: string;
+  version: string;
+  status: DownloadProgress['status'];
   progress: number;
   error?: string;
+  createdAt?: string;
+  startedAt?: string;
+  completedAt?: string;
+}
+
+/** Size and contents of the archive cache */
+export interface CacheStats {
+  entries: number;
+  /** Distinct archives stored; mirrors of the same file share one */
+  archives: number;
+  totalBytes: number;
+  maxBytes: number;
+  path: string;
 }
 
 /** Locally scanned addon info */
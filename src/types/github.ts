@@ -43,3 +43,19 @@ export interface RepoPreview {
   latestRelease?: GitHubReleaseInfo;
   updatedAt?: string;
 }
+
+/** Rate limit of one GitHub API resource */
+export interface GitHubRateLimit {
+  resource: string;
+  limit: number;
+  remaining: number;
+  resetAt: string;
+}
+
+/** Known GitHub API budget */
+export interface GitHubBudget {
+  authenticated: boolean;
+  limits: GitHubRateLimit[];
+  /** Set while GitHub asked to back off after a secondary rate limit */
+  blockedUntil?: string;
+}
@@ -5,6 +5,30 @@ export interface AddonIndex {
   addon_count: number;
   addons: IndexAddon[];
   fetched_at?: string;
+  /** Where this copy came from: downloaded, revalidated via 304, or served from cache */
+  fetch_source?: 'network' | 'revalidated' | 'cache';
+  /** Sources that could not be loaded into the merged catalog */
+  source_errors?: string[];
+}
+
+/** A configured addon index source */
+export interface IndexSource {
+  id: number;
+  name: string;
+  url: string;
+  /** Lower value wins when two sources carry the same slug */
+  priority: number;
+  addedAt: string;
+  /** Minisign public key; when set the index must carry a valid detached signature */
+  publicKey?: string;
+}
+
+/** Origin of an addon entry in the merged catalog */
+export interface IndexProvenance {
+  source_id: number;
+  source_name: string;
+  /** Lower-priority sources that also listed this slug and were overridden */
+  overridden_sources: string[];
 }
 
 /** A download source for an addon (jsDelivr CDN or GitHub archive) */
@@ -35,6 +59,8 @@ export interface IndexAddon {
   download_sources?: DownloadSource[];
   /** ISO 8601 timestamp of when the addon was last updated */
   last_updated?: string;
+  /** Which configured index source provided this entry */
+  provenance?: IndexProvenance;
 }
 
 /** Source repository information */
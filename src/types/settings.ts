@@ -4,5 +4,28 @@ export interface AppSettings {
   checkUpdatesOnStartup: boolean;
   autoUpdate: boolean;
   theme: 'system' | 'light' | 'dark';
-  indexUrl?: string;
+  /** Number of previous installs to keep per addon for rollback */
+  historyRetention?: number;
+  /** Number of queued downloads that run at the same time (applies after restart) */
+  maxConcurrentDownloads?: number;
+  /** HTTP, HTTPS or SOCKS5 proxy for all network requests (e.g. socks5://host:1080) */
+  proxyUrl?: string;
+  /** PEM file with additional root certificates to trust */
+  caCertificatePath?: string;
+  /** Seconds allowed to establish a connection */
+  connectTimeoutSecs?: number;
+  /** Seconds allowed between reads before a request times out */
+  readTimeoutSecs?: number;
+  /** Size limit of the downloaded archive cache in megabytes */
+  cacheMaxSizeMb?: number;
+  /** Install only from the archive cache, without downloading */
+  offlineMode?: boolean;
+  /** Combined download rate of user-initiated installs in KiB/s */
+  downloadRateLimitKib?: number;
+  /** Rate of each user-initiated download in KiB/s */
+  downloadRateLimitPerDownloadKib?: number;
+  /** Combined download rate of automatic and bulk updates in KiB/s */
+  backgroundRateLimitKib?: number;
+  /** Rate of each automatic or bulk update download in KiB/s */
+  backgroundRateLimitPerDownloadKib?: number;
 }